@@ -6,7 +6,7 @@ use std::collections::HashMap;
 
 use std::fmt::Write;
 
-use crate::codegen_fuzzing::CodegenFuzzer;
+use crate::codegen_fuzzing::{CodegenFuzzer, read_serialized_values};
 use crate::rand::Rand;
 
 use crate::compilation_config::GenCodeFuzzMode;
@@ -14,9 +14,9 @@ use crate::compilation_config::GenCodeFuzzMode;
 use crate::arm_codegen_ctx::ARMSIMDCodegenCtx;
 use crate::arm_codegen_ctx::{generate_arm_codegen_ctx, generate_cpp_code_from_arm_codegen_ctx};
 
-use crate::exec_mem::ExecPage;
+use crate::exec_mem::{ExecPage, ExecCrash, ExecUnavailable};
 
-use crate::code_exe_server_conn::{CodeExeAndInput, CodeExeServClient};
+use crate::code_exe_server_conn::{CodeExeAndInput, CodeObjAndInput, CodeExeServClient, CodeExeReply};
 
 // :(
 use crate::x86_intrinsics::AlignedWrapper;
@@ -45,7 +45,7 @@ pub struct ARMCodegenFuzzerThreadInput {
 pub struct ARMCodegenFuzzer {
 	type_to_intrinsics_map : HashMap<ARMSIMDType, Vec<ARMSIMDIntrinsic>>,
 	all_intrinsic_return_types : Vec<ARMSIMDType>,
-	outer_rng : Rand,
+	thread_seed : u64,
 	code_exe_serv : Option<CodeExeServClient>
 }
 
@@ -72,7 +72,7 @@ impl ARMCodeFuzzerInputValues {
 			write!(out_str, "{} ", f_val).expect("");
 		}
 		
-		write!(out_str, "{}\n", self.d_vals.len()).expect("");
+		write!(out_str, "\n{}\n", self.d_vals.len()).expect("");
 		for d_val in self.d_vals.iter() {
 			write!(out_str, "{} ", d_val).expect("");
 		}
@@ -81,19 +81,15 @@ impl ARMCodeFuzzerInputValues {
 	}
 	
 	pub fn read_from_str(serial : &str) -> Self {
-		let mut lines = serial.split('\n');
-		let num_i_vals = lines.next().unwrap().parse::<usize>().unwrap();
-		
-		let i_vals_iter = lines.next().unwrap().trim().split(' ');
-		let mut i_vals = Vec::with_capacity(num_i_vals);
-		for i_val in i_vals_iter {
-			i_vals.push(i_val.parse::<i32>().unwrap());
-		}
+		let mut lines = serial.lines();
+		let i_vals = read_serialized_values::<i32>(&mut lines);
+		let f_vals = read_serialized_values::<f32>(&mut lines);
+		let d_vals = read_serialized_values::<f64>(&mut lines);
 		
 		Self {
 			i_vals: i_vals,
-			f_vals: Vec::new(), // TODO
-			d_vals: Vec::new(), // TODO
+			f_vals: f_vals,
+			d_vals: d_vals,
 		}
 	}
 }
@@ -180,8 +176,8 @@ fn minimize_gen_arm_code<F: Fn(&ARMCodegenFuzzer, &ARMSIMDCodegenCtx) -> bool>(f
 	return best_ctx.clone();
 }
 
-fn generate_random_input_for_program(num_i_vals : usize, num_f_vals : usize, num_d_vals : usize) -> ARMCodeFuzzerInputValues {
-	let mut rng = Rand::default();
+fn generate_random_input_for_program(num_i_vals : usize, num_f_vals : usize, num_d_vals : usize, input_seed : u64) -> ARMCodeFuzzerInputValues {
+	let mut rng = Rand::new(input_seed);
 
 	let mut i_vals = Vec::<i32>::with_capacity(num_i_vals);
 	for _ in 0..num_i_vals {
@@ -274,7 +270,7 @@ fn decode_simd_count(encoded_count : u32) -> i32 {
 	}
 }
 
-fn encode_return_type(return_type : ARMSIMDType) -> u32 {
+pub fn encode_return_type(return_type : ARMSIMDType) -> u32 {
 	match return_type {
 		ARMSIMDType::Primitive(base_type) => {
 			let (core_type, ln2_bits) = base_type_to_core_type_and_ln2_bits(base_type);
@@ -292,7 +288,7 @@ fn encode_return_type(return_type : ARMSIMDType) -> u32 {
 	}
 }
 
-fn decode_return_type(return_type : u32) -> ARMSIMDType {
+pub fn decode_return_type(return_type : u32) -> ARMSIMDType {
 	let core_type = return_type & 0b11;
 	let ln2_bits = (return_type >> 2) & 0b111;
 	let encoded_simd_count = (return_type >> 5) & 0b111;
@@ -314,11 +310,10 @@ fn decode_return_type(return_type : u32) -> ARMSIMDType {
 	}
 }
 
-#[cfg(target_arch = "aarch64")]
 fn execute_simd_code_with_return_type<T : std::fmt::Debug>(exec_page : &ExecPage, input : &ARMCodeFuzzerInputValues) -> ARMSIMDOutputValues {
 
 	// Get the function, casting to proper return type
-	let func_ptr = unsafe { exec_page.page.as_ptr().add(exec_page.func_offset) };
+	let func_ptr = exec_page.get_func_ptr();
 	let func: unsafe extern "C" fn(*const i32, *const f32, *const f64) -> T = unsafe { std::mem::transmute(func_ptr) };
 
 	let ret = unsafe {
@@ -339,6 +334,170 @@ fn execute_simd_code_with_return_type<T : std::fmt::Debug>(exec_page : &ExecPage
 	}
 }
 
+// Calls do_stuff with the return type it actually has, or gives back None if it's not one we can call.
+// Off of ARM that's everything but the primitive ints, which are only there so the exe server can be tried out with host code
+pub fn execute_with_return_type(exec_page : &ExecPage, return_type : ARMSIMDType, input : &ARMCodeFuzzerInputValues) -> Option<ARMSIMDOutputValues> {
+	let output = match return_type {
+		ARMSIMDType::Primitive(ARMBaseType::Int8)  => execute_simd_code_with_return_type::<i8>( exec_page, input),
+		ARMSIMDType::Primitive(ARMBaseType::Int16) => execute_simd_code_with_return_type::<i16>(exec_page, input),
+		ARMSIMDType::Primitive(ARMBaseType::Int32) => execute_simd_code_with_return_type::<i32>(exec_page, input),
+		ARMSIMDType::Primitive(ARMBaseType::Int64) => execute_simd_code_with_return_type::<i64>(exec_page, input),
+
+		ARMSIMDType::Primitive(ARMBaseType::UInt8)  => execute_simd_code_with_return_type::<u8>( exec_page, input),
+		ARMSIMDType::Primitive(ARMBaseType::UInt16) => execute_simd_code_with_return_type::<u16>(exec_page, input),
+		ARMSIMDType::Primitive(ARMBaseType::UInt32) => execute_simd_code_with_return_type::<u32>(exec_page, input),
+		ARMSIMDType::Primitive(ARMBaseType::UInt64) => execute_simd_code_with_return_type::<u64>(exec_page, input),
+
+		#[cfg(target_arch = "aarch64")]
+		_ => execute_simd_vector_code(exec_page, return_type, input)?,
+		#[cfg(not(target_arch = "aarch64"))]
+		_ => return None
+	};
+
+	return Some(output);
+}
+
+#[cfg(target_arch = "aarch64")]
+fn execute_simd_vector_code(exec_page : &ExecPage, return_type : ARMSIMDType, input : &ARMCodeFuzzerInputValues) -> Option<ARMSIMDOutputValues> {
+	let output = match return_type {
+		ARMSIMDType::SIMD(ARMBaseType::Int8, 8)  => execute_simd_code_with_return_type::<aarch64::int8x8_t>( exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int8, 16) => execute_simd_code_with_return_type::<aarch64::int8x16_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int16, 4) => execute_simd_code_with_return_type::<aarch64::int16x4_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int16, 8) => execute_simd_code_with_return_type::<aarch64::int16x8_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int32, 2) => execute_simd_code_with_return_type::<aarch64::int32x2_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int32, 4) => execute_simd_code_with_return_type::<aarch64::int32x4_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int64, 1) => execute_simd_code_with_return_type::<aarch64::int64x1_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::Int64, 2) => execute_simd_code_with_return_type::<aarch64::int64x2_t>(exec_page, input),
+
+		ARMSIMDType::SIMD(ARMBaseType::UInt8, 8)  => execute_simd_code_with_return_type::<aarch64::uint8x8_t>( exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt8, 16) => execute_simd_code_with_return_type::<aarch64::uint8x16_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt16, 4) => execute_simd_code_with_return_type::<aarch64::uint16x4_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt16, 8) => execute_simd_code_with_return_type::<aarch64::uint16x8_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt32, 2) => execute_simd_code_with_return_type::<aarch64::uint32x2_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt32, 4) => execute_simd_code_with_return_type::<aarch64::uint32x4_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt64, 1) => execute_simd_code_with_return_type::<aarch64::uint64x1_t>(exec_page, input),
+		ARMSIMDType::SIMD(ARMBaseType::UInt64, 2) => execute_simd_code_with_return_type::<aarch64::uint64x2_t>(exec_page, input),
+
+		ARMSIMDType::SIMDArr(ARMBaseType::Int8,  8, 2) => execute_simd_code_with_return_type::<aarch64::int8x8x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int8,  16, 2) => execute_simd_code_with_return_type::<aarch64::int8x16x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  8, 2) => execute_simd_code_with_return_type::<aarch64::uint8x8x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  16, 2) => execute_simd_code_with_return_type::<aarch64::uint8x16x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int8,  8, 3) => execute_simd_code_with_return_type::<aarch64::int8x8x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int8,  16, 3) => execute_simd_code_with_return_type::<aarch64::int8x16x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  8, 3) => execute_simd_code_with_return_type::<aarch64::uint8x8x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  16, 3) => execute_simd_code_with_return_type::<aarch64::uint8x16x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int8,  8, 4) => execute_simd_code_with_return_type::<aarch64::int8x8x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int8,  16, 4) => execute_simd_code_with_return_type::<aarch64::int8x16x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  8, 4) => execute_simd_code_with_return_type::<aarch64::uint8x8x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  16, 4) => execute_simd_code_with_return_type::<aarch64::uint8x16x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int16,  4, 2) => execute_simd_code_with_return_type::<aarch64::int16x4x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int16,  8, 2) => execute_simd_code_with_return_type::<aarch64::int16x8x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  4, 2) => execute_simd_code_with_return_type::<aarch64::uint16x4x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  8, 2) => execute_simd_code_with_return_type::<aarch64::uint16x8x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int16,  4, 3) => execute_simd_code_with_return_type::<aarch64::int16x4x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int16,  8, 3) => execute_simd_code_with_return_type::<aarch64::int16x8x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  4, 3) => execute_simd_code_with_return_type::<aarch64::uint16x4x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  8, 3) => execute_simd_code_with_return_type::<aarch64::uint16x8x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int16,  4, 4) => execute_simd_code_with_return_type::<aarch64::int16x4x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int16,  8, 4) => execute_simd_code_with_return_type::<aarch64::int16x8x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  4, 4) => execute_simd_code_with_return_type::<aarch64::uint16x4x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  8, 4) => execute_simd_code_with_return_type::<aarch64::uint16x8x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int32,  2, 2) => execute_simd_code_with_return_type::<aarch64::int32x2x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int32,  4, 2) => execute_simd_code_with_return_type::<aarch64::int32x4x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  2, 2) => execute_simd_code_with_return_type::<aarch64::uint32x2x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  4, 2) => execute_simd_code_with_return_type::<aarch64::uint32x4x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int32,  2, 3) => execute_simd_code_with_return_type::<aarch64::int32x2x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int32,  4, 3) => execute_simd_code_with_return_type::<aarch64::int32x4x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  2, 3) => execute_simd_code_with_return_type::<aarch64::uint32x2x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  4, 3) => execute_simd_code_with_return_type::<aarch64::uint32x4x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int32,  2, 4) => execute_simd_code_with_return_type::<aarch64::int32x2x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int32,  4, 4) => execute_simd_code_with_return_type::<aarch64::int32x4x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  2, 4) => execute_simd_code_with_return_type::<aarch64::uint32x2x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  4, 4) => execute_simd_code_with_return_type::<aarch64::uint32x4x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int64,  1, 2) => execute_simd_code_with_return_type::<aarch64::int64x1x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int64,  2, 2) => execute_simd_code_with_return_type::<aarch64::int64x2x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  1, 2) => execute_simd_code_with_return_type::<aarch64::uint64x1x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  2, 2) => execute_simd_code_with_return_type::<aarch64::uint64x2x2_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int64,  1, 3) => execute_simd_code_with_return_type::<aarch64::int64x1x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int64,  2, 3) => execute_simd_code_with_return_type::<aarch64::int64x2x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  1, 3) => execute_simd_code_with_return_type::<aarch64::uint64x1x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  2, 3) => execute_simd_code_with_return_type::<aarch64::uint64x2x3_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int64,  1, 4) => execute_simd_code_with_return_type::<aarch64::int64x1x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::Int64,  2, 4) => execute_simd_code_with_return_type::<aarch64::int64x2x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  1, 4) => execute_simd_code_with_return_type::<aarch64::uint64x1x4_t>(exec_page, input),
+		ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  2, 4) => execute_simd_code_with_return_type::<aarch64::uint64x2x4_t>(exec_page, input),
+
+		_ => return None
+	};
+
+	return Some(output);
+}
+
+// The code's already been compiled and relocated here, so if it doesn't care where it is, the exe server can just copy it in and run it.
+// Otherwise the server gets the object as the compiler wrote it, and links it for itself
+fn execute_on_exe_server(code_exe_serv : &CodeExeServClient, exec_page : &ExecPage, return_type : ARMSIMDType, input : &ARMCodeFuzzerInputValues) -> ARMSIMDOutputValues {
+	assert!(exec_page.get_external_code().is_none(), "can't send external code to the exe server, it needs to be loaded from an object");
+
+	let send_res = if exec_page.needs_server_link {
+		assert!(exec_page.get_obj_data().len() > 0, "exe server needs the object the code was loaded from");
+		code_exe_serv.send_obj_and_input(&CodeObjAndInput {
+			obj_data: exec_page.get_obj_data(),
+			i_vals: &input.i_vals[..],
+			f_vals: &input.f_vals[..],
+			d_vals: &input.d_vals[..],
+			return_type: encode_return_type(return_type)
+		})
+	}
+	else {
+		code_exe_serv.send_exe_and_input(&CodeExeAndInput {
+			code_bytes: exec_page.get_bytes(),
+			func_offset: exec_page.get_func_offset() as u32,
+			i_vals: &input.i_vals[..],
+			f_vals: &input.f_vals[..],
+			d_vals: &input.d_vals[..],
+			return_type: encode_return_type(return_type)
+		}).map(|ret_bytes| CodeExeReply::Output(ret_bytes))
+	};
+
+	// Both of these unwind with resume_unwind, so they skip the panic hook
+	let ret_bytes = match send_res {
+		Ok(CodeExeReply::Output(ret_bytes)) => ret_bytes,
+		// Same as a shared lib taking down its worker, the driver counts it as a diff
+		Ok(CodeExeReply::Crashed(err)) => {
+			print!("EXE SERVER CRASH: {}\n", err);
+			std::panic::resume_unwind(Box::new(ExecCrash(err)));
+		}
+		Ok(CodeExeReply::Error(err)) => {
+			panic!("exe server could not run code returning {:?}: {}", return_type, err);
+		}
+		// It's already had its one reconnect, so the driver stops the run
+		Err(err) => {
+			let err = format!("lost connection to exe server: {}", err);
+			print!("EXE SERVER ERR: {}\n", err);
+			std::panic::resume_unwind(Box::new(ExecUnavailable(err)));
+		}
+	};
+
+	// Nothing we can call returns nothing, so that's how a 0x66 reply says it couldn't run it. The server's a different process,
+	// so that means it crashed (or it's not a return type it can call, which the server logs)
+	if ret_bytes.len() == 0 {
+		let err = format!("exe server sent back nothing for code returning {:?}", return_type);
+		print!("EXE SERVER CRASH: {}\n", err);
+		std::panic::resume_unwind(Box::new(ExecCrash(err)));
+	}
+	if ret_bytes.len() > 64 {
+		panic!("exe server sent back {} bytes for code returning {:?}", ret_bytes.len(), return_type);
+	}
+
+	let mut output_bytes = [0u8 ; 64];
+	output_bytes[..ret_bytes.len()].copy_from_slice(&ret_bytes[..]);
+
+	ARMSIMDOutputValues {
+		output_bytes : output_bytes,
+		output_len : ret_bytes.len()
+	}
+}
+
 impl CodegenFuzzer<ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues> for ARMCodegenFuzzer {
 	// Each of these will go on a thread, can contain inputs like
 	// a parsed spec data, seed, flags, config, etc.
@@ -347,20 +506,27 @@ impl CodegenFuzzer<ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuz
 		for (ret_type, _) in input_data.type_to_intrinsics_map.iter() {
 			all_intrinsic_return_types.push(*ret_type);
 		}
+		// The map's order changes every process, and the seed picks from this by index
+		all_intrinsic_return_types.sort();
 
-		let needs_exe_server = false;//(input_data.mode == GenCodeFuzzMode::CrashAndDiff);
+		// Only worth connecting if we're going to run anything
+		let needs_exe_server = !input_data.connect_addr.is_empty() && input_data.mode == GenCodeFuzzMode::CrashAndDiff;
 
 		ARMCodegenFuzzer {
 			type_to_intrinsics_map: input_data.type_to_intrinsics_map,
 			all_intrinsic_return_types: all_intrinsic_return_types,
-			outer_rng: Rand::new(input_data.thread_seed),
+			thread_seed: input_data.thread_seed,
 			code_exe_serv: if needs_exe_server { Some(CodeExeServClient::new(&input_data.connect_addr)) } else { None }
 		}
 	}
 
+	fn get_thread_seed(&self) -> u64 {
+		return self.thread_seed;
+	}
+
 	// This generates some context struct that's basically analagous to the AST
-	fn generate_ctx(&mut self) -> Self::CodegenCtx {
-		let mut codegen_ctx = Self::CodegenCtx::new(self.outer_rng.rand_u64());
+	fn generate_ctx(&mut self, ctx_seed : u64) -> Self::CodegenCtx {
+		let mut codegen_ctx = Self::CodegenCtx::new(ctx_seed);
 		generate_arm_codegen_ctx(&mut codegen_ctx, &self.type_to_intrinsics_map, &self.all_intrinsic_return_types);
 		return codegen_ctx;
 	}
@@ -377,8 +543,8 @@ impl CodegenFuzzer<ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuz
 		return (cpp_code, meta_data);
 	}
 
-	fn generate_random_input(&self, code_meta : &Self::CodeMeta) -> Self::FuzzerInput {
-		return generate_random_input_for_program(code_meta.num_i_vals, code_meta.num_f_vals, code_meta.num_d_vals);
+	fn generate_random_input(&self, code_meta : &Self::CodeMeta, input_seed : u64) -> Self::FuzzerInput {
+		return generate_random_input_for_program(code_meta.num_i_vals, code_meta.num_f_vals, code_meta.num_d_vals, input_seed);
 	}
 
 	// uhh.....idk
@@ -388,98 +554,18 @@ impl CodegenFuzzer<ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuz
 
 	// Actually execute it: this is probably like local, but 
 	fn execute(&self, exec_page : &ExecPage, code_meta: &Self::CodeMeta, input : &Self::FuzzerInput) -> Self::FuzzerOutput {
-		
-		//dbg!(&code_meta.return_type);
+		if let Some(code_exe_serv) = &self.code_exe_serv {
+			return execute_on_exe_server(code_exe_serv, exec_page, code_meta.return_type, input);
+		}
 
-		//dbg!(execute_simd_code_with_return_type::<i8>(exec_page, input));
-		
 		#[cfg(target_arch = "aarch64")]
 		{
-			match code_meta.return_type {
-				ARMSIMDType::Primitive(ARMBaseType::Int8)  => { execute_simd_code_with_return_type::<i8>( exec_page, input) }
-				ARMSIMDType::Primitive(ARMBaseType::Int16) => { execute_simd_code_with_return_type::<i16>(exec_page, input) }
-				ARMSIMDType::Primitive(ARMBaseType::Int32) => { execute_simd_code_with_return_type::<i32>(exec_page, input) }
-				ARMSIMDType::Primitive(ARMBaseType::Int64) => { execute_simd_code_with_return_type::<i64>(exec_page, input) }
-
-				ARMSIMDType::Primitive(ARMBaseType::UInt8)  => { execute_simd_code_with_return_type::<u8>( exec_page, input) }
-				ARMSIMDType::Primitive(ARMBaseType::UInt16) => { execute_simd_code_with_return_type::<u16>(exec_page, input) }
-				ARMSIMDType::Primitive(ARMBaseType::UInt32) => { execute_simd_code_with_return_type::<u32>(exec_page, input) }
-				ARMSIMDType::Primitive(ARMBaseType::UInt64) => { execute_simd_code_with_return_type::<u64>(exec_page, input) }
-
-				ARMSIMDType::SIMD(ARMBaseType::Int8, 8)  => { execute_simd_code_with_return_type::<aarch64::int8x8_t>( exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int8, 16) => { execute_simd_code_with_return_type::<aarch64::int8x16_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int16, 4) => { execute_simd_code_with_return_type::<aarch64::int16x4_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int16, 8) => { execute_simd_code_with_return_type::<aarch64::int16x8_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int32, 2) => { execute_simd_code_with_return_type::<aarch64::int32x2_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int32, 4) => { execute_simd_code_with_return_type::<aarch64::int32x4_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int64, 1) => { execute_simd_code_with_return_type::<aarch64::int64x1_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::Int64, 2) => { execute_simd_code_with_return_type::<aarch64::int64x2_t>(exec_page, input) }
-
-				ARMSIMDType::SIMD(ARMBaseType::UInt8, 8)  => { execute_simd_code_with_return_type::<aarch64::uint8x8_t>( exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt8, 16) => { execute_simd_code_with_return_type::<aarch64::uint8x16_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt16, 4) => { execute_simd_code_with_return_type::<aarch64::uint16x4_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt16, 8) => { execute_simd_code_with_return_type::<aarch64::uint16x8_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt32, 2) => { execute_simd_code_with_return_type::<aarch64::uint32x2_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt32, 4) => { execute_simd_code_with_return_type::<aarch64::uint32x4_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt64, 1) => { execute_simd_code_with_return_type::<aarch64::uint64x1_t>(exec_page, input) }
-				ARMSIMDType::SIMD(ARMBaseType::UInt64, 2) => { execute_simd_code_with_return_type::<aarch64::uint64x2_t>(exec_page, input) }
-
-				ARMSIMDType::SIMDArr(ARMBaseType::Int8,  8, 2) => { execute_simd_code_with_return_type::<aarch64::int8x8x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int8,  16, 2) => { execute_simd_code_with_return_type::<aarch64::int8x16x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  8, 2) => { execute_simd_code_with_return_type::<aarch64::uint8x8x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  16, 2) => { execute_simd_code_with_return_type::<aarch64::uint8x16x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int8,  8, 3) => { execute_simd_code_with_return_type::<aarch64::int8x8x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int8,  16, 3) => { execute_simd_code_with_return_type::<aarch64::int8x16x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  8, 3) => { execute_simd_code_with_return_type::<aarch64::uint8x8x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  16, 3) => { execute_simd_code_with_return_type::<aarch64::uint8x16x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int8,  8, 4) => { execute_simd_code_with_return_type::<aarch64::int8x8x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int8,  16, 4) => { execute_simd_code_with_return_type::<aarch64::int8x16x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  8, 4) => { execute_simd_code_with_return_type::<aarch64::uint8x8x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt8,  16, 4) => { execute_simd_code_with_return_type::<aarch64::uint8x16x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int16,  4, 2) => { execute_simd_code_with_return_type::<aarch64::int16x4x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int16,  8, 2) => { execute_simd_code_with_return_type::<aarch64::int16x8x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  4, 2) => { execute_simd_code_with_return_type::<aarch64::uint16x4x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  8, 2) => { execute_simd_code_with_return_type::<aarch64::uint16x8x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int16,  4, 3) => { execute_simd_code_with_return_type::<aarch64::int16x4x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int16,  8, 3) => { execute_simd_code_with_return_type::<aarch64::int16x8x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  4, 3) => { execute_simd_code_with_return_type::<aarch64::uint16x4x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  8, 3) => { execute_simd_code_with_return_type::<aarch64::uint16x8x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int16,  4, 4) => { execute_simd_code_with_return_type::<aarch64::int16x4x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int16,  8, 4) => { execute_simd_code_with_return_type::<aarch64::int16x8x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  4, 4) => { execute_simd_code_with_return_type::<aarch64::uint16x4x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt16,  8, 4) => { execute_simd_code_with_return_type::<aarch64::uint16x8x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int32,  2, 2) => { execute_simd_code_with_return_type::<aarch64::int32x2x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int32,  4, 2) => { execute_simd_code_with_return_type::<aarch64::int32x4x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  2, 2) => { execute_simd_code_with_return_type::<aarch64::uint32x2x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  4, 2) => { execute_simd_code_with_return_type::<aarch64::uint32x4x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int32,  2, 3) => { execute_simd_code_with_return_type::<aarch64::int32x2x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int32,  4, 3) => { execute_simd_code_with_return_type::<aarch64::int32x4x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  2, 3) => { execute_simd_code_with_return_type::<aarch64::uint32x2x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  4, 3) => { execute_simd_code_with_return_type::<aarch64::uint32x4x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int32,  2, 4) => { execute_simd_code_with_return_type::<aarch64::int32x2x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int32,  4, 4) => { execute_simd_code_with_return_type::<aarch64::int32x4x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  2, 4) => { execute_simd_code_with_return_type::<aarch64::uint32x2x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt32,  4, 4) => { execute_simd_code_with_return_type::<aarch64::uint32x4x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int64,  1, 2) => { execute_simd_code_with_return_type::<aarch64::int64x1x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int64,  2, 2) => { execute_simd_code_with_return_type::<aarch64::int64x2x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  1, 2) => { execute_simd_code_with_return_type::<aarch64::uint64x1x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  2, 2) => { execute_simd_code_with_return_type::<aarch64::uint64x2x2_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int64,  1, 3) => { execute_simd_code_with_return_type::<aarch64::int64x1x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int64,  2, 3) => { execute_simd_code_with_return_type::<aarch64::int64x2x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  1, 3) => { execute_simd_code_with_return_type::<aarch64::uint64x1x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  2, 3) => { execute_simd_code_with_return_type::<aarch64::uint64x2x3_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int64,  1, 4) => { execute_simd_code_with_return_type::<aarch64::int64x1x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::Int64,  2, 4) => { execute_simd_code_with_return_type::<aarch64::int64x2x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  1, 4) => { execute_simd_code_with_return_type::<aarch64::uint64x1x4_t>(exec_page, input) }
-				ARMSIMDType::SIMDArr(ARMBaseType::UInt64,  2, 4) => { execute_simd_code_with_return_type::<aarch64::uint64x2x4_t>(exec_page, input) }
-
-				_ => panic!("unsupported return type {:?}", code_meta.return_type)
-			}
+			return execute_with_return_type(exec_page, code_meta.return_type, input).unwrap_or_else(|| panic!("unsupported return type {:?}", code_meta.return_type));
 		}
 		
 		#[cfg(not(target_arch = "aarch64"))]
 		{
-			panic!("Cannot fuzz ARM code on non-ARM platform");
+			panic!("Cannot fuzz ARM code on non-ARM platform, set extra_config.exe_server to run it on one");
 		}
 	}
 
@@ -530,5 +616,40 @@ impl CodegenFuzzer<ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuz
 	}
 }
 
+#[test]
+fn test_same_seed_gives_same_code() {
+	let intrinsics = [
+		("vaddq_s32", "int32x4_t", vec!["int32x4_t", "int32x4_t"]),
+		("vmulq_u16", "uint16x8_t", vec!["uint16x8_t", "uint16x8_t"]),
+		("vget_low_s32", "int32x2_t", vec!["int32x4_t"]),
+		("vmovl_u8", "uint16x8_t", vec!["uint8x8_t"]),
+		("vaddv_s32", "int32_t", vec!["int32x2_t"])
+	];
+
+	// Built separately, so each map gets its own random iteration order
+	let make_fuzzer = || {
+		let mut type_to_intrinsics_map = HashMap::<ARMSIMDType, Vec<ARMSIMDIntrinsic>>::new();
+		for (name, return_type, param_types) in intrinsics.iter() {
+			type_to_intrinsics_map.entry(parse_arm_simd_type(return_type)).or_insert_with(Vec::new).push(ARMSIMDIntrinsic {
+				intrinsic_name: name.to_string(),
+				return_type: parse_arm_simd_type(return_type),
+				param_types: param_types.iter().map(|param_type| parse_arm_simd_type(param_type)).collect()
+			});
+		}
 
+		ARMCodegenFuzzer::new_fuzzer_state(ARMCodegenFuzzerThreadInput {
+			thread_seed: 0,
+			type_to_intrinsics_map: type_to_intrinsics_map,
+			mode: GenCodeFuzzMode::CrashOnly,
+			connect_addr: "".to_string()
+		})
+	};
 
+	for ctx_seed in 0..20 {
+		let mut fuzzer_1 = make_fuzzer();
+		let mut fuzzer_2 = make_fuzzer();
+		let ctx_1 = fuzzer_1.generate_ctx(ctx_seed);
+		let ctx_2 = fuzzer_2.generate_ctx(ctx_seed);
+		assert_eq!(fuzzer_1.generate_cpp_code(&ctx_1).0, fuzzer_2.generate_cpp_code(&ctx_2).0);
+	}
+}
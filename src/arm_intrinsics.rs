@@ -1,7 +1,7 @@
 
 use std::fmt::Write;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum ARMBaseType {
 	Void,
 	Int8,
@@ -23,7 +23,7 @@ pub enum ARMBaseType {
 	Poly128
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum ARMSIMDType {
 	Primitive(ARMBaseType),
 	ConstantIntImmediate(i32, i32), // The valid range (min, max) and for now only integers allowed
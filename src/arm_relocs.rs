@@ -0,0 +1,418 @@
+// AArch64 ELF relocations, see https://github.com/ARM-software/abi-aa/blob/main/aaelf64/aaelf64.rst
+// Mach-O's handful of ARM64_RELOC_* get mapped onto the same thing.
+// Everything gets its range checked before it's patched in, since a truncated immediate just silently branches/loads somewhere else
+
+use object::{Object, ObjectSection, RelocationKind, RelocationEncoding, RelocationTarget};
+use object::read::SectionIndex;
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use crate::exec_mem::{ExecPage, DataLayout};
+use crate::obj_linker::{ObjLinker, RelocTarget, get_reloc_target, get_reloc_target_name, read_implicit_addend, should_relocate_section};
+use crate::parse_exe::LoadError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AArch64Reloc {
+	Abs64,
+	Abs32,
+	Abs16,
+	Prel64,
+	Prel32,
+	Prel16,
+	// movz/movk of each 16 bits of an absolute address, the _NC ones don't check for overflow
+	MovwUabsG0,
+	MovwUabsG0Nc,
+	MovwUabsG1,
+	MovwUabsG1Nc,
+	MovwUabsG2,
+	MovwUabsG2Nc,
+	MovwUabsG3,
+	LdPrelLo19,
+	AdrPrelLo21,
+	AdrPrelPgHi21,
+	AdrPrelPgHi21Nc,
+	AddAbsLo12Nc,
+	Ldst8AbsLo12Nc,
+	Ldst16AbsLo12Nc,
+	Ldst32AbsLo12Nc,
+	Ldst64AbsLo12Nc,
+	Ldst128AbsLo12Nc,
+	Tstbr14,
+	Condbr19,
+	Jump26,
+	Call26,
+	GotLdPrel19,
+	AdrGotPage,
+	Ld64GotLo12Nc,
+	// Mach-O's PAGEOFF12, which is an add or any size of load/store depending on the instruction. See resolve_page_offset
+	PageOff12
+}
+
+impl AArch64Reloc {
+	// object already turns some of these into generic kinds, so we have to go back the other way for those
+	pub fn from_relocation(reloc : &object::Relocation) -> Option<AArch64Reloc> {
+		match (reloc.kind(), reloc.size()) {
+			(RelocationKind::Absolute, 64) => Some(AArch64Reloc::Abs64),
+			(RelocationKind::Absolute, 32) => Some(AArch64Reloc::Abs32),
+			(RelocationKind::Absolute, 16) => Some(AArch64Reloc::Abs16),
+			(RelocationKind::Relative, 64) => Some(AArch64Reloc::Prel64),
+			(RelocationKind::Relative, 32) => Some(AArch64Reloc::Prel32),
+			(RelocationKind::Relative, 16) => Some(AArch64Reloc::Prel16),
+			(RelocationKind::PltRelative, 26) if reloc.encoding() == RelocationEncoding::AArch64Call => Some(AArch64Reloc::Call26),
+			(RelocationKind::Elf(r_type), _) => AArch64Reloc::from_elf_type(r_type),
+			(RelocationKind::MachO { value, .. }, _) => AArch64Reloc::from_macho_type(value),
+			_ => None
+		}
+	}
+
+	pub fn from_elf_type(r_type : u32) -> Option<AArch64Reloc> {
+		use object::elf::*;
+		match r_type {
+			R_AARCH64_ABS64 => Some(AArch64Reloc::Abs64),
+			R_AARCH64_ABS32 => Some(AArch64Reloc::Abs32),
+			R_AARCH64_ABS16 => Some(AArch64Reloc::Abs16),
+			R_AARCH64_PREL64 => Some(AArch64Reloc::Prel64),
+			R_AARCH64_PREL32 => Some(AArch64Reloc::Prel32),
+			R_AARCH64_PREL16 => Some(AArch64Reloc::Prel16),
+			R_AARCH64_MOVW_UABS_G0 => Some(AArch64Reloc::MovwUabsG0),
+			R_AARCH64_MOVW_UABS_G0_NC => Some(AArch64Reloc::MovwUabsG0Nc),
+			R_AARCH64_MOVW_UABS_G1 => Some(AArch64Reloc::MovwUabsG1),
+			R_AARCH64_MOVW_UABS_G1_NC => Some(AArch64Reloc::MovwUabsG1Nc),
+			R_AARCH64_MOVW_UABS_G2 => Some(AArch64Reloc::MovwUabsG2),
+			R_AARCH64_MOVW_UABS_G2_NC => Some(AArch64Reloc::MovwUabsG2Nc),
+			R_AARCH64_MOVW_UABS_G3 => Some(AArch64Reloc::MovwUabsG3),
+			R_AARCH64_LD_PREL_LO19 => Some(AArch64Reloc::LdPrelLo19),
+			R_AARCH64_ADR_PREL_LO21 => Some(AArch64Reloc::AdrPrelLo21),
+			R_AARCH64_ADR_PREL_PG_HI21 => Some(AArch64Reloc::AdrPrelPgHi21),
+			R_AARCH64_ADR_PREL_PG_HI21_NC => Some(AArch64Reloc::AdrPrelPgHi21Nc),
+			R_AARCH64_ADD_ABS_LO12_NC => Some(AArch64Reloc::AddAbsLo12Nc),
+			R_AARCH64_LDST8_ABS_LO12_NC => Some(AArch64Reloc::Ldst8AbsLo12Nc),
+			R_AARCH64_LDST16_ABS_LO12_NC => Some(AArch64Reloc::Ldst16AbsLo12Nc),
+			R_AARCH64_LDST32_ABS_LO12_NC => Some(AArch64Reloc::Ldst32AbsLo12Nc),
+			R_AARCH64_LDST64_ABS_LO12_NC => Some(AArch64Reloc::Ldst64AbsLo12Nc),
+			R_AARCH64_LDST128_ABS_LO12_NC => Some(AArch64Reloc::Ldst128AbsLo12Nc),
+			R_AARCH64_TSTBR14 => Some(AArch64Reloc::Tstbr14),
+			R_AARCH64_CONDBR19 => Some(AArch64Reloc::Condbr19),
+			R_AARCH64_JUMP26 => Some(AArch64Reloc::Jump26),
+			R_AARCH64_CALL26 => Some(AArch64Reloc::Call26),
+			R_AARCH64_GOT_LD_PREL19 => Some(AArch64Reloc::GotLdPrel19),
+			R_AARCH64_ADR_GOT_PAGE => Some(AArch64Reloc::AdrGotPage),
+			R_AARCH64_LD64_GOT_LO12_NC => Some(AArch64Reloc::Ld64GotLo12Nc),
+			_ => None
+		}
+	}
+
+	// The addends come in a separate ARM64_RELOC_ADDEND, so that one isn't here
+	pub fn from_macho_type(r_type : u8) -> Option<AArch64Reloc> {
+		use object::macho::*;
+		match r_type {
+			ARM64_RELOC_BRANCH26 => Some(AArch64Reloc::Call26),
+			ARM64_RELOC_PAGE21 => Some(AArch64Reloc::AdrPrelPgHi21),
+			ARM64_RELOC_PAGEOFF12 => Some(AArch64Reloc::PageOff12),
+			ARM64_RELOC_GOT_LOAD_PAGE21 => Some(AArch64Reloc::AdrGotPage),
+			ARM64_RELOC_GOT_LOAD_PAGEOFF12 => Some(AArch64Reloc::Ld64GotLo12Nc),
+			_ => None
+		}
+	}
+
+	// Works out what a PageOff12 really is from the instruction it's patching, everything else is already specific enough
+	pub fn resolve_page_offset(&self, instruction : u32) -> Option<AArch64Reloc> {
+		if *self != AArch64Reloc::PageOff12 {
+			return Some(*self);
+		}
+
+		// add (immediate), 32 or 64-bit
+		if instruction & 0x7F80_0000 == 0x1100_0000 {
+			return Some(AArch64Reloc::AddAbsLo12Nc);
+		}
+
+		// ldr/str (unsigned offset): the size is the top 2 bits, except for q registers which are size 0 with V and opc[1] set
+		if instruction & 0x3B00_0000 == 0x3900_0000 {
+			let is_q_reg = (instruction & (1 << 26)) != 0 && (instruction & (1 << 23)) != 0;
+			return match (instruction >> 30, is_q_reg) {
+				(0, true) => Some(AArch64Reloc::Ldst128AbsLo12Nc),
+				(0, false) => Some(AArch64Reloc::Ldst8AbsLo12Nc),
+				(1, _) => Some(AArch64Reloc::Ldst16AbsLo12Nc),
+				(2, _) => Some(AArch64Reloc::Ldst32AbsLo12Nc),
+				_ => Some(AArch64Reloc::Ldst64AbsLo12Nc)
+			};
+		}
+
+		return None;
+	}
+
+	pub fn is_data(&self) -> bool {
+		return matches!(self, AArch64Reloc::Abs64 | AArch64Reloc::Abs32 | AArch64Reloc::Abs16 | AArch64Reloc::Prel64 | AArch64Reloc::Prel32 | AArch64Reloc::Prel16);
+	}
+
+	pub fn uses_got(&self) -> bool {
+		return matches!(self, AArch64Reloc::GotLdPrel19 | AArch64Reloc::AdrGotPage | AArch64Reloc::Ld64GotLo12Nc);
+	}
+
+	// Branches only reach +/-128MB, so anything going out to the host has to go through a PLT entry
+	pub fn is_branch(&self) -> bool {
+		return matches!(self, AArch64Reloc::Jump26 | AArch64Reloc::Call26);
+	}
+}
+
+// What actually gets written into the instruction (or data) once we've worked out the value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AArch64Patch {
+	Data { value : i64, size_bits : u32 },
+	// adr/adrp both split their immediate into immlo/immhi
+	AdrImmediate(i32),
+	AddImmediate(i32),
+	LdstOffset(i32),
+	ImmField { value : u32, lsb : u32, num_bits : u32 }
+}
+
+fn check_range(value : i64, min_value : i64, max_value : i64, reloc_type : AArch64Reloc) -> Result<(), LoadError> {
+	if value < min_value || value > max_value {
+		return Err(LoadError::BadRelocationValue(format!("{:#x} out of range [{:#x}, {:#x}] for {:?}", value, min_value, max_value, reloc_type)));
+	}
+
+	return Ok(());
+}
+
+fn check_alignment(value : i64, alignment : i64, reloc_type : AArch64Reloc) -> Result<(), LoadError> {
+	if value % alignment != 0 {
+		return Err(LoadError::BadRelocationValue(format!("{:#x} not aligned to {} for {:?}", value, alignment, reloc_type)));
+	}
+
+	return Ok(());
+}
+
+fn get_page(addr : i64) -> i64 {
+	return addr & !0xFFF;
+}
+
+// target is S + A (or G + A for the GOT ones), place is P
+pub fn compute_aarch64_patch(reloc_type : AArch64Reloc, target : i64, place : i64) -> Result<AArch64Patch, LoadError> {
+	let patch = match reloc_type {
+		AArch64Reloc::Abs64 => AArch64Patch::Data { value: target, size_bits: 64 },
+		AArch64Reloc::Abs32 => {
+			check_range(target, -(1 << 31), (1 << 32) - 1, reloc_type)?;
+			AArch64Patch::Data { value: target, size_bits: 32 }
+		}
+		AArch64Reloc::Abs16 => {
+			check_range(target, -(1 << 15), (1 << 16) - 1, reloc_type)?;
+			AArch64Patch::Data { value: target, size_bits: 16 }
+		}
+		AArch64Reloc::Prel64 => AArch64Patch::Data { value: target - place, size_bits: 64 },
+		AArch64Reloc::Prel32 => {
+			check_range(target - place, -(1 << 31), (1 << 32) - 1, reloc_type)?;
+			AArch64Patch::Data { value: target - place, size_bits: 32 }
+		}
+		AArch64Reloc::Prel16 => {
+			check_range(target - place, -(1 << 15), (1 << 16) - 1, reloc_type)?;
+			AArch64Patch::Data { value: target - place, size_bits: 16 }
+		}
+		AArch64Reloc::MovwUabsG0 | AArch64Reloc::MovwUabsG0Nc | AArch64Reloc::MovwUabsG1 | AArch64Reloc::MovwUabsG1Nc
+				| AArch64Reloc::MovwUabsG2 | AArch64Reloc::MovwUabsG2Nc | AArch64Reloc::MovwUabsG3 => {
+			let (group, checked) = match reloc_type {
+				AArch64Reloc::MovwUabsG0 => (0, true),
+				AArch64Reloc::MovwUabsG0Nc => (0, false),
+				AArch64Reloc::MovwUabsG1 => (1, true),
+				AArch64Reloc::MovwUabsG1Nc => (1, false),
+				AArch64Reloc::MovwUabsG2 => (2, true),
+				AArch64Reloc::MovwUabsG2Nc => (2, false),
+				_ => (3, false)
+			};
+
+			if checked {
+				check_range(target, 0, (1i64 << (16 * (group + 1))) - 1, reloc_type)?;
+			}
+
+			AArch64Patch::ImmField { value: ((target as u64 >> (16 * group)) & 0xFFFF) as u32, lsb: 5, num_bits: 16 }
+		}
+		AArch64Reloc::LdPrelLo19 | AArch64Reloc::Condbr19 | AArch64Reloc::GotLdPrel19 => {
+			check_alignment(target - place, 4, reloc_type)?;
+			check_range(target - place, -(1 << 20), (1 << 20) - 1, reloc_type)?;
+			AArch64Patch::ImmField { value: ((target - place) >> 2) as u32, lsb: 5, num_bits: 19 }
+		}
+		AArch64Reloc::Tstbr14 => {
+			check_alignment(target - place, 4, reloc_type)?;
+			check_range(target - place, -(1 << 15), (1 << 15) - 1, reloc_type)?;
+			AArch64Patch::ImmField { value: ((target - place) >> 2) as u32, lsb: 5, num_bits: 14 }
+		}
+		AArch64Reloc::Jump26 | AArch64Reloc::Call26 => {
+			check_alignment(target - place, 4, reloc_type)?;
+			check_range(target - place, -(1 << 27), (1 << 27) - 1, reloc_type)?;
+			AArch64Patch::ImmField { value: ((target - place) >> 2) as u32, lsb: 0, num_bits: 26 }
+		}
+		AArch64Reloc::AdrPrelLo21 => {
+			check_range(target - place, -(1 << 20), (1 << 20) - 1, reloc_type)?;
+			AArch64Patch::AdrImmediate((target - place) as i32)
+		}
+		AArch64Reloc::AdrPrelPgHi21 | AArch64Reloc::AdrPrelPgHi21Nc | AArch64Reloc::AdrGotPage => {
+			let page_delta = (get_page(target) - get_page(place)) >> 12;
+			if reloc_type != AArch64Reloc::AdrPrelPgHi21Nc {
+				check_range(page_delta, -(1 << 20), (1 << 20) - 1, reloc_type)?;
+			}
+			AArch64Patch::AdrImmediate(page_delta as i32)
+		}
+		AArch64Reloc::AddAbsLo12Nc => AArch64Patch::AddImmediate((target & 0xFFF) as i32),
+		AArch64Reloc::Ldst8AbsLo12Nc | AArch64Reloc::Ldst16AbsLo12Nc | AArch64Reloc::Ldst32AbsLo12Nc
+				| AArch64Reloc::Ldst64AbsLo12Nc | AArch64Reloc::Ldst128AbsLo12Nc | AArch64Reloc::Ld64GotLo12Nc => {
+			// The offset gets scaled by the size of the load/store
+			let size_shift = match reloc_type {
+				AArch64Reloc::Ldst8AbsLo12Nc => 0,
+				AArch64Reloc::Ldst16AbsLo12Nc => 1,
+				AArch64Reloc::Ldst32AbsLo12Nc => 2,
+				AArch64Reloc::Ldst128AbsLo12Nc => 4,
+				_ => 3
+			};
+
+			let page_offset = target & 0xFFF;
+			check_alignment(page_offset, 1 << size_shift, reloc_type)?;
+			AArch64Patch::LdstOffset((page_offset >> size_shift) as i32)
+		}
+		AArch64Reloc::PageOff12 => {
+			return Err(LoadError::UnsupportedRelocation(format!("{:?} that never got resolved to an add/load/store", reloc_type)));
+		}
+	};
+
+	return Ok(patch);
+}
+
+pub fn apply_aarch64_patch(exec_page : &mut ExecPage, write_offset : usize, patch : AArch64Patch) {
+	match patch {
+		AArch64Patch::Data { value, size_bits } => exec_page.fix_up_redirect(write_offset, size_bits as usize, value, false),
+		AArch64Patch::AdrImmediate(value) => exec_page.fix_up_arm_adrp_redirect(write_offset, value),
+		AArch64Patch::AddImmediate(value) => exec_page.fix_up_arm_add_immediate(write_offset, value),
+		AArch64Patch::LdstOffset(value) => exec_page.fix_up_arm_ldr_offset_redirect(write_offset, value, 10),
+		AArch64Patch::ImmField { value, lsb, num_bits } => exec_page.fix_up_arm_imm_field(write_offset, value, lsb, num_bits)
+	}
+}
+
+const AARCH64_RET_BYTES : [u8; 4] = [0xC0, 0x03, 0x5F, 0xD6];
+// brk #0
+const AARCH64_TRAP_BYTES : [u8; 4] = [0x00, 0x00, 0x20, 0xD4];
+
+// __stack_chk_fail never returns, so it's just a trap, and a smashed canary stops there instead of carrying on through the broken epilogue.
+// __stack_chk_guard just needs to be something. Our stacks are already mapped, so macOS's ___chkstk_darwin doesn't need to probe them
+const AARCH64_BUILTIN_STUBS : [(&str, &[u8]); 3] = [
+	("__stack_chk_fail", &AARCH64_TRAP_BYTES),
+	("__stack_chk_guard", &[0u8; 8]),
+	("__chkstk_darwin", &AARCH64_RET_BYTES)
+];
+
+// ldr x16, #8; br x16, followed by the 8-byte address to jump to
+const AARCH64_PLT_ENTRY_BYTES : [u8; 8] = [0x50, 0x00, 0x00, 0x58, 0x00, 0x02, 0x1F, 0xD6];
+
+fn aarch64_reloc_uses_got(reloc : &object::Relocation) -> bool {
+	return AArch64Reloc::from_relocation(reloc).map(|reloc_type| reloc_type.uses_got()).unwrap_or(false);
+}
+
+fn aarch64_reloc_uses_plt(reloc : &object::Relocation) -> bool {
+	return AArch64Reloc::from_relocation(reloc).map(|reloc_type| reloc_type.is_branch()).unwrap_or(false);
+}
+
+fn is_macho_addend(reloc : &object::Relocation) -> bool {
+	return matches!(reloc.kind(), RelocationKind::MachO { value: object::macho::ARM64_RELOC_ADDEND, .. });
+}
+
+// Same idea as link_x86_64, just with AArch64's relocations
+pub fn link_aarch64(obj_file : &object::File, mut bytes_loaded_into_memory : Vec<u8>, section_to_memory_addr : &HashMap<SectionIndex, usize>,
+		func_offset : usize, data_layout : DataLayout) -> Result<ExecPage, LoadError> {
+	let is_macho = obj_file.format() == object::BinaryFormat::MachO;
+
+	let mut linker = ObjLinker::default();
+	linker.resolve_relocations(obj_file, section_to_memory_addr, &AARCH64_BUILTIN_STUBS[..], true, &mut bytes_loaded_into_memory, aarch64_reloc_uses_got, aarch64_reloc_uses_plt)?;
+	linker.append_got_and_plt(&mut bytes_loaded_into_memory, &AARCH64_PLT_ENTRY_BYTES[..]);
+
+	let mut exec_page = ExecPage::new(bytes_loaded_into_memory.len() / (16*1024) + 1);
+	exec_page.load_with_code(&bytes_loaded_into_memory[..], func_offset);
+	exec_page.data_layout = data_layout;
+	let page_base = exec_page.page.as_ptr() as usize;
+	linker.fill_got(&mut exec_page, page_base);
+
+	exec_page.needs_server_link = linker.has_absolute_addrs() || data_layout.writable_len > 0;
+
+	for section in obj_file.sections().filter(|section| should_relocate_section(section) && section_to_memory_addr.contains_key(&section.index())) {
+		let section_offset_in_memory = *section_to_memory_addr.get(&section.index()).expect("");
+
+		// Mach-O addends come in an ARM64_RELOC_ADDEND at the same address as the relocation they're for, with the addend where the symbol number would be
+		let mut macho_addends = HashMap::<u64, i64>::new();
+		for (reloc_addr, reloc) in section.relocations().filter(|(_, reloc)| is_macho_addend(reloc)) {
+			if let RelocationTarget::Section(section_index) = reloc.target() {
+				macho_addends.insert(reloc_addr, ((section_index.0 as i64) << 40) >> 40);
+			}
+		}
+
+		for (reloc_addr, reloc) in section.relocations().filter(|(_, reloc)| !is_macho_addend(reloc)) {
+			let target = get_reloc_target(&reloc)?;
+			let target_name = get_reloc_target_name(obj_file, target)?;
+
+			let reloc_insert_offset_in_memory = section_offset_in_memory + reloc_addr as usize;
+			let place = (page_base + reloc_insert_offset_in_memory) as i64;
+
+			let instruction = bytes_loaded_into_memory.get(reloc_insert_offset_in_memory..reloc_insert_offset_in_memory+4)
+				.map(|instruction_bytes| u32::from_le_bytes(instruction_bytes.try_into().expect(""))).unwrap_or(0);
+			let reloc_type = AArch64Reloc::from_relocation(&reloc).and_then(|reloc_type| reloc_type.resolve_page_offset(instruction))
+				.ok_or_else(|| LoadError::UnsupportedRelocation(format!("AArch64 {:?} to '{}'", reloc.kind(), target_name)))?;
+
+			// Relocations straight to a section only make sense for data, where the target's address is the implicit addend
+			if let RelocTarget::Section(_) = target {
+				if !reloc_type.is_data() {
+					return Err(LoadError::UnsupportedRelocation(format!("{:?} to '{}'", reloc_type, target_name)));
+				}
+			}
+
+			// object makes up a -4 for anything PC-relative in Mach-O, which is only right for x86
+			let mut addend = if is_macho { macho_addends.get(&reloc_addr).cloned().unwrap_or(0) } else { reloc.addend() };
+			if reloc.has_implicit_addend() && reloc_type.is_data() {
+				addend += read_implicit_addend(&bytes_loaded_into_memory, reloc_insert_offset_in_memory, reloc.size())?;
+			}
+
+			let target_addr = if reloc_type.uses_got() {
+				linker.get_got_slot_addr(target, page_base)?
+			}
+			else {
+				linker.get_target_addr(obj_file, target, section_to_memory_addr, page_base, reloc_type.is_branch())?
+			};
+
+			if matches!(reloc_type, AArch64Reloc::Abs64 | AArch64Reloc::Abs32 | AArch64Reloc::Abs16) {
+				exec_page.needs_server_link = true;
+			}
+
+			let patch = compute_aarch64_patch(reloc_type, target_addr + addend, place)
+				.map_err(|err| LoadError::BadRelocationValue(format!("{} (to '{}')", err, target_name)))?;
+			apply_aarch64_patch(&mut exec_page, reloc_insert_offset_in_memory, patch);
+		}
+	}
+
+	exec_page.make_executable();
+
+	return Ok(exec_page);
+}
+
+#[test]
+fn test_compute_aarch64_patch_checks_ranges() {
+	// A call 4 bytes back
+	assert_eq!(compute_aarch64_patch(AArch64Reloc::Call26, 0x1000, 0x1004), Ok(AArch64Patch::ImmField { value: (-1i32) as u32, lsb: 0, num_bits: 26 }));
+	assert!(compute_aarch64_patch(AArch64Reloc::Call26, 0x1000 + (1 << 27), 0x1000).is_err());
+	assert!(compute_aarch64_patch(AArch64Reloc::Call26, 0x1002, 0x1000).is_err());
+
+	assert_eq!(compute_aarch64_patch(AArch64Reloc::AdrPrelPgHi21, 0x7fff_1234_5678, 0x7fff_1200_0010), Ok(AArch64Patch::AdrImmediate(0x345)));
+	assert!(compute_aarch64_patch(AArch64Reloc::AdrPrelPgHi21, 0x2_0000_0000, 0x1000).is_err());
+
+	assert_eq!(compute_aarch64_patch(AArch64Reloc::Ldst64AbsLo12Nc, 0x5_0a18, 0), Ok(AArch64Patch::LdstOffset(0xa18 >> 3)));
+	assert!(compute_aarch64_patch(AArch64Reloc::Ldst128AbsLo12Nc, 0x5_0a18, 0).is_err());
+
+	assert_eq!(compute_aarch64_patch(AArch64Reloc::MovwUabsG1Nc, 0x1234_5678_9abc, 0), Ok(AArch64Patch::ImmField { value: 0x5678, lsb: 5, num_bits: 16 }));
+	assert!(compute_aarch64_patch(AArch64Reloc::MovwUabsG1, 0x1234_5678_9abc, 0).is_err());
+}
+
+#[test]
+fn test_resolve_page_offset_from_instruction() {
+	assert_eq!(AArch64Reloc::PageOff12.resolve_page_offset(0x9100_0000), Some(AArch64Reloc::AddAbsLo12Nc)); // add x0, x0, #0
+	assert_eq!(AArch64Reloc::PageOff12.resolve_page_offset(0x3940_0000), Some(AArch64Reloc::Ldst8AbsLo12Nc)); // ldrb w0, [x0]
+	assert_eq!(AArch64Reloc::PageOff12.resolve_page_offset(0xb941_5128), Some(AArch64Reloc::Ldst32AbsLo12Nc)); // ldr w8, [x9, #336]
+	assert_eq!(AArch64Reloc::PageOff12.resolve_page_offset(0xf941_1508), Some(AArch64Reloc::Ldst64AbsLo12Nc)); // ldr x8, [x8, #552]
+	assert_eq!(AArch64Reloc::PageOff12.resolve_page_offset(0x3dc0_0000), Some(AArch64Reloc::Ldst128AbsLo12Nc)); // ldr q0, [x0]
+	assert_eq!(AArch64Reloc::PageOff12.resolve_page_offset(0xaa01_03e0), None); // mov x0, x1
+
+	assert_eq!(AArch64Reloc::Call26.resolve_page_offset(0xaa01_03e0), Some(AArch64Reloc::Call26));
+}
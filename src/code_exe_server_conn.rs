@@ -1,10 +1,36 @@
 
 
-use std::net::TcpStream;
+use std::net::{TcpListener, TcpStream};
 use std::io::{Read, Write};
 use std::cell::RefCell;
+use std::convert::TryInto;
+
+use crate::arm_codegen_fuzzing::{ARMCodeFuzzerInputValues, execute_with_return_type, decode_return_type};
+use crate::exec_mem::ExecPage;
+use crate::page_pool::EXEC_PAGE_SIZE;
+use crate::parse_exe::parse_obj_file;
+
+// Run this code with these inputs. The code's already been relocated wherever the client loaded it, so this only works for code
+// that doesn't care where it is (see ExecPage::needs_server_link). The reply is just the output, with nothing meaning it couldn't be run
+const MSG_TYPE_EXECUTE : u8 = 0x66;
+
+// Link this object and run its do_stuff with these inputs, for everything 0x66 can't do (GOT/PLT, absolute relocations, globals that get written).
+// Only our own exe-server knows this one, so the client sticks to 0x66 whenever it can
+const MSG_TYPE_EXECUTE_OBJ : u8 = 0x67;
+
+// The first byte of every 0x67 reply, saying what the rest of it is
+const REPLY_STATUS_OK : u8 = 0;
+const REPLY_STATUS_CRASHED : u8 = 1;
+const REPLY_STATUS_ERROR : u8 = 2;
+
+// Anything bigger than this is a bad length, not a real message
+const MAX_MSG_LEN : u32 = 64 * 1024 * 1024;
+
+// The generated code doesn't loop, so anything taking this long is stuck
+const EXECUTE_TIMEOUT_SECONDS : u64 = 10;
 
 pub struct CodeExeServClient {
+	connect_addr : String,
 	socket : RefCell<TcpStream>
 }
 
@@ -18,12 +44,70 @@ pub struct CodeExeAndInput<'a> {
 	pub return_type : u32
 }
 
+pub struct CodeObjAndInput<'a> {
+	pub obj_data: &'a [u8],
+	pub i_vals: &'a [i32],
+	pub f_vals: &'a [f32],
+	pub d_vals: &'a [f64],
+	pub return_type : u32
+}
+
+// What the server did with a 0x67
+#[derive(Debug, PartialEq)]
+pub enum CodeExeReply {
+	// The bytes do_stuff returned
+	Output(Vec<u8>),
+	// It took down the process it was running in (or timed out), and this says how
+	Crashed(String),
+	// It never got to run, e.g. the object didn't link or it's not a return type the server can call
+	Error(String)
+}
+
 // | overall msg length, incl. type (4 bytes) | type (1 byte) | return_type (4 bytes, be) | func offset (4 bytes, be) | code len (4 bytes, be) | code_bytes | num i_vals (4 bytes, be) | i_vals | ...
+// 0x67 is the same, but with | obj len (4 bytes, be) | obj_data | in place of the func offset and code.
+// The reply is | length (4 bytes, be) | output bytes |, and for 0x67 the first byte after the length is one of the REPLY_STATUS_*,
+// followed by the output for REPLY_STATUS_OK or a message for anything else
+
+fn connect_to_exe_server(connect_addr : &str) -> std::io::Result<TcpStream> {
+	let socket = TcpStream::connect(connect_addr)?;
+	// Every message waits on its reply, so don't let Nagle sit on them
+	socket.set_nodelay(true)?;
+	return Ok(socket);
+}
+
+fn push_input_vals(overall_msg : &mut Vec<u8>, i_vals : &[i32], f_vals : &[f32], d_vals : &[f64]) {
+	{
+		let num_i_vals = (i_vals.len() as u32).to_be_bytes();
+		overall_msg.extend_from_slice(&num_i_vals);
+		for i_val in i_vals {
+			overall_msg.extend_from_slice(&i_val.to_be_bytes());
+		}
+	}
+	
+	//println!("Sending {} iVals", i_vals.len());
+	
+	{
+		let num_f_vals = (f_vals.len() as u32).to_be_bytes();
+		overall_msg.extend_from_slice(&num_f_vals);
+		for f_val in f_vals {
+			overall_msg.extend_from_slice(&f_val.to_be_bytes());
+		}
+	}
+	
+	{
+		let num_d_vals = (d_vals.len() as u32).to_be_bytes();
+		overall_msg.extend_from_slice(&num_d_vals);
+		for d_val in d_vals {
+			overall_msg.extend_from_slice(&d_val.to_be_bytes());
+		}
+	}
+}
 
 impl<'a> CodeExeServClient {
 
 	pub fn new(connect_addr : &str) -> Self {
-		Self { socket: RefCell::new(TcpStream::connect(connect_addr).unwrap()) }
+		let socket = connect_to_exe_server(connect_addr).unwrap();
+		Self { connect_addr: connect_addr.to_string(), socket: RefCell::new(socket) }
 	}
 
 	// return type:
@@ -34,7 +118,7 @@ impl<'a> CodeExeServClient {
 	pub fn send_exe_and_input(&self, exe_and_input : &CodeExeAndInput) -> std::io::Result<Vec<u8>> {
 		let mut overall_msg = Vec::<u8>::new();
 		
-		overall_msg.push(0x66);
+		overall_msg.push(MSG_TYPE_EXECUTE);
 		
 		overall_msg.extend_from_slice(&exe_and_input.return_type.to_be_bytes());
 		//println!("Sending return type {}", exe_and_input.return_type);
@@ -50,43 +134,62 @@ impl<'a> CodeExeServClient {
 		
 		//println!("Sending code len {}", exe_and_input.code_bytes.len());
 		
-		{
-			let num_i_vals = (exe_and_input.i_vals.len() as u32).to_be_bytes();
-			overall_msg.extend_from_slice(&num_i_vals);
-			for i_val in exe_and_input.i_vals {
-				overall_msg.extend_from_slice(&i_val.to_be_bytes());
-			}
-		}
-		
-		//println!("Sending {} iVals", exe_and_input.i_vals.len());
-		
-		{
-			let num_f_vals = (exe_and_input.f_vals.len() as u32).to_be_bytes();
-			overall_msg.extend_from_slice(&num_f_vals);
-			for f_val in exe_and_input.f_vals {
-				overall_msg.extend_from_slice(&f_val.to_be_bytes());
-			}
+		push_input_vals(&mut overall_msg, exe_and_input.i_vals, exe_and_input.f_vals, exe_and_input.d_vals);
+
+		return self.send_msg(&overall_msg);
+	}
+
+	// Same return type as send_exe_and_input
+	pub fn send_obj_and_input(&self, obj_and_input : &CodeObjAndInput) -> std::io::Result<CodeExeReply> {
+		let mut overall_msg = Vec::<u8>::new();
+		overall_msg.push(MSG_TYPE_EXECUTE_OBJ);
+		overall_msg.extend_from_slice(&obj_and_input.return_type.to_be_bytes());
+		overall_msg.extend_from_slice(&(obj_and_input.obj_data.len() as u32).to_be_bytes());
+		overall_msg.extend_from_slice(obj_and_input.obj_data);
+		push_input_vals(&mut overall_msg, obj_and_input.i_vals, obj_and_input.f_vals, obj_and_input.d_vals);
+
+		let mut msg = self.send_msg(&overall_msg)?;
+		if msg.len() == 0 {
+			return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "empty reply to an object, does the server know 0x67?"));
 		}
-		
-		{
-			let num_d_vals = (exe_and_input.d_vals.len() as u32).to_be_bytes();
-			overall_msg.extend_from_slice(&num_d_vals);
-			for d_val in exe_and_input.d_vals {
-				overall_msg.extend_from_slice(&d_val.to_be_bytes());
+
+		let payload = msg.split_off(1);
+		return match msg[0] {
+			REPLY_STATUS_OK => Ok(CodeExeReply::Output(payload)),
+			REPLY_STATUS_CRASHED => Ok(CodeExeReply::Crashed(String::from_utf8_lossy(&payload).to_string())),
+			REPLY_STATUS_ERROR => Ok(CodeExeReply::Error(String::from_utf8_lossy(&payload).to_string())),
+			status => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown reply status {}", status)))
+		};
+	}
+
+	// If the connection's gone (e.g. the server got restarted), this reconnects and tries once more before giving up
+	fn send_msg(&self, overall_msg : &[u8]) -> std::io::Result<Vec<u8>> {
+		match self.try_send_msg(overall_msg) {
+			Ok(msg) => { return Ok(msg); }
+			Err(err) => {
+				print!("Lost connection to exe server at {} ({}), reconnecting\n", self.connect_addr, err);
 			}
 		}
 
+		*self.socket.borrow_mut() = connect_to_exe_server(&self.connect_addr)?;
+		return self.try_send_msg(overall_msg);
+	}
+
+	fn try_send_msg(&self, overall_msg : &[u8]) -> std::io::Result<Vec<u8>> {
 		let overall_msg_len = overall_msg.len() as u32;
 		let overall_msg_len_bytes = overall_msg_len.to_be_bytes();
 		
 		let mut socket = self.socket.borrow_mut();
-		socket.write(&overall_msg_len_bytes)?;
-		socket.write(&overall_msg)?;
+		socket.write_all(&overall_msg_len_bytes)?;
+		socket.write_all(overall_msg)?;
 		
 		let mut msg_len_buff = [0u8 ; 4];
 		socket.read_exact(&mut msg_len_buff)?;
 		
 		let msg_len = u32::from_be_bytes(msg_len_buff);
+		if msg_len > MAX_MSG_LEN {
+			return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("reply length {} is too big", msg_len)));
+		}
 
 		let mut msg = vec![0u8 ; msg_len as usize];
 		
@@ -96,5 +199,436 @@ impl<'a> CodeExeServClient {
 	}
 }
 
+// The server side of all that, for 'exe-server'. Each request runs in its own forked child, so code that crashes (or hangs)
+// only takes that down. For a 0x67 the object gets linked here, so the GOT/PLT point at our builtins and the writable data is actually writable.
+// A 0x66 is loaded as it came, all read+exec since the message doesn't say where the writable data ends, and there's no way to say it crashed
+// besides sending back nothing
+enum CodeToRun {
+	Relocated { func_offset : u32, code_bytes : Vec<u8> },
+	Object(Vec<u8>)
+}
+
+struct CodeExeRequest {
+	return_type : u32,
+	code : CodeToRun,
+	input : ARMCodeFuzzerInputValues
+}
+
+struct MsgReader<'a> {
+	msg : &'a [u8],
+	pos : usize
+}
+
+impl<'a> MsgReader<'a> {
+	fn read_bytes(&mut self, num_bytes : usize) -> Result<&'a [u8], String> {
+		if self.msg.len() - self.pos < num_bytes {
+			return Err(format!("message ended at {} bytes, wanted {} more at {}", self.msg.len(), num_bytes, self.pos));
+		}
+
+		let bytes = &self.msg[self.pos..self.pos + num_bytes];
+		self.pos += num_bytes;
+		return Ok(bytes);
+	}
+
+	fn read_u32(&mut self) -> Result<u32, String> {
+		return Ok(u32::from_be_bytes(self.read_bytes(4)?.try_into().expect("")));
+	}
+
+	// A count, and then that many values of VAL_SIZE bytes each
+	fn read_vals<T, const VAL_SIZE : usize>(&mut self, from_be_bytes : fn([u8; VAL_SIZE]) -> T) -> Result<Vec<T>, String> {
+		let num_vals = self.read_u32()? as usize;
+		let val_bytes = self.read_bytes(num_vals.checked_mul(VAL_SIZE).ok_or("bad value count")?)?;
+		return Ok(val_bytes.chunks_exact(VAL_SIZE).map(|bytes| from_be_bytes(bytes.try_into().expect(""))).collect());
+	}
+}
+
+impl CodeExeRequest {
+	// msg is everything after the length
+	fn decode(msg : &[u8]) -> Result<CodeExeRequest, String> {
+		let mut reader = MsgReader { msg: msg, pos: 0 };
+
+		let msg_type = reader.read_bytes(1)?[0];
+		let return_type = reader.read_u32()?;
+		let code = match msg_type {
+			MSG_TYPE_EXECUTE => {
+				let func_offset = reader.read_u32()?;
+				let code_len = reader.read_u32()? as usize;
+				let code_bytes = reader.read_bytes(code_len)?.to_vec();
+				if func_offset as usize >= code_bytes.len() {
+					return Err(format!("func offset {} is past the end of {} bytes of code", func_offset, code_bytes.len()));
+				}
+				CodeToRun::Relocated { func_offset: func_offset, code_bytes: code_bytes }
+			}
+			MSG_TYPE_EXECUTE_OBJ => {
+				let obj_len = reader.read_u32()? as usize;
+				CodeToRun::Object(reader.read_bytes(obj_len)?.to_vec())
+			}
+			_ => {
+				return Err(format!("unknown message type 0x{:02x}", msg_type));
+			}
+		};
+
+		let i_vals = reader.read_vals(i32::from_be_bytes)?;
+		let f_vals = reader.read_vals(f32::from_be_bytes)?;
+		let d_vals = reader.read_vals(f64::from_be_bytes)?;
+		if reader.pos != msg.len() {
+			return Err(format!("{} extra bytes at the end of the message", msg.len() - reader.pos));
+		}
+
+		return Ok(CodeExeRequest {
+			return_type: return_type,
+			code: code,
+			input: ARMCodeFuzzerInputValues { i_vals: i_vals, f_vals: f_vals, d_vals: d_vals }
+		});
+	}
+
+	fn execute(&self) -> CodeExeReply {
+		let exec_page = match &self.code {
+			CodeToRun::Relocated { func_offset, code_bytes } => {
+				let mut exec_page = ExecPage::new(code_bytes.len() / EXEC_PAGE_SIZE + 1);
+				exec_page.load_with_code(&code_bytes[..], *func_offset as usize);
+				exec_page.make_executable();
+				exec_page
+			}
+			CodeToRun::Object(obj_data) => {
+				match parse_obj_file(&obj_data[..], "do_stuff") {
+					Ok(exec_page) => exec_page,
+					Err(err) => { return CodeExeReply::Error(format!("could not load object: {}", err)); }
+				}
+			}
+		};
+
+		let return_type = decode_return_type(self.return_type);
+		return run_in_child(|| match execute_with_return_type(&exec_page, return_type, &self.input) {
+			Some(output) => Ok(output.output_bytes[..output.output_len].to_vec()),
+			None => Err(format!("can't call code returning {:?} here", return_type))
+		});
+	}
+
+	fn encode_reply(&self, reply : CodeExeReply) -> Vec<u8> {
+		let msg = match (&self.code, reply) {
+			(CodeToRun::Relocated { .. }, CodeExeReply::Output(output)) => output,
+			(CodeToRun::Relocated { .. }, CodeExeReply::Crashed(_) | CodeExeReply::Error(_)) => Vec::new(),
+			(CodeToRun::Object(_), reply) => {
+				let (status, payload) = match reply {
+					CodeExeReply::Output(output) => (REPLY_STATUS_OK, output),
+					CodeExeReply::Crashed(err) => (REPLY_STATUS_CRASHED, err.into_bytes()),
+					CodeExeReply::Error(err) => (REPLY_STATUS_ERROR, err.into_bytes())
+				};
+				let mut msg = vec![status];
+				msg.extend_from_slice(&payload[..]);
+				msg
+			}
+		};
+
+		let mut reply_bytes = Vec::with_capacity(msg.len() + 4);
+		reply_bytes.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+		reply_bytes.extend_from_slice(&msg[..]);
+		return reply_bytes;
+	}
+}
+
+// Runs func in a forked child, and gets back what it returned over a pipe: exiting with 0 means it's output, 1 means it's an error message.
+// Anything else (a signal, or the watchdog killing it) means the code crashed
+#[cfg(unix)]
+fn run_in_child<F : FnOnce() -> Result<Vec<u8>, String>>(func : F) -> CodeExeReply {
+	use std::os::unix::io::FromRawFd;
+	use std::os::unix::process::ExitStatusExt;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use std::time::Duration;
+	use crate::compilation_config::{spawn_process_watchdog, wait_for_exit_without_reaping, get_status_code};
 
+	let mut pipe_fds = [0 as libc::c_int ; 2];
+	if unsafe { libc::pipe(pipe_fds.as_mut_ptr()) } != 0 {
+		return CodeExeReply::Error(format!("could not create pipe: {}", std::io::Error::last_os_error()));
+	}
+	let (read_fd, write_fd) = (pipe_fds[0], pipe_fds[1]);
+
+	let pid = unsafe { libc::fork() };
+	if pid < 0 {
+		unsafe { libc::close(read_fd); libc::close(write_fd); }
+		return CodeExeReply::Error(format!("could not fork: {}", std::io::Error::last_os_error()));
+	}
 
+	if pid == 0 {
+		// Own process group, same as the compilers, so the watchdog can kill it
+		unsafe { libc::close(read_fd); libc::setpgid(0, 0); }
+		let (exit_code, reply_bytes) = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(func)) {
+			Ok(Ok(output)) => (0, output),
+			Ok(Err(err)) => (1, err.into_bytes()),
+			Err(_) => (1, b"panicked running the code".to_vec())
+		};
+
+		let mut write_file = unsafe { std::fs::File::from_raw_fd(write_fd) };
+		let _ = write_file.write_all(&reply_bytes[..]);
+		// Skip anything atexit would do, that all belongs to the server
+		unsafe { libc::_exit(exit_code); }
+	}
+
+	// Both of us set the process group, so it's there no matter who gets to it first
+	unsafe { libc::close(write_fd); libc::setpgid(pid, pid); }
+
+	let timed_out = Arc::new(AtomicBool::new(false));
+	let (done_sender, watchdog_join_handle) = spawn_process_watchdog(pid as u32, Duration::from_secs(EXECUTE_TIMEOUT_SECONDS), timed_out.clone());
+
+	let mut reply_bytes = Vec::<u8>::new();
+	let read_res = unsafe { std::fs::File::from_raw_fd(read_fd) }.read_to_end(&mut reply_bytes);
+
+	wait_for_exit_without_reaping(pid as u32);
+	drop(done_sender);
+	watchdog_join_handle.join().expect("could not join process watchdog thread");
+
+	let mut raw_status : libc::c_int = 0;
+	while unsafe { libc::waitpid(pid, &mut raw_status, 0) } < 0 {
+		if std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+			return CodeExeReply::Error(format!("could not wait on child {}: {}", pid, std::io::Error::last_os_error()));
+		}
+	}
+	let status = std::process::ExitStatus::from_raw(raw_status);
+
+	if timed_out.load(Ordering::SeqCst) {
+		return CodeExeReply::Crashed(format!("timed out after {} seconds", EXECUTE_TIMEOUT_SECONDS));
+	}
+
+	match (status.code(), read_res) {
+		(Some(0), Ok(_)) => { return CodeExeReply::Output(reply_bytes); }
+		(Some(1), Ok(_)) => { return CodeExeReply::Error(String::from_utf8_lossy(&reply_bytes).to_string()); }
+		_ => { return CodeExeReply::Crashed(format!("exited with {}", get_status_code(&status))); }
+	}
+}
+
+// No fork here, so a crash still takes the whole server down
+#[cfg(not(unix))]
+fn run_in_child<F : FnOnce() -> Result<Vec<u8>, String>>(func : F) -> CodeExeReply {
+	return match func() {
+		Ok(output) => CodeExeReply::Output(output),
+		Err(err) => CodeExeReply::Error(err)
+	};
+}
+
+// None if the client hung up between messages
+fn read_msg(stream : &mut TcpStream) -> std::io::Result<Option<Vec<u8>>> {
+	let mut msg_len_buff = [0u8 ; 4];
+	match stream.read_exact(&mut msg_len_buff) {
+		Ok(()) => {}
+		Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => { return Ok(None); }
+		Err(err) => { return Err(err); }
+	}
+
+	let msg_len = u32::from_be_bytes(msg_len_buff);
+	if msg_len > MAX_MSG_LEN {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("message length {} is too big", msg_len)));
+	}
+
+	let mut msg = vec![0u8 ; msg_len as usize];
+	stream.read_exact(&mut msg[..])?;
+	return Ok(Some(msg));
+}
+
+fn serve_code_exe_connection(mut stream : TcpStream) {
+	let peer_addr = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or("?".to_string());
+	stream.set_nodelay(true).expect("could not set TCP_NODELAY");
+
+	loop {
+		let msg = match read_msg(&mut stream) {
+			Ok(Some(msg)) => msg,
+			Ok(None) => break,
+			Err(err) => {
+				print!("Could not read message from {}: {}\n", peer_addr, err);
+				break;
+			}
+		};
+
+		// A bad message means we don't know where the next one starts, so there's no recovering the connection
+		let request = match CodeExeRequest::decode(&msg) {
+			Ok(request) => request,
+			Err(err) => {
+				print!("Bad message from {}: {}\n", peer_addr, err);
+				break;
+			}
+		};
+
+		let reply = request.execute();
+		match &reply {
+			CodeExeReply::Crashed(err) => { print!("Code from {} crashed: {}\n", peer_addr, err); }
+			CodeExeReply::Error(err) => { print!("Could not run code from {}: {}\n", peer_addr, err); }
+			CodeExeReply::Output(_) => {}
+		}
+
+		if let Err(err) = stream.write_all(&request.encode_reply(reply)) {
+			print!("Could not reply to {}: {}\n", peer_addr, err);
+			break;
+		}
+	}
+}
+
+// Each fuzzer thread keeps its own connection open for the whole run, so they each get a thread here too
+pub fn run_code_exe_server(listener : TcpListener) {
+	for stream in listener.incoming() {
+		match stream {
+			Ok(stream) => {
+				std::thread::spawn(move || serve_code_exe_connection(stream));
+			}
+			Err(err) => {
+				print!("Could not accept connection: {}\n", err);
+			}
+		}
+	}
+}
+
+
+// A minimal x86-64 ELF object with do_stuff at the start of .text, and a PC32 relocation to .data at each of data_reloc_offsets
+#[cfg(test)]
+fn build_test_x86_64_obj(code_bytes : &[u8], data_bytes : &[u8], data_reloc_offsets : &[u64]) -> Vec<u8> {
+	// Sections are null, .text, .data, .rela.text, .symtab, .strtab, .shstrtab
+	let shstrtab = b"\0.text\0.data\0.rela.text\0.symtab\0.strtab\0.shstrtab\0";
+	let strtab = b"\0do_stuff\0";
+
+	// Null symbol, then .data's section symbol, then do_stuff
+	let mut symtab = vec![0u8; 24];
+	symtab.extend_from_slice(&0u32.to_le_bytes());
+	symtab.extend_from_slice(&[0x03, 0]);
+	symtab.extend_from_slice(&2u16.to_le_bytes());
+	symtab.extend_from_slice(&[0u8; 16]);
+	symtab.extend_from_slice(&1u32.to_le_bytes());
+	symtab.extend_from_slice(&[0x12, 0]);
+	symtab.extend_from_slice(&1u16.to_le_bytes());
+	symtab.extend_from_slice(&0u64.to_le_bytes());
+	symtab.extend_from_slice(&(code_bytes.len() as u64).to_le_bytes());
+
+	let mut rela = Vec::<u8>::new();
+	for reloc_offset in data_reloc_offsets.iter() {
+		rela.extend_from_slice(&reloc_offset.to_le_bytes());
+		rela.extend_from_slice(&((1u64 << 32) | object::elf::R_X86_64_PC32 as u64).to_le_bytes());
+		rela.extend_from_slice(&(-4i64).to_le_bytes());
+	}
+
+	let mut obj = vec![0u8; 64];
+	let mut section_offsets = Vec::<u64>::new();
+	for section_data in [code_bytes, data_bytes, &rela[..], &symtab[..], &strtab[..], &shstrtab[..]] {
+		crate::parse_exe::align_vec(&mut obj, 16);
+		section_offsets.push(obj.len() as u64);
+		obj.extend_from_slice(section_data);
+	}
+	crate::parse_exe::align_vec(&mut obj, 8);
+	let section_headers_offset = obj.len() as u64;
+
+	// (name, type, flags, offset, size, link, info, align, entsize)
+	let section_headers : [(u32, u32, u64, u64, u64, u32, u32, u64, u64); 7] = [
+		(0, 0, 0, 0, 0, 0, 0, 0, 0),
+		(1, 1, 0x6, section_offsets[0], code_bytes.len() as u64, 0, 0, 16, 0),
+		(7, 1, 0x3, section_offsets[1], data_bytes.len() as u64, 0, 0, 8, 0),
+		(13, 4, 0x40, section_offsets[2], rela.len() as u64, 4, 1, 8, 24),
+		(24, 2, 0, section_offsets[3], symtab.len() as u64, 5, 2, 8, 24),
+		(32, 3, 0, section_offsets[4], strtab.len() as u64, 0, 0, 1, 0),
+		(40, 3, 0, section_offsets[5], shstrtab.len() as u64, 0, 0, 1, 0)
+	];
+	for (name, section_type, flags, offset, size, link, info, align, entsize) in section_headers.iter() {
+		obj.extend_from_slice(&name.to_le_bytes());
+		obj.extend_from_slice(&section_type.to_le_bytes());
+		obj.extend_from_slice(&flags.to_le_bytes());
+		obj.extend_from_slice(&0u64.to_le_bytes());
+		obj.extend_from_slice(&offset.to_le_bytes());
+		obj.extend_from_slice(&size.to_le_bytes());
+		obj.extend_from_slice(&link.to_le_bytes());
+		obj.extend_from_slice(&info.to_le_bytes());
+		obj.extend_from_slice(&align.to_le_bytes());
+		obj.extend_from_slice(&entsize.to_le_bytes());
+	}
+
+	// ELF64, little endian, relocatable, x86-64
+	let mut header = vec![0x7F, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+	header.extend_from_slice(&1u16.to_le_bytes());
+	header.extend_from_slice(&62u16.to_le_bytes());
+	header.extend_from_slice(&1u32.to_le_bytes());
+	header.extend_from_slice(&0u64.to_le_bytes());
+	header.extend_from_slice(&0u64.to_le_bytes());
+	header.extend_from_slice(&section_headers_offset.to_le_bytes());
+	header.extend_from_slice(&0u32.to_le_bytes());
+	for header_val in [64u16, 0, 0, 64, section_headers.len() as u16, 6] {
+		header.extend_from_slice(&header_val.to_le_bytes());
+	}
+	obj[..64].copy_from_slice(&header[..]);
+
+	return obj;
+}
+
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_code_exe_server_loopback() {
+	use crate::arm_codegen_fuzzing::encode_return_type;
+	use crate::arm_intrinsics::{ARMSIMDType, ARMBaseType};
+
+	// The first connection gets dropped right away, so the client has to reconnect for its first message
+	let listener = TcpListener::bind("127.0.0.1:0").expect("could not listen on loopback");
+	let server_addr = listener.local_addr().expect("").to_string();
+	std::thread::spawn(move || {
+		drop(listener.accept().expect("could not accept first connection"));
+		run_code_exe_server(listener);
+	});
+
+	let int_return_type = encode_return_type(ARMSIMDType::Primitive(ARMBaseType::Int32));
+	let simd_return_type = encode_return_type(ARMSIMDType::SIMD(ARMBaseType::Int32, 4));
+	let client = CodeExeServClient::new(&server_addr);
+
+	// 0x66: some padding first so the func offset gets used: mov eax, [rdi] ; add eax, [rdi + 4] ; ret
+	let code_bytes = [0xCC, 0xCC, 0x8B, 0x07, 0x03, 0x47, 0x04, 0xC3];
+	// Vectors can't be called on x86, and ud2 crashes, so both of those send back nothing
+	for (code_bytes, func_offset, i_vals, return_type, expected) in [
+			(&code_bytes[..], 2, [40, 2], int_return_type, 42i32.to_le_bytes().to_vec()),
+			(&code_bytes[..], 2, [-1, -2], int_return_type, (-3i32).to_le_bytes().to_vec()),
+			(&code_bytes[..], 2, [0, 0], simd_return_type, Vec::new()),
+			(&[0x0F, 0x0B][..], 0, [0, 0], int_return_type, Vec::new()),
+			(&code_bytes[..], 2, [1, 2], int_return_type, 3i32.to_le_bytes().to_vec())] {
+		let output = client.send_exe_and_input(&CodeExeAndInput {
+			code_bytes: code_bytes,
+			func_offset: func_offset,
+			i_vals: &i_vals[..],
+			f_vals: &[1.5],
+			d_vals: &[],
+			return_type: return_type
+		}).expect("");
+		assert_eq!(output, expected);
+	}
+
+	// 0x67: mov eax, [rip + counter] ; add eax, [rdi] ; mov [rip + counter], eax ; ret, with the counter starting at 100 in .data.
+	// It gets linked on the server, so the global's writable, and every run starts from the same value
+	let code_bytes = [0x8B, 0x05, 0, 0, 0, 0, 0x03, 0x07, 0x89, 0x05, 0, 0, 0, 0, 0xC3];
+	let obj_data = build_test_x86_64_obj(&code_bytes, &100u32.to_le_bytes(), &[2, 10]);
+	let crashing_obj_data = build_test_x86_64_obj(&[0x0F, 0x0B], &[], &[]);
+	// That's what makes the fuzzer send the object instead of the relocated code
+	assert!(crate::parse_exe::load_obj_file(&obj_data[..], "do_stuff").expect("").needs_server_link);
+	assert!(!crate::parse_exe::load_obj_file(&crashing_obj_data[..], "do_stuff").expect("").needs_server_link);
+	for (obj_data, i_vals, return_type, expected) in [
+			(&obj_data[..], [40, 2], int_return_type, CodeExeReply::Output(140i32.to_le_bytes().to_vec())),
+			(&obj_data[..], [-1, -2], int_return_type, CodeExeReply::Output(99i32.to_le_bytes().to_vec())),
+			(&obj_data[..], [40, 2], int_return_type, CodeExeReply::Output(140i32.to_le_bytes().to_vec()))] {
+		let reply = client.send_obj_and_input(&CodeObjAndInput {
+			obj_data: obj_data,
+			i_vals: &i_vals[..],
+			f_vals: &[],
+			d_vals: &[],
+			return_type: return_type
+		}).expect("");
+		assert_eq!(reply, expected);
+	}
+
+	// ud2 only takes down the child running it, and garbage or vectors on x86 just can't be run
+	for (obj_data, return_type, expect_crash) in [(&crashing_obj_data[..], int_return_type, true), (&obj_data[..], simd_return_type, false), (&b"not an object"[..], int_return_type, false)] {
+		let reply = client.send_obj_and_input(&CodeObjAndInput {
+			obj_data: obj_data,
+			i_vals: &[],
+			f_vals: &[],
+			d_vals: &[],
+			return_type: return_type
+		}).expect("");
+		if expect_crash {
+			assert!(matches!(reply, CodeExeReply::Crashed(_)), "{:?}", reply);
+		}
+		else {
+			assert!(matches!(reply, CodeExeReply::Error(_)), "{:?}", reply);
+		}
+	}
+}
@@ -13,14 +13,23 @@ pub trait CodegenFuzzer<InputData, CtxType, CodeMetadata, RunInputs, RunOutputs>
 	// a parsed spec data, flags, config, etc.
 	fn new_fuzzer_state(input_data : InputData) -> Self;
 
+	// The seed the driver derives each case from, see CaseSeed
+	fn get_thread_seed(&self) -> u64;
+
 	// This generates some context struct that's basically analagous to the AST
-	fn generate_ctx(&mut self) -> CtxType;
+	fn generate_ctx(&mut self, ctx_seed : u64) -> CtxType;
 
 	// Turn the AST/context into actual CPP code, along with any metadata (i.e. number of values to pass for SIMD's iVals pointer
 	fn generate_cpp_code(&self, ctx : &CtxType) -> (String, CodeMetadata);
 
 	// Given the metadata about the code, generate a random input for it
-	fn generate_random_input(&self, code_meta : &CodeMetadata) -> RunInputs;
+	fn generate_random_input(&self, code_meta : &CodeMetadata, input_seed : u64) -> RunInputs;
+
+	// Anything in the generated code that could be UB, so the case can be thrown out before compiling it.
+	// Empty means it's fine, or that the fuzzer doesn't check (the intrinsics ones can't really, each intrinsic has its own rules)
+	fn find_potential_ub(&self, _ctx : &CtxType) -> Vec<String> {
+		return Vec::new();
+	}
 
 	// uhh.....idk
 	fn try_minimize<F: Fn(&Self, &CtxType) -> bool>(&self, ctx: CtxType, func: F) -> Option<CtxType>;
@@ -39,6 +48,66 @@ pub trait CodegenFuzzer<InputData, CtxType, CodeMetadata, RunInputs, RunOutputs>
 	fn num_inputs_per_codegen(&self) -> u32;
 }
 
+// Every case is derived from (thread seed, case index), and every input from that plus the input index,
+// so a finding can be regenerated exactly from those numbers without needing any of the saved files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaseSeed {
+	pub thread_seed : u64,
+	pub case_index : u64
+}
+
+// splitmix64, so that nearby thread seeds/case indices still give unrelated seeds
+fn mix_seed(seed : u64) -> u64 {
+	let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+	return z ^ (z >> 31);
+}
+
+impl CaseSeed {
+	pub fn ctx_seed(&self) -> u64 {
+		return mix_seed(self.thread_seed ^ mix_seed(self.case_index));
+	}
+
+	pub fn input_seed(&self, input_index : u32) -> u64 {
+		return mix_seed(self.ctx_seed() ^ mix_seed((input_index as u64) + 1));
+	}
+
+	// Formatted as [thread_seed]:[case_index]:[input_index], which is what 'replay --seed' takes
+	pub fn to_replay_string(&self, input_index : u32) -> String {
+		return format!("{}:{}:{}", self.thread_seed, self.case_index, input_index);
+	}
+
+	// The input index is optional, and defaults to the first input
+	pub fn parse_replay_string(seed_str : &str) -> Option<(CaseSeed, u32)> {
+		let parts : Vec<&str> = seed_str.trim().split(':').collect();
+		if parts.len() < 2 || parts.len() > 3 {
+			return None;
+		}
+
+		let thread_seed = parts[0].parse::<u64>().ok()?;
+		let case_index = parts[1].parse::<u64>().ok()?;
+		let input_index = if parts.len() == 3 { parts[2].parse::<u32>().ok()? } else { 0 };
+
+		return Some((CaseSeed { thread_seed: thread_seed, case_index: case_index }, input_index));
+	}
+}
+
+// Inputs are serialized as a line with the number of values, followed by a line of space-separated values
+pub fn read_serialized_values<T : std::str::FromStr>(lines : &mut std::str::Lines) -> Vec<T> where T::Err : std::fmt::Debug {
+	let num_vals = lines.next().expect("missing value count in serialized input").trim().parse::<usize>().expect("bad value count in serialized input");
+
+	let mut vals = Vec::<T>::with_capacity(num_vals);
+	if let Some(vals_line) = lines.next() {
+		for val in vals_line.split_whitespace() {
+			vals.push(val.parse::<T>().expect("bad value in serialized input"));
+		}
+	}
+
+	assert!(vals.len() == num_vals, "expected {} values in serialized input, got {}", num_vals, vals.len());
+	return vals;
+}
+
 
 
 
@@ -4,12 +4,29 @@ use std::process::{Command, Stdio};
 use std::io::Write as IOWrite;
 use std::io::Read as IORead;
 use std::collections::BTreeSet;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use std::sync::mpsc;
-
-use crate::parse_exe::parse_obj_file;
-use crate::exec_mem::ExecPage;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::parse_exe::{load_obj_file, LoadError};
+use crate::exec_mem::{ExecPage, ExternalCode};
+use crate::shared_lib_exec::SharedLib;
+use crate::generated_exe::{GeneratedExe, EXE_HARNESS_MAIN};
+
+// How we get from the compiler's output to something we can run
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum ExecBackend {
+	// Load and relocate the object ourselves, and run it in-process
+	#[default]
+	ObjLoader,
+	// Have the compiler link a shared lib, and dlopen it in a worker process (see shared_lib_exec)
+	SharedLib,
+	// Have the compiler link a whole executable, and run that once per input (see generated_exe)
+	Executable
+}
 
 #[derive(Default, Debug, Clone)]
 pub struct TestCompilation {
@@ -17,7 +34,114 @@ pub struct TestCompilation {
 	pub compiler_args : Vec<String>,
 	pub timeout_seconds : i32,
 	pub tmp_file_name : Option<String>,
-	pub use_tmp_file : bool
+	pub use_tmp_file : bool,
+	// Set if the args take the code as a file (^GENERATED_SOURCE_FILENAME^) instead of on stdin
+	pub source_file_name : Option<String>,
+	// Set if the args have the compiler link an executable (^GENERATED_EXE_FILENAME^)
+	pub exe_file_name : Option<String>,
+	// Set for "exec_backend": "shared_lib", where the libs get written out for the worker to dlopen
+	pub shared_lib_dir : Option<PathBuf>,
+	// Copied from the config's parallel_compilations, same as the timeout
+	pub run_in_parallel : bool,
+	pub exec_backend : ExecBackend
+}
+
+// Every file the compiles need goes under one directory per run (tmp/[name]_[pid]), which gets removed once the run is done.
+// If we get killed hard it'll stick around, but at least it's obvious what it was from
+pub struct RunTmpDir {
+	pub path : PathBuf,
+	keep : bool
+}
+
+impl RunTmpDir {
+	pub fn create(name : &str) -> Self {
+		let path = PathBuf::from(format!("tmp/{}_{}", name, std::process::id()));
+		std::fs::create_dir_all(&path).expect("could not create run tmp directory");
+		return Self { path: path, keep: false };
+	}
+
+	// For when something in it is meant to be looked at afterwards, e.g. replay's regenerated case
+	pub fn keep(mut self) -> PathBuf {
+		self.keep = true;
+		return self.path.clone();
+	}
+
+	pub fn placeholder_values(&self, thread_id : Option<u32>) -> PlaceholderValues {
+		return PlaceholderValues {
+			tmp_dir: self.path.clone(),
+			thread_id: thread_id,
+			case_id: None
+		};
+	}
+}
+
+impl Drop for RunTmpDir {
+	fn drop(&mut self) {
+		if self.keep {
+			return;
+		}
+
+		if let Err(err) = std::fs::remove_dir_all(&self.path) {
+			print!("Could not clean up tmp directory '{}': {}\n", self.path.display(), err);
+		}
+	}
+}
+
+// What the placeholders in compiler_args get filled in with
+#[derive(Debug, Clone)]
+pub struct PlaceholderValues {
+	pub tmp_dir : PathBuf,
+	pub thread_id : Option<u32>,
+	pub case_id : Option<u64>
+}
+
+// The config's compiler_args can use:
+//   ^TMP_FILENAME^ - the object file the compiler writes to, if the compilation has use_temp_file set
+//   ^GENERATED_SOURCE_FILENAME^ - the generated code, written out to a file instead of sent on stdin
+//   ^GENERATED_EXE_FILENAME^ - where the compiler puts the executable, for "exec_backend": "executable"
+//   ^THREAD_ID^, ^CASE_ID^ - which fuzzer thread/case this is (empty if there isn't one)
+// The files are unique per thread and compilation entry, so nothing races even with parallel compilations
+pub fn expand_placeholders(compilation_tests : &Vec<TestCompilation>, values : &PlaceholderValues) -> Vec<TestCompilation> {
+	let thread_str = values.thread_id.map_or("".to_string(), |thread_id| thread_id.to_string());
+	let case_str = values.case_id.map_or("".to_string(), |case_id| case_id.to_string());
+	let file_prefix = values.thread_id.map_or("main".to_string(), |thread_id| format!("thr{}", thread_id));
+
+	let mut expanded_tests = compilation_tests.clone();
+	for (compile_idx, compilation_test) in expanded_tests.iter_mut().enumerate() {
+		let tmp_filename = values.tmp_dir.join(format!("{}_c{}.o", file_prefix, compile_idx)).to_string_lossy().to_string();
+		let source_filename = values.tmp_dir.join(format!("{}_c{}.cpp", file_prefix, compile_idx)).to_string_lossy().to_string();
+		let exe_filename = values.tmp_dir.join(format!("{}_c{}{}", file_prefix, compile_idx, std::env::consts::EXE_SUFFIX)).to_string_lossy().to_string();
+
+		let mut uses_source_file = false;
+		let mut uses_exe_file = false;
+		for arg in compilation_test.compiler_args.iter_mut() {
+			uses_source_file |= arg.contains("^GENERATED_SOURCE_FILENAME^");
+			uses_exe_file |= arg.contains("^GENERATED_EXE_FILENAME^");
+			*arg = arg.replace("^TMP_FILENAME^", &tmp_filename)
+				.replace("^GENERATED_SOURCE_FILENAME^", &source_filename)
+				.replace("^GENERATED_EXE_FILENAME^", &exe_filename)
+				.replace("^THREAD_ID^", &thread_str)
+				.replace("^CASE_ID^", &case_str);
+		}
+
+		if compilation_test.use_tmp_file {
+			compilation_test.tmp_file_name = Some(tmp_filename);
+		}
+
+		if uses_source_file {
+			compilation_test.source_file_name = Some(source_filename);
+		}
+
+		if uses_exe_file {
+			compilation_test.exe_file_name = Some(exe_filename);
+		}
+
+		if compilation_test.exec_backend == ExecBackend::SharedLib {
+			compilation_test.shared_lib_dir = Some(values.tmp_dir.clone());
+		}
+	}
+
+	return expanded_tests;
 }
 
 //#[derive(Clone)]
@@ -35,7 +159,9 @@ pub enum GenCodeResult {
 	Success(Vec<CompiledCodeOutput>), // stdout of program
 	CompilerTimeout,
 	CompilerFailure(i32, String, String),
-	RuntimeDiff(String)
+	RuntimeDiff(String),
+	// The compiler was fine, but we couldn't load what it gave us. Has the object file so it can be saved out
+	ObjectLoadFailure(LoadError, Vec<u8>)
 }
 
 // This is basically saying "send this data to this stdin handle" for a compiler invocation
@@ -60,12 +186,22 @@ pub struct CompilerIOThread {
 
 impl CompilerIOThread {
 	pub fn spawn_io_thread() -> (CompilerIOThreadHandle, std::thread::JoinHandle<()>) {
+		return Self::spawn_io_thread_with_max_compile_jobs(get_default_max_compile_jobs());
+	}
+
+	// Also spawns the compile workers, which get joined along with the IO thread
+	pub fn spawn_io_thread_with_max_compile_jobs(max_compile_jobs : u32) -> (CompilerIOThreadHandle, std::thread::JoinHandle<()>) {
 		
 		let (tx, rx) : (mpsc::Sender<CompilerIOMessage>, mpsc::Receiver<CompilerIOMessage>) = mpsc::channel();
+		let (job_tx, job_rx) : (mpsc::Sender<CompileJobMessage>, mpsc::Receiver<CompileJobMessage>) = mpsc::channel();
 		
 		let io_thread = CompilerIOThread {
 			receiver: rx
 		};
+
+		let num_compile_workers = max_compile_jobs.max(1);
+		let job_rx = Arc::new(Mutex::new(job_rx));
+		let compile_worker_handles : Vec<_> = (0..num_compile_workers).map(|_| spawn_compile_worker(job_rx.clone(), tx.clone())).collect();
 		
 		let join_handle = std::thread::spawn(move || {
 			for msg in io_thread.receiver.iter() {
@@ -78,45 +214,190 @@ impl CompilerIOThread {
 					}
 				}
 			}
+
+			for compile_worker_handle in compile_worker_handles {
+				compile_worker_handle.join().expect("could not join compile worker thread");
+			}
 		});
 		
-		let io_thread_handle = CompilerIOThreadHandle::new(tx);
+		let io_thread_handle = CompilerIOThreadHandle::new(tx, job_tx, num_compile_workers);
 		
 		return (io_thread_handle, join_handle);
 	}
 }
 
+pub fn get_default_max_compile_jobs() -> u32 {
+	return std::thread::available_parallelism().map_or(4, |num_cpus| num_cpus.get() as u32);
+}
+
+// A compile queued up for the scheduler, the result gets sent back on result_sender once a compile worker gets to it
+pub struct CompileJob {
+	compile : TestCompilation,
+	code : Arc<String>,
+	queued_time : Instant,
+	result_sender : mpsc::Sender<CompileJobResult>
+}
+
+pub struct CompileJobResult {
+	result : ProcessResult,
+	queue_wait_time : Duration,
+	compile_time : Duration
+}
+
+pub enum CompileJobMessage {
+	Compile(CompileJob),
+	Exit
+}
+
+// The compile workers all pull jobs off the same queue, so the number of them is the max number of compiler processes running at once.
+// That way the fuzzer threads (generating/executing) and the compilers can be scaled separately
+fn spawn_compile_worker(job_receiver : Arc<Mutex<mpsc::Receiver<CompileJobMessage>>>, io_sender : mpsc::Sender<CompilerIOMessage>) -> std::thread::JoinHandle<()> {
+	return std::thread::spawn(move || {
+		loop {
+			let msg = job_receiver.lock().expect("compile job queue lock poisoned").recv();
+			match msg {
+				Ok(CompileJobMessage::Compile(job)) => {
+					let queue_wait_time = job.queued_time.elapsed();
+					let compile_start = Instant::now();
+					let result = run_process_with_timeout(&job.compile.compiler_exe, &job.compile.compiler_args, &job.code, Some(job.compile.timeout_seconds), &io_sender);
+
+					// If whoever queued it went away, there's nobody to tell
+					let _ = job.result_sender.send(CompileJobResult {
+						result: result,
+						queue_wait_time: queue_wait_time,
+						compile_time: compile_start.elapsed()
+					});
+				}
+				Ok(CompileJobMessage::Exit) | Err(_) => {
+					break;
+				}
+			}
+		}
+	});
+}
+
 #[derive(Debug, Clone)]
 pub struct CompilerIOThreadHandle {
-	sender : mpsc::Sender<CompilerIOMessage>
+	sender : mpsc::Sender<CompilerIOMessage>,
+	compile_job_sender : mpsc::Sender<CompileJobMessage>,
+	num_compile_workers : u32
 }
 
 impl CompilerIOThreadHandle {
-	pub fn new(sender : mpsc::Sender<CompilerIOMessage>) -> Self {
+	pub fn new(sender : mpsc::Sender<CompilerIOMessage>, compile_job_sender : mpsc::Sender<CompileJobMessage>, num_compile_workers : u32) -> Self {
 		Self {
-			sender: sender
+			sender: sender,
+			compile_job_sender: compile_job_sender,
+			num_compile_workers: num_compile_workers
 		}
 	}
 	
 	pub fn send(&self, msg : CompilerIOMessage) {
 		self.sender.send(msg).expect("could not send msg to compiler IO thread");
 	}
+
+	// Puts a compile on the queue, the receiver gets the result once it's done
+	fn queue_compile_job(&self, compile : &TestCompilation, code : &Arc<String>) -> mpsc::Receiver<CompileJobResult> {
+		let (result_sender, result_receiver) = mpsc::channel();
+		let job = CompileJob {
+			compile: compile.clone(),
+			code: code.clone(),
+			queued_time: Instant::now(),
+			result_sender: result_sender
+		};
+
+		self.compile_job_sender.send(CompileJobMessage::Compile(job)).expect("could not send job to compile workers");
+		return result_receiver;
+	}
 	
+	// Should only be called once nothing else is going to queue up compiles
 	pub fn kill_thread(&self) {
+		for _ in 0..self.num_compile_workers {
+			self.compile_job_sender.send(CompileJobMessage::Exit).expect("could not send kill msg to compile worker");
+		}
 		self.sender.send(CompilerIOMessage::Exit).expect("could not send kill msg to compiler IO thread");
 	}
 }
 
+// Kills the process and anything it spawned (e.g. cc1plus under the g++ driver), so nothing is left holding its pipes open
+fn kill_process_tree(pid : u32) {
+	#[cfg(unix)]
+	{
+		// The child is the leader of its own process group, see run_process_with_timeout
+		let kill_res = unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+		if kill_res != 0 {
+			print!("Got error when killing process group {}: {}\n", pid, std::io::Error::last_os_error());
+		}
+	}
+
+	#[cfg(windows)]
+	{
+		let kill_res = Command::new("taskkill").args(["/F", "/T", "/PID", pid.to_string().as_str()]).stdout(Stdio::null()).stderr(Stdio::null()).status();
+		if kill_res.is_err() {
+			print!("Got error when killing process {:?}\n", kill_res);
+		}
+	}
+}
+
+// Waits on a channel for up to timeout, and kills the process if nobody tells it the process finished first.
+// Dropping the sender counts as telling it, so the watchdog goes away as soon as the process is done.
+// NOTE: The watchdog has to be done before the process gets reaped, otherwise its pid could get reused and we'd kill someone else.
+// See wait_for_exit_without_reaping
+pub fn spawn_process_watchdog(pid : u32, timeout : Duration, timed_out : Arc<AtomicBool>) -> (mpsc::Sender<()>, std::thread::JoinHandle<()>) {
+	let (done_sender, done_receiver) = mpsc::channel::<()>();
+	let join_handle = std::thread::spawn(move || {
+		if let Err(mpsc::RecvTimeoutError::Timeout) = done_receiver.recv_timeout(timeout) {
+			timed_out.store(true, Ordering::SeqCst);
+			kill_process_tree(pid);
+		}
+	});
+
+	return (done_sender, join_handle);
+}
+
+// Blocks until the process exits, but leaves it a zombie so its pid (and process group) can't be reused until it's waited on for real
+pub fn wait_for_exit_without_reaping(pid : u32) {
+	#[cfg(unix)]
+	{
+		let mut info : libc::siginfo_t = unsafe { std::mem::zeroed() };
+		loop {
+			let wait_res = unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WNOWAIT) };
+			if wait_res == 0 || std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+				break;
+			}
+		}
+	}
+
+	// Nothing to do on Windows, the handle we have keeps the pid from being reused
+	#[cfg(windows)]
+	{
+		let _ = pid;
+	}
+}
+
 // Returns the output of the process if successful, or the error code if not, or that it timed out
-fn run_process_with_timeout(exe : &str, args : &Vec<String>, input : &str, timeout_seconds : Option<i32>, io_thread_handle : &CompilerIOThreadHandle) -> ProcessResult {
+// Everything blocks (reads on stdout/stderr, then waiting on the process) so we return as soon as the process is done,
+// and a watchdog thread takes care of killing it if it runs past the timeout
+fn run_process_with_timeout(exe : &str, args : &Vec<String>, input : &str, timeout_seconds : Option<i32>, io_sender : &mpsc::Sender<CompilerIOMessage>) -> ProcessResult {
 	//print!("Running process {:?} with args {:?}\n", exe, args);
-	let mut child = Command::new(exe)
-		.args(args)
+	let mut command = Command::new(exe);
+	command.args(args)
 		.stdin(Stdio::piped())
 		.stdout(Stdio::piped())
-		.stderr(Stdio::piped())
-		.spawn()
-		.expect("command failed to start");
+		.stderr(Stdio::piped());
+
+	// Keep compilers out of our process group, so a Ctrl-C on the fuzzer lets it finish the current case instead of killing the compiler too.
+	// It also lets us kill the whole group on a timeout
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		command.process_group(0);
+	}
+
+	let mut child = command.spawn().expect("command failed to start");
+
+	let timed_out = Arc::new(AtomicBool::new(false));
+	let watchdog = timeout_seconds.map(|timeout_seconds| spawn_process_watchdog(child.id(), Duration::from_secs(timeout_seconds as u64), timed_out.clone()));
 	
 	// Send the stdin to the IO thread: this is to ensure that we don't deadlock waiting for buffers to flush while we aren't reading stdout
 	let stdin = child.stdin.take().expect("Failed to open child stdin");
@@ -125,71 +406,164 @@ fn run_process_with_timeout(exe : &str, args : &Vec<String>, input : &str, timeo
 		input: input.to_string()
 	});
 
-	io_thread_handle.send(msg);
-	
-	// https://users.rust-lang.org/t/weird-interaction-between-stdio-piped-and-child-try-wait/65898
+	io_sender.send(msg).expect("could not send msg to compiler IO thread");
+
+	// Same idea for stderr: if we only read stdout, a compiler spewing errors could fill up the stderr pipe and hang
+	let mut stderr = child.stderr.take().expect("Failed to open child stderr");
+	let stderr_thread = std::thread::spawn(move || {
+		let mut stderr_bytes = Vec::<u8>::new();
+		stderr.read_to_end(&mut stderr_bytes).expect("Could not read child stderr");
+		stderr_bytes
+	});
+
 	let mut stdout = child.stdout.take().expect("Failed to open child stdout");
 	let mut stdout_bytes : Vec<u8> = Vec::with_capacity(8192);
+	stdout.read_to_end(&mut stdout_bytes).expect("Could not read child stdout");
+	let stderr_bytes = stderr_thread.join().expect("could not join stderr reader thread");
 
-	let compile_start = Instant::now();
-	loop {
-		if let Some(timeout_seconds) = timeout_seconds {
-			if Instant::now().duration_since(compile_start) > Duration::from_secs(timeout_seconds as u64) {
-				print!("'{}' process timed out (if Windows maybe check we aren't leaking something?)\n", exe);
-				let kill_res = child.kill();
+	wait_for_exit_without_reaping(child.id());
+	if let Some((done_sender, watchdog_join_handle)) = watchdog {
+		drop(done_sender);
+		watchdog_join_handle.join().expect("could not join process watchdog thread");
+	}
 
-				if kill_res.is_err() {
-					print!("Got error when killing process {:?}\n", kill_res);
-				}
+	let status = child.wait().expect("Could not finish waiting for child");
 
-				// Let's see if this helps...
-				std::thread::sleep(Duration::from_millis(500));
-				return ProcessResult::Timeout;
-			}
-		}
+	if timed_out.load(Ordering::SeqCst) {
+		print!("'{}' process timed out\n", exe);
+		return ProcessResult::Timeout;
+	}
+	
+	if status.success() {
+		return ProcessResult::Success(stdout_bytes);
+	}
+	else {
+		// stdout isn't printable since it's code output, but stderr still good tho
+		let proc_stderr = String::from_utf8_lossy(&stderr_bytes);
+		return ProcessResult::Error(get_status_code(&status), "".to_string(), proc_stderr.to_string());
+	}
+}
 
-		match child.try_wait() {
-			Ok(Some(_)) => { break; }
-			Ok(None) => {
-				const CHUNK_BYTE_SIZE : usize = 4096;
+// If it died from a signal there's no exit code, so report it the way a shell would
+pub fn get_status_code(status : &std::process::ExitStatus) -> i32 {
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::ExitStatusExt;
+		return status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0));
+	}
+	#[cfg(not(unix))]
+	{
+		return status.code().expect("failed to get exit code");
+	}
+}
 
-				// Child isn't finished, try reading some bytes from its stdout
-				loop {
-					let mut buf = [0; CHUNK_BYTE_SIZE];
-					let num_bytes = stdout.read(&mut buf).expect("Failed to read child stdout bytes");
-					stdout_bytes.extend_from_slice(&buf[..num_bytes]);
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum CompileEntryOutcome {
+	#[default]
+	Success,
+	Failure,
+	Timeout,
+	LoadFailure
+}
 
-					// We probably need to wait to read some more,
-					// so don't try to keep reading
-					if num_bytes < CHUNK_BYTE_SIZE {
-						break;
+#[derive(Default, Debug, Clone, Copy)]
+pub struct CompilationEntryTimings {
+	// How long it sat on the compile queue waiting for a free worker
+	pub queue_wait_time : Duration,
+	pub compile_time : Duration,
+	pub obj_parse_time : Duration,
+	pub outcome : CompileEntryOutcome
+}
+
+// How long each compilation entry took, None if we bailed out before getting to it
+#[derive(Default, Debug, Clone)]
+pub struct CompilationTimings {
+	pub entries : Vec<Option<CompilationEntryTimings>>
+}
+
+pub fn test_generated_code_compilation(code : &str, compiles : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle) -> GenCodeResult {
+	let mut timings = CompilationTimings::default();
+	return test_generated_code_compilation_with_timings(code, compiles, io_thread_handle, &mut timings);
+}
+
+// Turns a finished compile into its code page, or the failure if it didn't work
+fn handle_compile_result(compile : &TestCompilation, job_result_receiver : mpsc::Receiver<CompileJobResult>, entry_timings : &mut CompilationEntryTimings) -> Result<CompiledCodeOutput, GenCodeResult> {
+	let job_result = job_result_receiver.recv().expect("compile worker went away before finishing the job");
+	entry_timings.queue_wait_time = job_result.queue_wait_time;
+	entry_timings.compile_time = job_result.compile_time;
+
+	match job_result.result {
+		ProcessResult::Error(err_code, stdout, stderr) => {
+			println!("COMPILER ERR: {}", stderr);
+			entry_timings.outcome = CompileEntryOutcome::Failure;
+			return Err(GenCodeResult::CompilerFailure(err_code, stdout, stderr));
+		}
+		ProcessResult::Timeout => {
+			entry_timings.outcome = CompileEntryOutcome::Timeout;
+			return Err(GenCodeResult::CompilerTimeout);
+		}
+		ProcessResult::Success(_) if compile.exec_backend == ExecBackend::Executable => {
+			// Nothing to load, the exe just gets run as-is
+			let exe_file_name = compile.exe_file_name.as_ref().expect("");
+			match GeneratedExe::take(exe_file_name, compile.timeout_seconds) {
+				Ok(exe) => {
+					return Ok(CompiledCodeOutput { code_page: ExecPage::from_external_code(ExternalCode::Executable(exe)) });
+				}
+				Err(load_error) => {
+					println!("OBJ LOAD ERR: {}", load_error);
+					entry_timings.outcome = CompileEntryOutcome::LoadFailure;
+					return Err(GenCodeResult::ObjectLoadFailure(load_error, Vec::new()));
+				}
+			}
+		}
+		ProcessResult::Success(proc_output) => {
+			let parse_start = Instant::now();
+			let compiled_out = if compile.use_tmp_file {
+				let tmp_file_name = compile.tmp_file_name.as_ref().expect("");
+				match std::fs::read(tmp_file_name) {
+					Ok(compiled_out) => compiled_out,
+					Err(err) => {
+						entry_timings.outcome = CompileEntryOutcome::LoadFailure;
+						return Err(GenCodeResult::ObjectLoadFailure(LoadError::BadObjectFile(format!("could not read '{}': {}", tmp_file_name, err)), Vec::new()));
 					}
 				}
-
-				std::thread::sleep(Duration::from_millis(100));
 			}
-			Err(e) => panic!("error attempting to wait: {}", e)
+			else {
+				proc_output
+			};
+
+			let code_page = match compile.exec_backend {
+				ExecBackend::ObjLoader => load_obj_file(&compiled_out, "do_stuff"),
+				ExecBackend::SharedLib => SharedLib::load(compiled_out.clone(), compile.shared_lib_dir.as_ref().expect("")).map(|shared_lib| ExecPage::from_external_code(ExternalCode::SharedLib(shared_lib))),
+				ExecBackend::Executable => unreachable!()
+			};
+			entry_timings.obj_parse_time = parse_start.elapsed();
+			match code_page {
+				Ok(code_page) => {
+					return Ok(CompiledCodeOutput { code_page: code_page });
+				}
+				Err(load_error) => {
+					println!("OBJ LOAD ERR: {}", load_error);
+					entry_timings.outcome = CompileEntryOutcome::LoadFailure;
+					return Err(GenCodeResult::ObjectLoadFailure(load_error, compiled_out));
+				}
+			}
 		}
 	}
+}
 
-	let status = child.wait().expect("Could not finish waiting for child");
-	stdout.read_to_end(&mut stdout_bytes).expect("Could not read remaining bytes from child stdout");
-	
-	if status.success() {
-		return ProcessResult::Success(stdout_bytes);
-	}
-	else {
-		let status_code = status.code().expect("failed to get exit code");
-		//let proc_stdout = String::from_utf8_lossy(&output.stdout);
-		// TODO:
-		let proc_stderr = "".to_string();//String::from_utf8_lossy(&output.stderr);
-		// TODO: stdout no longer printable since it's code output
-		// stderr still good tho
-		return ProcessResult::Error(status_code, "".to_string(), proc_stderr.to_string());
-	}
+pub fn test_generated_code_compilation_with_timings(code : &str, compiles : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle,
+		timings : &mut CompilationTimings) -> GenCodeResult {
+	let (result, _) = test_generated_code_compilation_with_all_failures(code, compiles, io_thread_handle, timings);
+	return result;
 }
 
-pub fn test_generated_code_compilation(code : &str, compiles : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle) -> GenCodeResult {
+// Same as above, but also gives back every other entry that failed along with its index in compiles, in config order.
+// Only parallel compilations can have any, the sequential path stops at the first failure
+pub fn test_generated_code_compilation_with_all_failures(code : &str, compiles : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle,
+		timings : &mut CompilationTimings) -> (GenCodeResult, Vec<(usize, GenCodeResult)>) {
+	timings.entries = vec![None; compiles.len()];
+
 	// TODO: better way?
 	//std::fs::write(&compiles[0].code_filename, code).expect("couldn't write to file?");
 	
@@ -198,32 +572,63 @@ pub fn test_generated_code_compilation(code : &str, compiles : &Vec<TestCompilat
 	//println!("-------CODE-----------------");
 	//println!("{}", code);
 	//println!("----------------------------");
-	
-	for compile in compiles {
-		let compile_result = run_process_with_timeout(&compile.compiler_exe, &compile.compiler_args, code, Some(compile.timeout_seconds), io_thread_handle);
 
-		match compile_result {
-			ProcessResult::Error(err_code, stdout, stderr) => {
-				println!("COMPILER ERR: {}", stderr);
-				return GenCodeResult::CompilerFailure(err_code, stdout, stderr);
-			}
-			ProcessResult::Timeout => {
-				return GenCodeResult::CompilerTimeout;
+	let exe_code = if compiles.iter().any(|compile| compile.exec_backend == ExecBackend::Executable) {
+		Arc::new(format!("{}\n{}", code, EXE_HARNESS_MAIN))
+	}
+	else {
+		Arc::new(String::new())
+	};
+	let code = Arc::new(code.to_string());
+	let no_code = Arc::new(String::new());
+
+	// Compilers that read the code from a file get nothing on stdin. Executables also need a main() to go with the code
+	let queue_compile_job = |compile : &TestCompilation| {
+		let code = if compile.exec_backend == ExecBackend::Executable { &exe_code } else { &code };
+		if let Some(source_file_name) = &compile.source_file_name {
+			std::fs::write(source_file_name, code.as_bytes()).expect("couldn't write to file?");
+			return io_thread_handle.queue_compile_job(compile, &no_code);
+		}
+
+		return io_thread_handle.queue_compile_job(compile, code);
+	};
+
+	if compiles.len() > 1 && compiles.iter().any(|compile| compile.run_in_parallel) {
+		// Queue up every compilation at once and look at all of them, so we hear about every entry that fails and not just the first.
+		// The obj parsing stays on this thread, the compile workers just hand back the compiler's output
+		let job_result_receivers : Vec<_> = compiles.iter().map(|compile| queue_compile_job(compile)).collect();
+
+		// If more than one failed, the first one in config order is the result so it doesn't depend on which finished first
+		let mut failures = Vec::<(usize, GenCodeResult)>::new();
+		for (compile_idx, (compile, job_result_receiver)) in compiles.iter().zip(job_result_receivers.into_iter()).enumerate() {
+			let mut entry_timings = CompilationEntryTimings::default();
+			match handle_compile_result(compile, job_result_receiver, &mut entry_timings) {
+				Ok(compiled_output) => { generated_codes.push(compiled_output); }
+				Err(failure) => { failures.push((compile_idx, failure)); }
 			}
-			ProcessResult::Success(proc_output) => {
-				let code_page = if compile.use_tmp_file {
-					let compiled_out = std::fs::read(compile.tmp_file_name.as_ref().unwrap()).unwrap();
-					parse_obj_file(&compiled_out, "do_stuff").unwrap()
-				}
-				else {
-					parse_obj_file(&proc_output, "do_stuff").unwrap()
-				};
-				generated_codes.push(CompiledCodeOutput { code_page: code_page });
+			timings.entries[compile_idx] = Some(entry_timings);
+		}
+
+		if failures.len() > 0 {
+			let (_, first_failure) = failures.remove(0);
+			return (first_failure, failures);
+		}
+	}
+	else {
+		for (compile_idx, compile) in compiles.iter().enumerate() {
+			let job_result_receiver = queue_compile_job(compile);
+			let mut entry_timings = CompilationEntryTimings::default();
+			let compiled_output = handle_compile_result(compile, job_result_receiver, &mut entry_timings);
+			timings.entries[compile_idx] = Some(entry_timings);
+
+			match compiled_output {
+				Ok(compiled_output) => { generated_codes.push(compiled_output); }
+				Err(failure) => { return (failure, Vec::new()); }
 			}
 		}
 	}
 
-	return GenCodeResult::Success(generated_codes);
+	return (GenCodeResult::Success(generated_codes), Vec::new());
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -233,73 +638,59 @@ pub enum GenCodeFuzzMode {
 	CrashAndOptBait
 }
 
-fn parse_fuzz_mode(mode_str : &str) -> GenCodeFuzzMode {
-	if mode_str == "crash+optbait" {
-		return GenCodeFuzzMode::CrashAndOptBait;
-	}
-	else if mode_str == "crash+diff" {
-		return GenCodeFuzzMode::CrashAndDiff;
-	}
-	else if mode_str == "crash" {
-		return GenCodeFuzzMode::CrashOnly;
-	}
-	else {
-		panic!("Could not understand testing mode '{}'", mode_str);
-	}
-}
-
 pub struct CompilationConfig {
 	pub compilations : Vec<TestCompilation>,
 	pub fuzz_mode : GenCodeFuzzMode,
 	pub mitigations : BTreeSet<String>,
-	pub extra_config : serde_json::Value
+	pub extra_config : serde_json::Value,
+	// If set, runtime diffs get built with this and re-run, to see if it's UB in the generated code (see sanitizer_check)
+	pub sanitizer_compilation : Option<TestCompilation>
 }
 
-// return struct at some point
-pub fn parse_compiler_config(config : &str) -> CompilationConfig {
-	let config_json : serde_json::Value = serde_json::from_str(config).expect("Could not parse JSON");
-	
-	let timeout = config_json["compilation_timeout_seconds"].as_i64().expect("could not parse compilation_timeout_seconds") as i32;
-	
-	let fuzz_mode_str = config_json["mode"].as_str().expect("could not parse mode");
-	let fuzz_mode = parse_fuzz_mode(fuzz_mode_str);
-	
-	let mut test_compilations = Vec::<TestCompilation>::with_capacity(8);
-	
-	for compilation in config_json["compilations"].as_array().expect("compilations must be an array") {
-		let compiler_exe = compilation["compiler_exe"].as_str().expect("Could not parse compiler_exe").to_string();
-		let mut compiler_args = Vec::<String>::with_capacity(8);
-		for compiler_arg in compilation["compiler_args"].as_array().expect("compiler_args must be an array") {
-			compiler_args.push(compiler_arg.as_str().expect("compiler_args must contain strings").to_string());
-		}
-		
-		let use_temp_file = compilation["use_temp_file"].as_bool().unwrap_or(false);
-		
-		test_compilations.push(TestCompilation {
-			compiler_exe : compiler_exe,
-			compiler_args :compiler_args,
-			timeout_seconds : timeout,
-			tmp_file_name: None, // will be filled in later...yeah could be better
-			use_tmp_file: use_temp_file
-		});
-	}
-	
-	let mut mitigations = BTreeSet::<String>::new();
-	if let Some(mitigations_json) = config_json["mitigations"].as_array() {
-		for mitigation in mitigations_json {
-			mitigations.insert(mitigation.as_str().expect("mitigations must contain strings").to_string());
-		}
-	}
 
-	let extra_config = config_json["extra_config"].clone();
 
-	return CompilationConfig {
-		compilations: test_compilations,
-		fuzz_mode: fuzz_mode,
-		mitigations: mitigations,
-		extra_config: extra_config
+
+
+#[test]
+fn test_parallel_compilation_gives_back_every_failure() {
+	let make_compile = |exit_code : i32| TestCompilation {
+		compiler_exe: "sh".to_string(),
+		compiler_args: vec!["-c".to_string(), format!("cat > /dev/null; exit {}", exit_code)],
+		timeout_seconds: 5,
+		tmp_file_name: None,
+		use_tmp_file: false,
+		source_file_name: None,
+		exe_file_name: None,
+		shared_lib_dir: None,
+		run_in_parallel: true,
+		exec_backend: ExecBackend::ObjLoader
 	};
+
+	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread_with_max_compile_jobs(3);
+	let compiles = vec![make_compile(3), make_compile(4), make_compile(5)];
+	let mut timings = CompilationTimings::default();
+	let (result, other_failures) = test_generated_code_compilation_with_all_failures("int x;", &compiles, &io_thread_handle, &mut timings);
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("");
+
+	assert!(matches!(result, GenCodeResult::CompilerFailure(3, _, _)));
+	let other_failures : Vec<(usize, i32)> = other_failures.iter().map(|(compile_idx, failure)| match failure {
+		GenCodeResult::CompilerFailure(err_code, _, _) => (*compile_idx, *err_code),
+		_ => panic!("should have been a compiler failure")
+	}).collect();
+	assert_eq!(other_failures, vec![(1, 4), (2, 5)]);
 }
 
+#[test]
+fn test_compile_timeout_kills_process_group() {
+	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread_with_max_compile_jobs(1);
+	let start_time = Instant::now();
 
+	// The backgrounded sleep keeps stdout open, so this only finishes once the whole group is gone
+	let result = run_process_with_timeout("sh", &vec!["-c".to_string(), "sleep 30 & sleep 30".to_string()], "", Some(1), &io_thread_handle.sender);
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("");
 
+	assert!(matches!(result, ProcessResult::Timeout));
+	assert!(start_time.elapsed() < Duration::from_secs(10));
+}
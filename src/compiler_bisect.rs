@@ -0,0 +1,218 @@
+
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::compilation_config::{test_generated_code_compilation, TestCompilation, GenCodeResult, CompiledCodeOutput, CompilerIOThreadHandle};
+use crate::saved_findings::{SavedFinding, FindingCategory};
+
+#[derive(Debug, PartialEq)]
+pub enum BisectResult {
+	// Indices into the candidate list
+	FirstBad { last_good : usize, first_bad : usize },
+	// Even the oldest build has the issue, so we can't say where it came from
+	AllBad,
+	// The newest build doesn't have the issue, so it's either fixed or flaky
+	NoneBad
+}
+
+// Assumes the candidates are ordered oldest to newest, and that once a build goes bad it stays bad
+pub fn bisect_first_bad<F : FnMut(usize) -> bool>(num_candidates : usize, mut is_bad : F) -> BisectResult {
+	if num_candidates == 0 || !is_bad(num_candidates - 1) {
+		return BisectResult::NoneBad;
+	}
+
+	if num_candidates == 1 || is_bad(0) {
+		return BisectResult::AllBad;
+	}
+
+	let mut last_good = 0;
+	let mut first_bad = num_candidates - 1;
+	while first_bad - last_good > 1 {
+		let mid = last_good + (first_bad - last_good) / 2;
+		if is_bad(mid) {
+			first_bad = mid;
+		}
+		else {
+			last_good = mid;
+		}
+	}
+
+	return BisectResult::FirstBad { last_good: last_good, first_bad: first_bad };
+}
+
+// Compares names with the numbers in them going by value, so r9 comes before r10 and llvm-9 before llvm-12
+pub fn compare_natural(a : &str, b : &str) -> Ordering {
+	let mut a_chars = a.chars().peekable();
+	let mut b_chars = b.chars().peekable();
+	loop {
+		match (a_chars.peek().cloned(), b_chars.peek().cloned()) {
+			(None, None) => { return Ordering::Equal; }
+			(None, Some(_)) => { return Ordering::Less; }
+			(Some(_), None) => { return Ordering::Greater; }
+			(Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+				let mut a_num = String::new();
+				while let Some(digit) = a_chars.next_if(|c| c.is_ascii_digit()) {
+					a_num.push(digit);
+				}
+				let mut b_num = String::new();
+				while let Some(digit) = b_chars.next_if(|c| c.is_ascii_digit()) {
+					b_num.push(digit);
+				}
+
+				// Without the leading zeros, a longer number is a bigger one, and the same length compares as a string
+				let a_trimmed = a_num.trim_start_matches('0');
+				let b_trimmed = b_num.trim_start_matches('0');
+				let num_ordering = a_trimmed.len().cmp(&b_trimmed.len()).then_with(|| a_trimmed.cmp(b_trimmed));
+				if num_ordering != Ordering::Equal {
+					return num_ordering;
+				}
+			}
+			(Some(a_char), Some(b_char)) => {
+				if a_char != b_char {
+					return a_char.cmp(&b_char);
+				}
+				a_chars.next();
+				b_chars.next();
+			}
+		}
+	}
+}
+
+// If given a single directory, each entry in it (sorted by name, see compare_natural) is a candidate: either the compiler itself,
+// or an install dir containing it at compiler_rel_path (e.g. a directory of LLVM nightly installs named by revision)
+// Otherwise, the args are the compiler binaries themselves, in order
+pub fn get_bisect_compiler_list(compiler_args : &[String], compiler_rel_path : &str) -> Vec<String> {
+	if compiler_args.len() == 1 && Path::new(&compiler_args[0]).is_dir() {
+		let mut entries = Vec::<String>::new();
+		let dir_entries = std::fs::read_dir(&compiler_args[0]).expect("could not read compiler directory");
+		for entry in dir_entries {
+			let entry_path = entry.expect("could not read compiler directory entry").path();
+			let compiler_path = if entry_path.is_dir() { entry_path.join(compiler_rel_path) } else { entry_path.clone() };
+			if compiler_path.is_file() {
+				entries.push(compiler_path.to_string_lossy().to_string());
+			}
+			else {
+				print!("Skipping '{}', no compiler found at '{}'\n", entry_path.display(), compiler_path.display());
+			}
+		}
+
+		entries.sort_by(|a, b| compare_natural(a, b));
+		return entries;
+	}
+
+	return compiler_args.to_vec();
+}
+
+// Swaps out the compiler for each compilation, or only the ones using replace_exe if specified
+// (e.g. when diffing GCC against a Clang nightly, we only want to bisect Clang)
+pub fn replace_compiler_exe(compilation_tests : &Vec<TestCompilation>, replace_exe : Option<&str>, new_exe : &str) -> Vec<TestCompilation> {
+	let mut new_compilation_tests = compilation_tests.clone();
+	for compilation_test in new_compilation_tests.iter_mut() {
+		if replace_exe.is_none() || replace_exe == Some(compilation_test.compiler_exe.as_str()) {
+			compilation_test.compiler_exe = new_exe.to_string();
+		}
+	}
+
+	return new_compilation_tests;
+}
+
+// Returns what category of finding the code produces with these compilations, or None if it runs fine
+pub fn get_finding_category_for_compilations(code : &str, compilation_tests : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle,
+		runtime_diff_check : &dyn Fn(&Vec<CompiledCodeOutput>) -> bool) -> Option<FindingCategory> {
+	let res = test_generated_code_compilation(code, compilation_tests, io_thread_handle);
+	if let GenCodeResult::Success(ref compiled_outputs) = res {
+		if runtime_diff_check(compiled_outputs) {
+			return Some(FindingCategory::RuntimeDiff);
+		}
+	}
+
+	return FindingCategory::from_result(&res);
+}
+
+pub fn bisect_finding(finding : &SavedFinding, compilers : &Vec<String>, compilation_tests : &Vec<TestCompilation>, replace_exe : Option<&str>,
+		io_thread_handle : &CompilerIOThreadHandle, runtime_diff_check : &dyn Fn(&Vec<CompiledCodeOutput>) -> bool) -> BisectResult {
+	print!("Bisecting {} finding {} across {} compilers\n", finding.category.dir_name(), finding.hash, compilers.len());
+
+	let bisect_result = bisect_first_bad(compilers.len(), |idx| {
+		let compiler = &compilers[idx];
+		let compilation_tests = replace_compiler_exe(compilation_tests, replace_exe, compiler);
+		let category = get_finding_category_for_compilations(&finding.code, &compilation_tests, io_thread_handle, runtime_diff_check);
+		let is_bad = (category == Some(finding.category));
+		print!("  [{}/{}] {} -> {}\n", idx + 1, compilers.len(), compiler, if is_bad { "bad" } else { "good" });
+		is_bad
+	});
+
+	let bisect_json = match bisect_result {
+		BisectResult::FirstBad { last_good, first_bad } => {
+			print!("First bad compiler is '{}' (last good '{}')\n", compilers[first_bad], compilers[last_good]);
+			serde_json::json!({
+				"category": finding.category.dir_name(),
+				"result": "first_bad",
+				"last_good": compilers[last_good],
+				"first_bad": compilers[first_bad],
+				"compilers": compilers
+			})
+		}
+		BisectResult::AllBad => {
+			print!("All compilers reproduce the issue, cannot bisect further\n");
+			serde_json::json!({
+				"category": finding.category.dir_name(),
+				"result": "all_bad",
+				"compilers": compilers
+			})
+		}
+		BisectResult::NoneBad => {
+			print!("Newest compiler does not reproduce the issue, cannot bisect\n");
+			serde_json::json!({
+				"category": finding.category.dir_name(),
+				"result": "none_bad",
+				"compilers": compilers
+			})
+		}
+	};
+
+	let bisect_filename = finding.artifact_filename("bisect.json");
+	std::fs::write(&bisect_filename, serde_json::to_string_pretty(&bisect_json).expect("")).expect("couldn't write to file?");
+	print!("Wrote bisect result to '{}'\n", bisect_filename.display());
+
+	return bisect_result;
+}
+
+#[test]
+fn test_bisect_first_bad_01() {
+	for num_candidates in 2..20 {
+		for first_bad in 1..num_candidates {
+			let res = bisect_first_bad(num_candidates, |idx| idx >= first_bad);
+			assert_eq!(res, BisectResult::FirstBad { last_good: first_bad - 1, first_bad: first_bad });
+		}
+	}
+}
+
+#[test]
+fn test_bisect_first_bad_02() {
+	assert_eq!(bisect_first_bad(0, |_| true), BisectResult::NoneBad);
+	assert_eq!(bisect_first_bad(1, |_| true), BisectResult::AllBad);
+	assert_eq!(bisect_first_bad(1, |_| false), BisectResult::NoneBad);
+	assert_eq!(bisect_first_bad(5, |_| true), BisectResult::AllBad);
+	assert_eq!(bisect_first_bad(5, |idx| idx < 3), BisectResult::NoneBad);
+}
+
+#[test]
+fn test_get_bisect_compiler_list_sorts_by_revision() {
+	use crate::compilation_config::RunTmpDir;
+
+	let compilers_dir = RunTmpDir::create("test_bisect_compilers");
+	for revision in ["r10", "r9", "r100", "r2", "r09a"] {
+		let bin_dir = compilers_dir.path.join(revision).join("bin");
+		std::fs::create_dir_all(&bin_dir).expect("");
+		std::fs::write(bin_dir.join("clang++"), "").expect("");
+	}
+
+	let compilers = get_bisect_compiler_list(&[compilers_dir.path.to_string_lossy().to_string()], "bin/clang++");
+	let revisions : Vec<String> = compilers.iter().map(|compiler| Path::new(compiler).parent().expect("").parent().expect("").file_name().expect("").to_string_lossy().to_string()).collect();
+	assert_eq!(revisions, vec!["r2", "r9", "r09a", "r10", "r100"]);
+
+	assert_eq!(compare_natural("llvm-9", "llvm-12"), Ordering::Less);
+	assert_eq!(compare_natural("gcc-12.2", "gcc-12.10"), Ordering::Less);
+	assert_eq!(compare_natural("abc", "abc"), Ordering::Equal);
+}
@@ -0,0 +1,867 @@
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::compilation_config::{CompilationConfig, TestCompilation, GenCodeFuzzMode, ExecBackend};
+
+// Something wrong (or suspicious) in the config, path is where in the JSON it is, e.g. "$.compilations[1].compiler_exe"
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+	pub path : String,
+	pub message : String
+}
+
+impl std::fmt::Display for ConfigIssue {
+	fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.path, self.message)
+	}
+}
+
+// Everything that came up while parsing, so the whole config can be fixed in one go instead of one panic at a time
+#[derive(Debug, Clone, Default)]
+pub struct ConfigReport {
+	pub errors : Vec<ConfigIssue>,
+	pub warnings : Vec<ConfigIssue>
+}
+
+impl ConfigReport {
+	fn error(&mut self, path : &str, message : String) {
+		self.errors.push(ConfigIssue { path: path.to_string(), message: message });
+	}
+
+	fn warning(&mut self, path : &str, message : String) {
+		self.warnings.push(ConfigIssue { path: path.to_string(), message: message });
+	}
+
+	fn remove_duplicates(&mut self) {
+		let mut seen = BTreeSet::<(String, String)>::new();
+		self.errors.retain(|issue| seen.insert((issue.path.clone(), issue.message.clone())));
+		seen.clear();
+		self.warnings.retain(|issue| seen.insert((issue.path.clone(), issue.message.clone())));
+	}
+}
+
+const TOP_LEVEL_KEYS : [&str; 9] = ["compilations", "compilation_timeout_seconds", "mode", "mitigations", "parallel_compilations", "extra_config", "variables", "include", "sanitizer_compilation"];
+const COMPILATION_KEYS : [&str; 6] = ["compiler_exe", "compiler_args", "use_temp_file", "timeout_seconds", "matrix", "exec_backend"];
+
+// Keys people have actually gotten wrong, which are close enough to a real one that we should point it out
+const KNOWN_KEY_MIXUPS : [(&str, &str); 3] = [
+	("use_tmp_file", "use_temp_file"),
+	("compile_timeout_seconds", "compilation_timeout_seconds"),
+	("timeout", "timeout_seconds")
+];
+
+pub fn parse_fuzz_mode(mode_str : &str) -> Option<GenCodeFuzzMode> {
+	match mode_str {
+		"crash+optbait" => Some(GenCodeFuzzMode::CrashAndOptBait),
+		"crash+diff" => Some(GenCodeFuzzMode::CrashAndDiff),
+		"crash" => Some(GenCodeFuzzMode::CrashOnly),
+		_ => None
+	}
+}
+
+pub fn parse_exec_backend(backend_str : &str) -> Option<ExecBackend> {
+	match backend_str {
+		"obj_loader" => Some(ExecBackend::ObjLoader),
+		"shared_lib" => Some(ExecBackend::SharedLib),
+		"executable" => Some(ExecBackend::Executable),
+		_ => None
+	}
+}
+
+fn get_edit_distance(a : &str, b : &str) -> usize {
+	let b_chars : Vec<char> = b.chars().collect();
+	let mut prev_row : Vec<usize> = (0..=b_chars.len()).collect();
+	for (ii, a_char) in a.chars().enumerate() {
+		let mut row = vec![ii + 1; b_chars.len() + 1];
+		for (jj, b_char) in b_chars.iter().enumerate() {
+			let substitute_cost = if a_char == *b_char { 0 } else { 1 };
+			row[jj + 1] = (prev_row[jj] + substitute_cost).min(prev_row[jj + 1] + 1).min(row[jj] + 1);
+		}
+		prev_row = row;
+	}
+
+	return prev_row[b_chars.len()];
+}
+
+fn warn_on_unknown_keys(object : &serde_json::Map<String, serde_json::Value>, known_keys : &[&str], path : &str, report : &mut ConfigReport) {
+	for key in object.keys() {
+		if known_keys.contains(&key.as_str()) {
+			continue;
+		}
+
+		let suggestion = KNOWN_KEY_MIXUPS.iter().find(|(wrong_key, _)| wrong_key == key).map(|(_, right_key)| *right_key)
+			.or_else(|| known_keys.iter().find(|known_key| get_edit_distance(key, known_key) <= 2).copied());
+
+		match suggestion {
+			Some(suggestion) => report.warning(&format!("{}.{}", path, key), format!("unknown key, did you mean '{}'? (it will be ignored)", suggestion)),
+			None => report.warning(&format!("{}.{}", path, key), "unknown key (it will be ignored)".to_string())
+		}
+	}
+}
+
+fn parse_optional_bool(value : &serde_json::Value, path : &str, report : &mut ConfigReport) -> bool {
+	if value.is_null() {
+		return false;
+	}
+
+	return value.as_bool().unwrap_or_else(|| {
+		report.error(path, format!("expected true or false, got {}", value));
+		false
+	});
+}
+
+fn parse_timeout_seconds(value : &serde_json::Value, path : &str, report : &mut ConfigReport) -> Option<i32> {
+	match value.as_i64() {
+		Some(timeout) if timeout > 0 && timeout <= i32::MAX as i64 => Some(timeout as i32),
+		_ => {
+			report.error(path, format!("expected a positive number of seconds, got {}", value));
+			None
+		}
+	}
+}
+
+fn parse_compilation(compilation : &serde_json::Value, path : &str, default_timeout : Option<i32>, report : &mut ConfigReport) -> Option<TestCompilation> {
+	let compilation_object = match compilation.as_object() {
+		Some(compilation_object) => compilation_object,
+		None => {
+			report.error(path, format!("expected an object, got {}", compilation));
+			return None;
+		}
+	};
+
+	warn_on_unknown_keys(compilation_object, &COMPILATION_KEYS, path, report);
+
+	let compiler_exe = match compilation["compiler_exe"].as_str() {
+		Some(compiler_exe) if !compiler_exe.is_empty() => Some(compiler_exe.to_string()),
+		_ => {
+			report.error(&format!("{}.compiler_exe", path), format!("expected the compiler to run, got {}", compilation["compiler_exe"]));
+			None
+		}
+	};
+
+	let mut compiler_args = Vec::<String>::with_capacity(8);
+	if !compilation["compiler_args"].is_null() {
+		match compilation["compiler_args"].as_array() {
+			Some(args_json) => {
+				for (arg_idx, arg) in args_json.iter().enumerate() {
+					match arg.as_str() {
+						Some(arg) => compiler_args.push(arg.to_string()),
+						None => report.error(&format!("{}.compiler_args[{}]", path, arg_idx), format!("expected a string, got {}", arg))
+					}
+				}
+			}
+			None => report.error(&format!("{}.compiler_args", path), format!("expected an array of strings, got {}", compilation["compiler_args"]))
+		}
+	}
+
+	let use_temp_file = parse_optional_bool(&compilation["use_temp_file"], &format!("{}.use_temp_file", path), report);
+	if use_temp_file && !compiler_args.iter().any(|arg| arg.contains("^TMP_FILENAME^")) {
+		report.warning(&format!("{}.use_temp_file", path), "set, but none of the compiler_args use ^TMP_FILENAME^".to_string());
+	}
+
+	// Asking for an executable is enough to get the executable backend
+	let uses_exe_file = compiler_args.iter().any(|arg| arg.contains("^GENERATED_EXE_FILENAME^"));
+	let exec_backend = match &compilation["exec_backend"] {
+		serde_json::Value::Null if uses_exe_file => ExecBackend::Executable,
+		serde_json::Value::Null => ExecBackend::ObjLoader,
+		backend_json => {
+			let exec_backend = backend_json.as_str().and_then(parse_exec_backend);
+			if exec_backend.is_none() {
+				report.error(&format!("{}.exec_backend", path), format!("expected 'obj_loader', 'shared_lib' or 'executable', got {}", backend_json));
+			}
+			exec_backend.unwrap_or_default()
+		}
+	};
+
+	if exec_backend == ExecBackend::Executable {
+		if !uses_exe_file {
+			report.error(&format!("{}.compiler_args", path), "exec_backend 'executable' needs one of the compiler_args to use ^GENERATED_EXE_FILENAME^".to_string());
+		}
+
+		if compiler_args.iter().any(|arg| arg == "-c") {
+			report.error(&format!("{}.compiler_args", path), "'-c' stops before linking, so there won't be an executable for exec_backend 'executable'".to_string());
+		}
+	}
+	else if uses_exe_file {
+		report.warning(&format!("{}.compiler_args", path), "^GENERATED_EXE_FILENAME^ is only used by exec_backend 'executable'".to_string());
+	}
+
+	// The shared lib needs to actually get linked, so make sure it does. The linker wants to seek around in its output too,
+	// so it has to go to a file instead of stdout
+	if exec_backend == ExecBackend::SharedLib {
+		for needed_arg in ["-shared", "-fPIC"] {
+			if !compiler_args.iter().any(|arg| arg == needed_arg) {
+				compiler_args.push(needed_arg.to_string());
+			}
+		}
+
+		if compiler_args.iter().any(|arg| arg == "-c") {
+			report.error(&format!("{}.compiler_args", path), "'-c' stops before linking, so there won't be a shared lib for exec_backend 'shared_lib'".to_string());
+		}
+
+		if !use_temp_file {
+			report.error(&format!("{}.use_temp_file", path), "exec_backend 'shared_lib' needs use_temp_file, since the shared lib can't be written to stdout".to_string());
+		}
+	}
+
+	let timeout_seconds = if compilation["timeout_seconds"].is_null() {
+		if default_timeout.is_none() {
+			report.error(&format!("{}.timeout_seconds", path), "no timeout for this compilation, set it here or set compilation_timeout_seconds".to_string());
+		}
+		default_timeout
+	}
+	else {
+		parse_timeout_seconds(&compilation["timeout_seconds"], &format!("{}.timeout_seconds", path), report)
+	};
+
+	return Some(TestCompilation {
+		compiler_exe: compiler_exe?,
+		compiler_args: compiler_args,
+		timeout_seconds: timeout_seconds?,
+		tmp_file_name: None, // will be filled in later by expand_placeholders
+		use_tmp_file: use_temp_file,
+		source_file_name: None,
+		exe_file_name: None,
+		shared_lib_dir: None,
+		run_in_parallel: false,
+		exec_backend: exec_backend
+	});
+}
+
+// Variables are used as ${NAME} in compiler_exe/compiler_args. A list can only be used as a whole arg, and turns into several args,
+// e.g. "TARGET": ["-march=haswell", "-mtune=haswell"]
+#[derive(Debug, Clone)]
+enum ConfigVariable {
+	Single(String),
+	List(Vec<String>)
+}
+
+fn parse_variable(value : &serde_json::Value, path : &str, report : &mut ConfigReport) -> Option<ConfigVariable> {
+	if let Some(value_str) = value.as_str() {
+		return Some(ConfigVariable::Single(value_str.to_string()));
+	}
+
+	if let Some(values_json) = value.as_array() {
+		let values : Vec<String> = values_json.iter().filter_map(|value| value.as_str().map(|value| value.to_string())).collect();
+		if values.len() == values_json.len() {
+			return Some(ConfigVariable::List(values));
+		}
+	}
+
+	report.error(path, format!("expected a string or an array of strings, got {}", value));
+	return None;
+}
+
+fn parse_variables(variables_json : &serde_json::Value, path : &str, report : &mut ConfigReport) -> HashMap<String, ConfigVariable> {
+	let mut variables = HashMap::<String, ConfigVariable>::new();
+	if variables_json.is_null() {
+		return variables;
+	}
+
+	match variables_json.as_object() {
+		Some(variables_object) => {
+			for (name, value) in variables_object.iter() {
+				if let Some(variable) = parse_variable(value, &format!("{}.{}", path, name), report) {
+					variables.insert(name.clone(), variable);
+				}
+			}
+		}
+		None => report.error(path, format!("expected an object of variables, got {}", variables_json))
+	}
+
+	return variables;
+}
+
+fn substitute_variables(value_str : &str, variables : &HashMap<String, ConfigVariable>, path : &str, report : &mut ConfigReport) -> String {
+	let mut substituted = String::with_capacity(value_str.len());
+	let mut rest = value_str;
+	while let Some(var_start) = rest.find("${") {
+		substituted.push_str(&rest[..var_start]);
+		let var_end = match rest[var_start..].find('}') {
+			Some(var_end) => var_start + var_end,
+			None => {
+				report.error(path, format!("unterminated variable in '{}'", value_str));
+				return value_str.to_string();
+			}
+		};
+
+		let var_name = &rest[var_start + 2..var_end];
+		match variables.get(var_name) {
+			Some(ConfigVariable::Single(var_value)) => substituted.push_str(var_value),
+			// Leave the original text in for these, so it doesn't also get flagged as e.g. an empty compiler_exe
+			Some(ConfigVariable::List(_)) => {
+				report.error(path, format!("'{}' is a list, so it can only be used as a whole compiler arg", var_name));
+				substituted.push_str(&rest[var_start..var_end + 1]);
+			}
+			None => {
+				report.error(path, format!("unknown variable '{}'", var_name));
+				substituted.push_str(&rest[var_start..var_end + 1]);
+			}
+		}
+
+		rest = &rest[var_end + 1..];
+	}
+	substituted.push_str(rest);
+
+	return substituted;
+}
+
+// Fills in the variables in a compilation's compiler_exe and compiler_args, leaving anything we don't know how to handle for parse_compilation to complain about
+fn substitute_compilation_variables(compilation : &serde_json::Value, variables : &HashMap<String, ConfigVariable>, path : &str, report : &mut ConfigReport) -> serde_json::Value {
+	let mut compilation = compilation.clone();
+	if let Some(compilation_object) = compilation.as_object_mut() {
+		compilation_object.remove("matrix");
+	}
+
+	if let Some(compiler_exe) = compilation["compiler_exe"].as_str().map(|compiler_exe| compiler_exe.to_string()) {
+		compilation["compiler_exe"] = serde_json::json!(substitute_variables(&compiler_exe, variables, &format!("{}.compiler_exe", path), report));
+	}
+
+	if let Some(args_json) = compilation["compiler_args"].as_array().cloned() {
+		let mut args = Vec::<serde_json::Value>::with_capacity(args_json.len());
+		for (arg_idx, arg) in args_json.into_iter().enumerate() {
+			let arg_str = match arg.as_str() {
+				Some(arg_str) => arg_str.to_string(),
+				None => { args.push(arg); continue; }
+			};
+
+			let whole_var_name = arg_str.strip_prefix("${").and_then(|arg_str| arg_str.strip_suffix('}'));
+			if let Some(ConfigVariable::List(var_values)) = whole_var_name.and_then(|var_name| variables.get(var_name)) {
+				args.extend(var_values.iter().map(|var_value| serde_json::json!(var_value)));
+			}
+			else {
+				args.push(serde_json::json!(substitute_variables(&arg_str, variables, &format!("{}.compiler_args[{}]", path, arg_idx), report)));
+			}
+		}
+		compilation["compiler_args"] = serde_json::Value::Array(args);
+	}
+
+	return compilation;
+}
+
+// A compilation with a matrix (e.g. "matrix": { "CXX": ["g++", "clang++"], "OPT": ["-O0", "-O2"] }) gets expanded into
+// one compilation for every combination, with each matrix variable set on top of the config's variables.
+// The combinations go in the order the config has them, with the first variable changing slowest (serde_json's preserve_order keeps the keys in order)
+fn expand_compilation_matrix(compilation : &serde_json::Value, variables : &HashMap<String, ConfigVariable>, path : &str, report : &mut ConfigReport) -> Vec<serde_json::Value> {
+	let matrix_json = &compilation["matrix"];
+	if matrix_json.is_null() {
+		return vec![substitute_compilation_variables(compilation, variables, path, report)];
+	}
+
+	let matrix_object = match matrix_json.as_object() {
+		Some(matrix_object) => matrix_object,
+		None => {
+			report.error(&format!("{}.matrix", path), format!("expected an object of variable name to its values, got {}", matrix_json));
+			return Vec::new();
+		}
+	};
+
+	let mut combinations = vec![variables.clone()];
+	for (var_name, var_values_json) in matrix_object.iter() {
+		let var_path = format!("{}.matrix.{}", path, var_name);
+		let var_values : Vec<ConfigVariable> = match var_values_json.as_array() {
+			Some(var_values_json) if !var_values_json.is_empty() => {
+				var_values_json.iter().enumerate().filter_map(|(value_idx, value)| parse_variable(value, &format!("{}[{}]", var_path, value_idx), report)).collect()
+			}
+			_ => {
+				report.error(&var_path, format!("expected a non-empty array of values, got {}", var_values_json));
+				continue;
+			}
+		};
+
+		let mut next_combinations = Vec::with_capacity(combinations.len() * var_values.len());
+		for combination in combinations.iter() {
+			for var_value in var_values.iter() {
+				let mut next_combination = combination.clone();
+				next_combination.insert(var_name.clone(), var_value.clone());
+				next_combinations.push(next_combination);
+			}
+		}
+		combinations = next_combinations;
+	}
+
+	return combinations.iter().map(|combination| substitute_compilation_variables(compilation, combination, path, report)).collect();
+}
+
+// Pulls in everything from the config's "include" files (relative to the file including them), then the config's own keys on top.
+// Compilations get appended in order, variables get merged, and anything else is just overridden.
+// Returns the JSON path for each compilation too, so errors still point at the right file
+fn resolve_includes(config_json : &serde_json::Value, base_dir : &Path, label : &str, include_stack : &mut Vec<PathBuf>, report : &mut ConfigReport)
+		-> (serde_json::Map<String, serde_json::Value>, Vec<String>) {
+	let mut merged = serde_json::Map::new();
+	let mut compilation_paths = Vec::<String>::new();
+
+	let config_object = match config_json.as_object() {
+		Some(config_object) => config_object,
+		None => {
+			report.error(&format!("{}$", label), "expected an object at the top level".to_string());
+			return (merged, compilation_paths);
+		}
+	};
+
+	warn_on_unknown_keys(config_object, &TOP_LEVEL_KEYS, &format!("{}$", label), report);
+
+	let include_filenames : Vec<String> = match &config_json["include"] {
+		serde_json::Value::Null => Vec::new(),
+		serde_json::Value::String(include_filename) => vec![include_filename.clone()],
+		serde_json::Value::Array(include_filenames) if include_filenames.iter().all(|include_filename| include_filename.is_string()) => {
+			include_filenames.iter().map(|include_filename| include_filename.as_str().expect("").to_string()).collect()
+		}
+		include_json => {
+			report.error(&format!("{}$.include", label), format!("expected a filename or an array of them, got {}", include_json));
+			Vec::new()
+		}
+	};
+
+	let mut to_merge = Vec::<(serde_json::Map<String, serde_json::Value>, Vec<String>)>::new();
+	for include_filename in include_filenames {
+		let include_path = base_dir.join(&include_filename);
+		let include_canonical_path = include_path.canonicalize().unwrap_or_else(|_| include_path.clone());
+		if include_stack.contains(&include_canonical_path) {
+			report.error(&format!("{}$.include", label), format!("'{}' ends up including itself", include_filename));
+			continue;
+		}
+
+		let include_contents = match std::fs::read_to_string(&include_path) {
+			Ok(include_contents) => include_contents,
+			Err(err) => {
+				report.error(&format!("{}$.include", label), format!("could not read '{}': {}", include_path.display(), err));
+				continue;
+			}
+		};
+
+		let include_label = format!("{}:", include_path.display());
+		let include_json : serde_json::Value = match serde_json::from_str(&include_contents) {
+			Ok(include_json) => include_json,
+			Err(err) => {
+				report.error(&format!("{}$", include_label), format!("not valid JSON: {}", err));
+				continue;
+			}
+		};
+
+		include_stack.push(include_canonical_path);
+		let include_dir = include_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+		to_merge.push(resolve_includes(&include_json, &include_dir, &include_label, include_stack, report));
+		include_stack.pop();
+	}
+
+	let own_paths = match config_json["compilations"].as_array() {
+		Some(compilations_json) => (0..compilations_json.len()).map(|compile_idx| format!("{}$.compilations[{}]", label, compile_idx)).collect(),
+		None => Vec::new()
+	};
+	let mut own_object = config_object.clone();
+	own_object.remove("include");
+	to_merge.push((own_object, own_paths));
+
+	for (object, paths) in to_merge {
+		for (key, value) in object {
+			match (key.as_str(), merged.get_mut(&key)) {
+				("compilations", Some(serde_json::Value::Array(compilations))) if value.is_array() => {
+					compilations.extend(value.as_array().expect("").iter().cloned());
+				}
+				("variables", Some(serde_json::Value::Object(variables))) if value.is_object() => {
+					variables.extend(value.as_object().expect("").clone());
+				}
+				_ => { merged.insert(key, value); }
+			}
+		}
+
+		// If the compilations got overridden by something that isn't an array, the paths don't matter anyway
+		compilation_paths.extend(paths);
+	}
+
+	return (merged, compilation_paths);
+}
+
+// Same as any other compilation, except it has to be an executable, and gets the sanitizers added if it doesn't pick its own
+fn parse_sanitizer_compilation(compilation : &serde_json::Value, variables : &HashMap<String, ConfigVariable>, default_timeout : Option<i32>, report : &mut ConfigReport) -> Option<TestCompilation> {
+	let path = "$.sanitizer_compilation";
+	let expanded_compilations = expand_compilation_matrix(compilation, variables, path, report);
+	if expanded_compilations.len() != 1 {
+		report.error(&format!("{}.matrix", path), "there's only one sanitizer compilation, so it can't have a matrix".to_string());
+		return None;
+	}
+
+	let mut sanitizer_compilation = parse_compilation(&expanded_compilations[0], path, default_timeout, report)?;
+	if sanitizer_compilation.exec_backend != ExecBackend::Executable {
+		report.error(&format!("{}.exec_backend", path), "has to be 'executable', with the compiler_args using ^GENERATED_EXE_FILENAME^".to_string());
+	}
+
+	// -fsanitize-recover and friends don't count as picking a sanitizer
+	if !sanitizer_compilation.compiler_args.iter().any(|arg| arg.starts_with("-fsanitize=")) {
+		sanitizer_compilation.compiler_args.push("-fsanitize=undefined,address".to_string());
+	}
+
+	// Without -fno-sanitize-recover, UBSan just prints its report and keeps going, and we'd never see a non-zero exit.
+	// This goes on even if the config picked its own sanitizers
+	if !sanitizer_compilation.compiler_args.iter().any(|arg| arg == "-fno-sanitize-recover=all") {
+		sanitizer_compilation.compiler_args.push("-fno-sanitize-recover=all".to_string());
+	}
+
+	return Some(sanitizer_compilation);
+}
+
+// Only returns a config if there were no errors, either way the report has everything that was wrong with it.
+// Any includes are relative to the current directory
+pub fn parse_compiler_config(config : &str) -> (Option<CompilationConfig>, ConfigReport) {
+	return parse_compiler_config_in_dir(config, Path::new("."), Vec::new());
+}
+
+pub fn load_compiler_config_file(config_filename : &str) -> (Option<CompilationConfig>, ConfigReport) {
+	let config_contents = match std::fs::read_to_string(config_filename) {
+		Ok(config_contents) => config_contents,
+		Err(err) => {
+			let mut report = ConfigReport::default();
+			report.error("$", format!("could not open config file: {}", err));
+			return (None, report);
+		}
+	};
+
+	// So a file that includes itself is caught right away
+	let config_path = Path::new(config_filename);
+	let include_stack = vec![config_path.canonicalize().unwrap_or_else(|_| config_path.to_path_buf())];
+	let config_dir = config_path.parent().unwrap_or(Path::new("."));
+	return parse_compiler_config_in_dir(&config_contents, config_dir, include_stack);
+}
+
+fn parse_compiler_config_in_dir(config : &str, config_dir : &Path, mut include_stack : Vec<PathBuf>) -> (Option<CompilationConfig>, ConfigReport) {
+	let mut report = ConfigReport::default();
+
+	let config_json : serde_json::Value = match serde_json::from_str(config) {
+		Ok(config_json) => config_json,
+		Err(err) => {
+			report.error("$", format!("not valid JSON: {}", err));
+			return (None, report);
+		}
+	};
+
+	if !config_json.is_object() {
+		report.error("$", "expected an object at the top level".to_string());
+		return (None, report);
+	}
+
+	let (config_object, compilation_paths) = resolve_includes(&config_json, config_dir, "", &mut include_stack, &mut report);
+	let config_json = serde_json::Value::Object(config_object);
+
+	let variables = parse_variables(&config_json["variables"], "$.variables", &mut report);
+
+	// Compilations can each have their own timeout, this is the default for the ones that don't
+	let default_timeout = if config_json["compilation_timeout_seconds"].is_null() {
+		None
+	}
+	else {
+		parse_timeout_seconds(&config_json["compilation_timeout_seconds"], "$.compilation_timeout_seconds", &mut report)
+	};
+
+	let fuzz_mode = match config_json["mode"].as_str() {
+		Some(mode_str) => {
+			let fuzz_mode = parse_fuzz_mode(mode_str);
+			if fuzz_mode.is_none() {
+				report.error("$.mode", format!("unknown mode '{}', expected one of 'crash', 'crash+diff' or 'crash+optbait'", mode_str));
+			}
+			fuzz_mode
+		}
+		None => {
+			report.error("$.mode", format!("expected one of 'crash', 'crash+diff' or 'crash+optbait', got {}", config_json["mode"]));
+			None
+		}
+	};
+
+	let parallel_compilations = parse_optional_bool(&config_json["parallel_compilations"], "$.parallel_compilations", &mut report);
+
+	let mut test_compilations = Vec::<TestCompilation>::with_capacity(8);
+	match config_json["compilations"].as_array() {
+		Some(compilations_json) => {
+			if compilations_json.is_empty() {
+				report.error("$.compilations", "need at least one compilation".to_string());
+			}
+
+			for (compile_idx, compilation) in compilations_json.iter().enumerate() {
+				let compilation_path = compilation_paths.get(compile_idx).cloned().unwrap_or_else(|| format!("$.compilations[{}]", compile_idx));
+				for expanded_compilation in expand_compilation_matrix(compilation, &variables, &compilation_path, &mut report) {
+					if let Some(mut test_compilation) = parse_compilation(&expanded_compilation, &compilation_path, default_timeout, &mut report) {
+						test_compilation.run_in_parallel = parallel_compilations;
+						test_compilations.push(test_compilation);
+					}
+				}
+			}
+		}
+		None => report.error("$.compilations", format!("expected an array of compilations, got {}", config_json["compilations"]))
+	}
+
+	// Optional, runtime diffs get checked for UB in the generated code with it (see sanitizer_check)
+	let sanitizer_compilation = if config_json["sanitizer_compilation"].is_null() {
+		None
+	}
+	else {
+		parse_sanitizer_compilation(&config_json["sanitizer_compilation"], &variables, default_timeout, &mut report)
+	};
+
+	let mut mitigations = BTreeSet::<String>::new();
+	if !config_json["mitigations"].is_null() {
+		match config_json["mitigations"].as_array() {
+			Some(mitigations_json) => {
+				for (mitigation_idx, mitigation) in mitigations_json.iter().enumerate() {
+					match mitigation.as_str() {
+						Some(mitigation) => { mitigations.insert(mitigation.to_string()); }
+						None => report.error(&format!("$.mitigations[{}]", mitigation_idx), format!("expected a string, got {}", mitigation))
+					}
+				}
+			}
+			None => report.error("$.mitigations", format!("expected an array of strings, got {}", config_json["mitigations"]))
+		}
+	}
+
+	// Each fuzzer looks for its own things in here, so we don't check it
+	let extra_config = config_json["extra_config"].clone();
+	if !extra_config.is_null() && !extra_config.is_object() {
+		report.error("$.extra_config", format!("expected an object, got {}", extra_config));
+	}
+
+	// A bad matrix entry gets reported once per combination otherwise
+	report.remove_duplicates();
+
+	if !report.errors.is_empty() {
+		return (None, report);
+	}
+
+	let config = CompilationConfig {
+		compilations: test_compilations,
+		fuzz_mode: fuzz_mode.expect(""),
+		mitigations: mitigations,
+		extra_config: extra_config,
+		sanitizer_compilation: sanitizer_compilation
+	};
+
+	return (Some(config), report);
+}
+
+// Returns the full path to the compiler if it exists, looking through PATH if it's just a name (like the config's "clang++")
+pub fn find_compiler_exe(compiler_exe : &str) -> Option<PathBuf> {
+	let exe_path = PathBuf::from(compiler_exe);
+	if exe_path.components().count() > 1 {
+		return if exe_path.is_file() { Some(exe_path) } else { None };
+	}
+
+	let path_var = std::env::var_os("PATH")?;
+	for dir in std::env::split_paths(&path_var) {
+		let candidate = dir.join(compiler_exe);
+		if candidate.is_file() {
+			return Some(candidate);
+		}
+
+		if cfg!(windows) {
+			let candidate = dir.join(format!("{}.exe", compiler_exe));
+			if candidate.is_file() {
+				return Some(candidate);
+			}
+		}
+	}
+
+	return None;
+}
+
+#[test]
+fn test_parse_compiler_config_reports_all_errors() {
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", 3] },
+			{ "compiler_args": ["-O0"] }
+		],
+		"mode": "crash+dif"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(compilation_config.is_none());
+
+	let error_paths : Vec<&str> = report.errors.iter().map(|error| error.path.as_str()).collect();
+	assert!(error_paths.contains(&"$.mode"));
+	assert!(error_paths.contains(&"$.compilations[0].compiler_args[1]"));
+	assert!(error_paths.contains(&"$.compilations[0].timeout_seconds"));
+	assert!(error_paths.contains(&"$.compilations[1].compiler_exe"));
+}
+
+#[test]
+fn test_parse_compiler_config_warns_on_unknown_keys() {
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-o", "^TMP_FILENAME^"], "use_tmp_file": true, "timeout_seconds": 10 },
+			{ "compiler_exe": "g++", "compiler_args": ["-O0"] }
+		],
+		"compilation_timeout_seconds": 5,
+		"mode": "crash"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	let compilation_config = compilation_config.expect("config should still parse with only warnings");
+	assert_eq!(compilation_config.compilations[0].timeout_seconds, 10);
+	assert_eq!(compilation_config.compilations[1].timeout_seconds, 5);
+	assert!(!compilation_config.compilations[0].use_tmp_file);
+
+	assert_eq!(report.warnings.len(), 1);
+	assert_eq!(report.warnings[0].path, "$.compilations[0].use_tmp_file");
+	assert!(report.warnings[0].message.contains("use_temp_file"));
+}
+
+#[test]
+fn test_parse_compiler_config_expands_matrix() {
+	let config = r#"{
+		"variables": { "CXX": "g++", "TARGET_FLAGS": ["-march=haswell", "-mtune=haswell"] },
+		"compilations": [
+			{
+				"compiler_exe": "${CXX}",
+				"compiler_args": ["${TARGET_FLAGS}", "${OPT}", "-DCXX=${CXX}"],
+				"matrix": { "CXX": ["g++", "clang++"], "OPT": ["-O0", "-O2"] }
+			},
+			{ "compiler_exe": "${CC}", "compiler_args": ["${TARGET_FLAGS}"] }
+		],
+		"compilation_timeout_seconds": 5,
+		"mode": "crash"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(compilation_config.is_none());
+	assert_eq!(report.errors.len(), 1);
+	assert_eq!(report.errors[0].path, "$.compilations[1].compiler_exe");
+
+	let config = config.replace("${CC}", "${CXX}");
+	let (compilation_config, report) = parse_compiler_config(&config);
+	assert!(report.errors.is_empty());
+
+	let compilations = compilation_config.expect("").compilations;
+	assert_eq!(compilations.len(), 5);
+	assert_eq!(compilations[0].compiler_exe, "g++");
+	assert_eq!(compilations[0].compiler_args, vec!["-march=haswell", "-mtune=haswell", "-O0", "-DCXX=g++"]);
+	assert_eq!(compilations[3].compiler_exe, "clang++");
+	assert_eq!(compilations[3].compiler_args, vec!["-march=haswell", "-mtune=haswell", "-O2", "-DCXX=clang++"]);
+	assert_eq!(compilations[4].compiler_exe, "g++");
+
+	// Not in alphabetical order, so OPT should be the one changing slowest now
+	let config = config.replace(r#""matrix": { "CXX": ["g++", "clang++"], "OPT": ["-O0", "-O2"] }"#, r#""matrix": { "OPT": ["-O2", "-O0"], "CXX": ["g++", "clang++"] }"#);
+	let compilations = parse_compiler_config(&config).0.expect("").compilations;
+	let compiler_and_opts : Vec<(&str, &str)> = compilations[..4].iter().map(|compilation| (compilation.compiler_exe.as_str(), compilation.compiler_args[2].as_str())).collect();
+	assert_eq!(compiler_and_opts, vec![("g++", "-O2"), ("clang++", "-O2"), ("g++", "-O0"), ("clang++", "-O0")]);
+}
+
+#[test]
+fn test_parse_compiler_config_shared_lib_backend() {
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-x", "c++", "-", "-o", "^TMP_FILENAME^"], "use_temp_file": true, "exec_backend": "shared_lib" },
+			{ "compiler_exe": "g++", "compiler_args": ["-O0", "-fPIC", "-o", "^TMP_FILENAME^"], "use_temp_file": true, "exec_backend": "shared_lib" },
+			{ "compiler_exe": "g++", "compiler_args": ["-O0", "-c", "-o", "^TMP_FILENAME^"], "use_temp_file": true, "exec_backend": "obj_loader" }
+		],
+		"compilation_timeout_seconds": 5,
+		"mode": "crash"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(report.errors.is_empty());
+
+	let compilations = compilation_config.expect("").compilations;
+	assert_eq!(compilations[0].exec_backend, ExecBackend::SharedLib);
+	assert_eq!(compilations[0].compiler_args, vec!["-O2", "-x", "c++", "-", "-o", "^TMP_FILENAME^", "-shared", "-fPIC"]);
+	assert_eq!(compilations[1].compiler_args, vec!["-O0", "-fPIC", "-o", "^TMP_FILENAME^", "-shared"]);
+	assert_eq!(compilations[2].exec_backend, ExecBackend::ObjLoader);
+	assert_eq!(compilations[2].compiler_args, vec!["-O0", "-c", "-o", "^TMP_FILENAME^"]);
+
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-c", "-o", "-"], "exec_backend": "shared_lib" },
+			{ "compiler_exe": "g++", "compiler_args": ["-O0"], "exec_backend": "dlopen" }
+		],
+		"compilation_timeout_seconds": 5,
+		"mode": "crash"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(compilation_config.is_none());
+
+	let error_paths : Vec<&str> = report.errors.iter().map(|error| error.path.as_str()).collect();
+	assert_eq!(error_paths, vec!["$.compilations[0].compiler_args", "$.compilations[0].use_temp_file", "$.compilations[1].exec_backend"]);
+}
+
+#[test]
+fn test_parse_compiler_config_executable_backend() {
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "^GENERATED_SOURCE_FILENAME^", "-o", "^GENERATED_EXE_FILENAME^"] },
+			{ "compiler_exe": "g++", "compiler_args": ["-O0", "-x", "c++", "-", "-o", "^GENERATED_EXE_FILENAME^"], "exec_backend": "executable" }
+		],
+		"compilation_timeout_seconds": 5,
+		"mode": "crash"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(report.errors.is_empty());
+
+	let compilations = compilation_config.expect("").compilations;
+	assert_eq!(compilations[0].exec_backend, ExecBackend::Executable);
+	assert_eq!(compilations[1].exec_backend, ExecBackend::Executable);
+
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-c", "-o", "^GENERATED_EXE_FILENAME^"] },
+			{ "compiler_exe": "g++", "compiler_args": ["-O0", "-o", "^TMP_FILENAME^"], "use_temp_file": true, "exec_backend": "executable" }
+		],
+		"compilation_timeout_seconds": 5,
+		"mode": "crash"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(compilation_config.is_none());
+
+	let error_paths : Vec<&str> = report.errors.iter().map(|error| error.path.as_str()).collect();
+	assert_eq!(error_paths, vec!["$.compilations[0].compiler_args", "$.compilations[1].compiler_args"]);
+}
+
+#[test]
+fn test_parse_compiler_config_sanitizer_compilation() {
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-c", "-x", "c++", "-", "-o", "-"] }
+		],
+		"sanitizer_compilation": { "compiler_exe": "g++", "compiler_args": ["-O1", "-x", "c++", "-", "-o", "^GENERATED_EXE_FILENAME^"] },
+		"compilation_timeout_seconds": 5,
+		"mode": "crash+diff"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(report.errors.is_empty());
+
+	let sanitizer_compilation = compilation_config.expect("").sanitizer_compilation.expect("");
+	assert_eq!(sanitizer_compilation.exec_backend, ExecBackend::Executable);
+	assert_eq!(sanitizer_compilation.compiler_args, vec!["-O1", "-x", "c++", "-", "-o", "^GENERATED_EXE_FILENAME^", "-fsanitize=undefined,address", "-fno-sanitize-recover=all"]);
+
+	// Picking its own sanitizer keeps it, but recovering still gets turned off, and -fsanitize-recover isn't a sanitizer
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-c", "-x", "c++", "-", "-o", "-"] }
+		],
+		"sanitizer_compilation": { "compiler_exe": "g++", "compiler_args": ["-fsanitize=address", "-x", "c++", "-", "-o", "^GENERATED_EXE_FILENAME^"] },
+		"compilation_timeout_seconds": 5,
+		"mode": "crash+diff"
+	}"#;
+	let sanitizer_compilation = parse_compiler_config(config).0.expect("").sanitizer_compilation.expect("");
+	assert_eq!(sanitizer_compilation.compiler_args, vec!["-fsanitize=address", "-x", "c++", "-", "-o", "^GENERATED_EXE_FILENAME^", "-fno-sanitize-recover=all"]);
+
+	let config = config.replace("-fsanitize=address", "-fsanitize-recover=address");
+	let sanitizer_compilation = parse_compiler_config(&config).0.expect("").sanitizer_compilation.expect("");
+	assert_eq!(sanitizer_compilation.compiler_args, vec!["-fsanitize-recover=address", "-x", "c++", "-", "-o", "^GENERATED_EXE_FILENAME^", "-fsanitize=undefined,address", "-fno-sanitize-recover=all"]);
+
+	let config = r#"{
+		"compilations": [
+			{ "compiler_exe": "g++", "compiler_args": ["-O2", "-c", "-x", "c++", "-", "-o", "-"] }
+		],
+		"sanitizer_compilation": { "compiler_exe": "g++", "compiler_args": ["-O1", "-fsanitize=undefined", "-c", "-x", "c++", "-", "-o", "-"] },
+		"compilation_timeout_seconds": 5,
+		"mode": "crash+diff"
+	}"#;
+
+	let (compilation_config, report) = parse_compiler_config(config);
+	assert!(compilation_config.is_none());
+
+	let error_paths : Vec<&str> = report.errors.iter().map(|error| error.path.as_str()).collect();
+	assert_eq!(error_paths, vec!["$.sanitizer_compilation.exec_backend"]);
+}
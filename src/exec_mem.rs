@@ -1,6 +1,8 @@
 
 
-use executable_memory::ExecutableMemory;
+use crate::page_pool::ExecMapping;
+use crate::shared_lib_exec::SharedLib;
+use crate::generated_exe::GeneratedExe;
 
 use libc;
 
@@ -12,17 +14,129 @@ use core::arch::x86_64::{__m128i, __m256i};
 // :(
 use crate::x86_intrinsics::AlignedWrapper;
 
+// The calling convention the loaded function expects. Usually the same as ours,
+// but COFF objects cross-compiled for x86_64-pc-windows-msvc want win64 even when we're on Linux
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallConv {
+	Host,
+	Win64
+}
+
+// How much of the front of the page is data. The writable data comes first, then the read-only data, and then the code,
+// each starting on its own EXEC_PAGE_SIZE boundary so they can get different protections
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DataLayout {
+	pub writable_len : usize,
+	pub read_only_len : usize
+}
+
+// The signatures do_stuff can have, which match up with the execute_with_* functions below.
+// This is what gets handed to ExternalCode, with the args as raw bytes and output_len bytes of output coming back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalCall {
+	// fn(*const u32, *mut u32, count), with the output being whatever got written to the second pointer
+	U32Io,
+	U64Io,
+	// fn(*const i32, *const f32, *const f64) -> __m128i/__m256i, with the output being the return value
+	Args128i,
+	Args256i
+}
+
+// Compiled code that gets run in some other process, instead of getting loaded into the page
+#[derive(Debug)]
+pub enum ExternalCode {
+	SharedLib(SharedLib),
+	Executable(GeneratedExe)
+}
+
+impl ExternalCode {
+	fn call(&self, call_kind : ExternalCall, args : &[&[u8]], output_len : usize) -> Vec<u8> {
+		match self {
+			ExternalCode::SharedLib(shared_lib) => shared_lib.call(call_kind, args, output_len),
+			ExternalCode::Executable(exe) => exe.call(call_kind, args, output_len)
+		}
+	}
+}
+
+// What running code unwinds with when it crashed somewhere other than our own process (a shared lib worker, the exe server).
+// The driver catches it and counts it as a diff, see do_compiled_outputs_differ
+#[derive(Debug)]
+pub struct ExecCrash(pub String);
+
+// ...and what it unwinds with when there's nowhere left to run it at all (e.g. we lost the exe server and couldn't get it back).
+// More cases won't fix that, so the driver stops the run with it as the error
+#[derive(Debug)]
+pub struct ExecUnavailable(pub String);
+
+pub fn as_byte_slice<T>(vals : &[T]) -> &[u8] {
+	return unsafe { std::slice::from_raw_parts(vals.as_ptr() as *const u8, std::mem::size_of_val(vals)) };
+}
+
 #[derive(Debug)]
 pub struct ExecPage {
 	// TODO: See if this can be non-pub in some way for ARM
-	pub page : ExecutableMemory,
+	pub page : ExecMapping,
 	pub func_offset : usize,
-	code_size : usize
+	pub call_conv : CallConv,
+	pub data_layout : DataLayout,
+	// Set by the linkers if the page has anything in it that's only right at this address (GOT slots, PLT entries, absolute relocations)
+	// or any writable data, so copying the relocated bytes somewhere else won't work and the exe server has to link the object itself
+	pub needs_server_link : bool,
+	code_size : usize,
+	// What the writable data looked like right after relocation, so every run can start from it
+	initial_writable_data : Vec<u8>,
+	// The object this got linked from, which is what gets sent to the exe server
+	obj_data : Vec<u8>,
+	// Linked for some other architecture, so it can only be run on the exe server
+	is_foreign : bool,
+	// If set, the code gets run out-of-process, and the page itself is left empty
+	external_code : Option<ExternalCode>
+}
+
+// Calls func_ptr as an `unsafe extern fn(args) -> ret` with whatever calling convention the page's function uses
+macro_rules! call_with_call_conv {
+	($call_conv:expr, $func_ptr:expr, fn($($arg_type:ty),*) -> $ret_type:ty, $($arg:expr),*) => {
+		match $call_conv {
+			CallConv::Host => {
+				let func: unsafe extern "C" fn($($arg_type),*) -> $ret_type = unsafe { std::mem::transmute($func_ptr) };
+				unsafe { func($($arg),*) }
+			}
+			#[cfg(target_arch = "x86_64")]
+			CallConv::Win64 => {
+				let func: unsafe extern "win64" fn($($arg_type),*) -> $ret_type = unsafe { std::mem::transmute($func_ptr) };
+				unsafe { func($($arg),*) }
+			}
+			#[cfg(not(target_arch = "x86_64"))]
+			CallConv::Win64 => {
+				panic!("win64 functions can only be called on x86-64");
+			}
+		}
+	};
 }
 
 impl ExecPage {
 	pub fn new(num_pages : usize) -> ExecPage {
-		return ExecPage { page: ExecutableMemory::new(num_pages), func_offset: 0, code_size: 0 }
+		return ExecPage { page: ExecMapping::new(num_pages), func_offset: 0, call_conv: CallConv::Host, data_layout: DataLayout::default(), needs_server_link: false, code_size: 0, initial_writable_data: Vec::new(), obj_data: Vec::new(), is_foreign: false, external_code: None }
+	}
+
+	pub fn from_external_code(external_code : ExternalCode) -> ExecPage {
+		let mut exec_page = ExecPage::new(1);
+		exec_page.external_code = Some(external_code);
+		exec_page.make_executable();
+		return exec_page;
+	}
+
+	pub fn get_external_code(&self) -> Option<&ExternalCode> {
+		return self.external_code.as_ref();
+	}
+
+	pub fn set_obj_data(&mut self, obj_data : Vec<u8>, is_foreign : bool) {
+		self.obj_data = obj_data;
+		self.is_foreign = is_foreign;
+	}
+
+	pub fn get_obj_data(&self) -> &[u8] {
+		return &self.obj_data[..];
 	}
 
 	pub fn load_with_code(&mut self, instructions : &[u8], func_offset : usize) {
@@ -57,7 +171,7 @@ impl ExecPage {
 		let current_value_int = i32::from_le_bytes(current_value_bytes.try_into().expect(""));
 
 		let imm_lo = (value & 0x03); // bits [0:1]
-		let imm_hi = (value >> 2) & ((1 << 17) - 1); // bits [2:19]
+		let imm_hi = (value >> 2) & ((1 << 19) - 1); // bits [2:20]
 
 		let imm_lo_positioned = ((imm_lo as u32) << 29) as i32;
 		let imm_hi_positioned = ((imm_hi as u32) << 5) as i32;
@@ -68,6 +182,17 @@ impl ExecPage {
 		self.page[write_offset..write_offset+4].clone_from_slice(&new_value_bytes[..]);
 	}
 	
+	// Sets num_bits of the instruction starting at lsb to value, for the immediates that don't need anything special (branches, movz/movk, etc.)
+	pub fn fix_up_arm_imm_field(&mut self, write_offset : usize, value : u32, lsb : u32, num_bits : u32) {
+		let current_value_bytes = &self.page[write_offset..write_offset+4];
+		let current_value_int = u32::from_le_bytes(current_value_bytes.try_into().expect(""));
+		let field_mask = (((1u64 << num_bits) - 1) as u32) << lsb;
+		let new_value_int = (current_value_int & !field_mask) | ((value << lsb) & field_mask);
+
+		let new_value_bytes = new_value_int.to_le_bytes();
+		self.page[write_offset..write_offset+4].clone_from_slice(&new_value_bytes[..]);
+	}
+
 	pub fn fix_up_arm_ldr_offset_redirect(&mut self, write_offset : usize, value : i32, shift : i32) {
 		let current_value_bytes = &self.page[write_offset..write_offset+4];
 		let current_value_int = i32::from_le_bytes(current_value_bytes.try_into().expect(""));
@@ -88,6 +213,29 @@ impl ExecPage {
 		self.page[write_offset..write_offset+4].clone_from_slice(&new_value_bytes[..]);
 	}
 
+	// Once everything's loaded and relocated. Only the writable data can be written to after this
+	pub fn make_executable(&mut self) {
+		let writable_len = self.data_layout.writable_len;
+		self.initial_writable_data = self.page[..writable_len].to_vec();
+		self.page.make_executable(writable_len, self.data_layout.read_only_len);
+		self.flush_cache();
+	}
+
+	// Puts the writable data back the way it was after loading, so whatever the last run left in its globals doesn't affect the next one
+	pub fn reset_writable_data(&self) {
+		assert!(self.page.is_executable());
+		// The writable part of the mapping stays RW, so this is fine even though we only have &self
+		unsafe { std::ptr::copy_nonoverlapping(self.initial_writable_data.as_ptr(), self.page.as_ptr() as *mut u8, self.initial_writable_data.len()); }
+	}
+
+	// Anything that calls the function needs to get it through here, so that it gets fresh writable data
+	pub fn get_func_ptr(&self) -> *const u8 {
+		assert!(self.external_code.is_none(), "can't call into external code directly, it's in another process");
+		assert!(!self.is_foreign, "can't call code linked for another architecture, it needs to go to an exe server");
+		self.reset_writable_data();
+		return unsafe { self.page.as_ptr().add(self.func_offset) };
+	}
+
 	pub fn flush_cache(&self) {
 		#[cfg(target_arch = "aarch64")]
 		{
@@ -102,44 +250,51 @@ impl ExecPage {
 	
 	#[cfg(target_arch = "x86_64")]
 	pub fn execute_with_args_256i(&self, i_vals: &[i32], f_vals: &[f32], d_vals: &[f64]) -> __m256i {
-		let func_ptr = unsafe { self.page.as_ptr().add(self.func_offset) };
-		let func: unsafe extern "C" fn(*const i32, *const f32, *const f64) -> __m256i = unsafe { std::mem::transmute(func_ptr) };
+		if let Some(external_code) = &self.external_code {
+			let ret_bytes = external_code.call(ExternalCall::Args256i, &[as_byte_slice(i_vals), as_byte_slice(f_vals), as_byte_slice(d_vals)], 32);
+			return unsafe { std::ptr::read_unaligned(ret_bytes.as_ptr() as *const __m256i) };
+		}
 
-		let ret = unsafe {
-			func(i_vals.as_ptr(), f_vals.as_ptr(), d_vals.as_ptr())
-		};
+		let func_ptr = self.get_func_ptr();
+		let ret = call_with_call_conv!(self.call_conv, func_ptr, fn(*const i32, *const f32, *const f64) -> __m256i, i_vals.as_ptr(), f_vals.as_ptr(), d_vals.as_ptr());
 
 		return ret;
 	}
 
 	#[cfg(target_arch = "x86_64")]
 	pub fn execute_with_args_128i(&self, i_vals: &[i32], f_vals: &[f32], d_vals: &[f64]) -> __m128i {
-		let func_ptr = unsafe { self.page.as_ptr().add(self.func_offset) };
-		let func: unsafe extern "C" fn(*const i32, *const f32, *const f64) -> __m128i = unsafe { std::mem::transmute(func_ptr) };
+		if let Some(external_code) = &self.external_code {
+			let ret_bytes = external_code.call(ExternalCall::Args128i, &[as_byte_slice(i_vals), as_byte_slice(f_vals), as_byte_slice(d_vals)], 16);
+			return unsafe { std::ptr::read_unaligned(ret_bytes.as_ptr() as *const __m128i) };
+		}
 
-		let ret = unsafe {
-			func(i_vals.as_ptr(), f_vals.as_ptr(), d_vals.as_ptr())
-		};
+		let func_ptr = self.get_func_ptr();
+		let ret = call_with_call_conv!(self.call_conv, func_ptr, fn(*const i32, *const f32, *const f64) -> __m128i, i_vals.as_ptr(), f_vals.as_ptr(), d_vals.as_ptr());
 
 		return ret;
 	}
 	
+	// The function also gets the number of inputs as a third arg (the loop/asm fuzzers' 'count')
 	pub fn execute_with_u32_io(&self, input: &[u32], output: &mut [u32]) {
-		let func_ptr = unsafe { self.page.as_ptr().add(self.func_offset) };
-		let func: unsafe extern "C" fn(*const u32, *mut u32) = unsafe { std::mem::transmute(func_ptr) };
-
-		unsafe {
-			func(input.as_ptr(), output.as_mut_ptr());
+		if let Some(external_code) = &self.external_code {
+			let output_bytes = external_code.call(ExternalCall::U32Io, &[as_byte_slice(input)], std::mem::size_of_val(output));
+			unsafe { std::ptr::copy_nonoverlapping(output_bytes.as_ptr(), output.as_mut_ptr() as *mut u8, output_bytes.len()); }
+			return;
 		}
+
+		let func_ptr = self.get_func_ptr();
+		call_with_call_conv!(self.call_conv, func_ptr, fn(*const u32, *mut u32, i32) -> (), input.as_ptr(), output.as_mut_ptr(), input.len() as i32);
 	}
 	
 	pub fn execute_with_u64_io(&self, input: &[u64], output: &mut [u64]) {
-		let func_ptr = unsafe { self.page.as_ptr().add(self.func_offset) };
-		let func: unsafe extern "C" fn(*const u64, *mut u64) = unsafe { std::mem::transmute(func_ptr) };
-
-		unsafe {
-			func(input.as_ptr(), output.as_mut_ptr());
+		if let Some(external_code) = &self.external_code {
+			let output_bytes = external_code.call(ExternalCall::U64Io, &[as_byte_slice(input)], std::mem::size_of_val(output));
+			unsafe { std::ptr::copy_nonoverlapping(output_bytes.as_ptr(), output.as_mut_ptr() as *mut u8, output_bytes.len()); }
+			return;
 		}
+
+		let func_ptr = self.get_func_ptr();
+		call_with_call_conv!(self.call_conv, func_ptr, fn(*const u64, *mut u64, i32) -> (), input.as_ptr(), output.as_mut_ptr(), input.len() as i32);
 	}
 	
 	pub fn get_bytes(&self) -> &[u8] {
@@ -200,3 +355,29 @@ unsafe fn __aarch64_sync_cache_range(base : *mut libc::c_void, end : *mut libc::
 }
 //---------------------------------------------------------------------------------------
 
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_writable_data_reset_between_runs() {
+	use crate::page_pool::EXEC_PAGE_SIZE;
+
+	// inc dword [rip - 16390] ; mov eax, [rip - 16396] ; mov [rsi], eax ; ret, with a counter at the very start of the page
+	let mut code_bytes = vec![0u8; EXEC_PAGE_SIZE];
+	code_bytes[..4].copy_from_slice(&41u32.to_le_bytes());
+	code_bytes.extend_from_slice(&[0xFF, 0x05]);
+	code_bytes.extend_from_slice(&(-16390i32).to_le_bytes());
+	code_bytes.extend_from_slice(&[0x8B, 0x05]);
+	code_bytes.extend_from_slice(&(-16396i32).to_le_bytes());
+	code_bytes.extend_from_slice(&[0x89, 0x06, 0xC3]);
+
+	let mut exec_page = ExecPage::new(2);
+	exec_page.load_with_code(&code_bytes[..], EXEC_PAGE_SIZE);
+	exec_page.data_layout = DataLayout { writable_len: EXEC_PAGE_SIZE, read_only_len: 0 };
+	exec_page.make_executable();
+
+	for _ in 0..3 {
+		let mut output = [0u32; 1];
+		exec_page.execute_with_u32_io(&[], &mut output);
+		assert_eq!(output[0], 42);
+	}
+}
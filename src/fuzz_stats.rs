@@ -0,0 +1,273 @@
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::compilation_config::{TestCompilation, CompilationTimings, CompileEntryOutcome};
+use crate::saved_findings::FindingCategory;
+
+// Durations are kept as nanoseconds so the threads can just add to them atomically
+pub fn add_duration(counter : &AtomicU64, duration : Duration) {
+	counter.fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+}
+
+fn load_seconds(counter : &AtomicU64) -> f64 {
+	return counter.load(Ordering::SeqCst) as f64 / 1_000_000_000.0;
+}
+
+// One per entry in the config's compilations list, to see which compiler/flags are the slow ones
+#[derive(Default)]
+pub struct CompilationEntryStats {
+	pub num_compiles : AtomicU64,
+	pub queue_wait_nanos : AtomicU64,
+	pub compile_nanos : AtomicU64,
+	pub obj_parse_nanos : AtomicU64,
+	// With parallel compilations every entry gets to run, so these say which ones are actually failing
+	pub num_failures : AtomicU64,
+	pub num_timeouts : AtomicU64,
+	pub num_load_failures : AtomicU64
+}
+
+impl CompilationEntryStats {
+	fn to_json(&self, compilation_test : &TestCompilation) -> serde_json::Value {
+		let num_compiles = self.num_compiles.load(Ordering::SeqCst);
+		let avg_seconds = |counter : &AtomicU64| if num_compiles > 0 { load_seconds(counter) / num_compiles as f64 } else { 0.0 };
+		return serde_json::json!({
+			"compiler_exe": compilation_test.compiler_exe,
+			"compiler_args": compilation_test.compiler_args,
+			"num_compiles": num_compiles,
+			"num_failures": self.num_failures.load(Ordering::SeqCst),
+			"num_timeouts": self.num_timeouts.load(Ordering::SeqCst),
+			"num_load_failures": self.num_load_failures.load(Ordering::SeqCst),
+			"queue_wait_seconds": load_seconds(&self.queue_wait_nanos),
+			"compile_seconds": load_seconds(&self.compile_nanos),
+			"obj_parse_seconds": load_seconds(&self.obj_parse_nanos),
+			"avg_queue_wait_seconds": avg_seconds(&self.queue_wait_nanos),
+			"avg_compile_seconds": avg_seconds(&self.compile_nanos),
+			"avg_obj_parse_seconds": avg_seconds(&self.obj_parse_nanos)
+		});
+	}
+}
+
+// The phases a case spends its time in. Execute time includes generating the inputs (which is pretty cheap next to running them).
+// Queue wait is time spent waiting on a free compile worker, if that's high then --max-compile-jobs is the bottleneck
+pub const PHASE_NAMES : [&str; 6] = ["generate", "queue_wait", "compile", "obj_parse", "execute", "minimize"];
+
+// Shared between all the fuzzer threads, which bump the counters as they go
+pub struct FuzzStats {
+	pub num_cases : AtomicUsize,
+	pub num_bytes_fuzzed : AtomicUsize,
+	pub thread_num_cases : Vec<AtomicUsize>,
+
+	pub num_compiler_timeouts : AtomicUsize,
+	pub num_compiler_failures : AtomicUsize,
+	pub num_runtime_diffs : AtomicUsize,
+	pub num_obj_load_failures : AtomicUsize,
+	// Runtime diffs that turned out to be UB in the generated code, which don't count as bugs
+	pub num_generator_ub : AtomicUsize,
+	// Cases that find_potential_ub threw out before compiling them
+	pub num_rejected_cases : AtomicUsize,
+
+	// How many inputs got run through the compiled code, to see what num_inputs_per_codegen is costing us
+	pub num_inputs_executed : AtomicU64,
+
+	// Compile time only counts the first compile of each case, compiles done while minimizing count towards minimize time
+	pub generate_nanos : AtomicU64,
+	pub queue_wait_nanos : AtomicU64,
+	pub compile_nanos : AtomicU64,
+	pub obj_parse_nanos : AtomicU64,
+	pub execute_nanos : AtomicU64,
+	pub minimize_nanos : AtomicU64,
+
+	pub compilation_entries : Vec<CompilationEntryStats>
+}
+
+impl FuzzStats {
+	pub fn new(num_threads : u32, num_compilations : usize) -> Self {
+		Self {
+			num_cases: AtomicUsize::new(0),
+			num_bytes_fuzzed: AtomicUsize::new(0),
+			thread_num_cases: (0..num_threads).map(|_| AtomicUsize::new(0)).collect(),
+			num_compiler_timeouts: AtomicUsize::new(0),
+			num_compiler_failures: AtomicUsize::new(0),
+			num_runtime_diffs: AtomicUsize::new(0),
+			num_obj_load_failures: AtomicUsize::new(0),
+			num_generator_ub: AtomicUsize::new(0),
+			num_rejected_cases: AtomicUsize::new(0),
+			num_inputs_executed: AtomicU64::new(0),
+			generate_nanos: AtomicU64::new(0),
+			queue_wait_nanos: AtomicU64::new(0),
+			compile_nanos: AtomicU64::new(0),
+			obj_parse_nanos: AtomicU64::new(0),
+			execute_nanos: AtomicU64::new(0),
+			minimize_nanos: AtomicU64::new(0),
+			compilation_entries: (0..num_compilations).map(|_| CompilationEntryStats::default()).collect()
+		}
+	}
+
+	pub fn add_case(&self, thread_id : u32, num_cpp_bytes : usize) {
+		self.num_cases.fetch_add(1, Ordering::SeqCst);
+		self.thread_num_cases[thread_id as usize].fetch_add(1, Ordering::SeqCst);
+		self.num_bytes_fuzzed.fetch_add(num_cpp_bytes, Ordering::SeqCst);
+	}
+
+	pub fn add_bug(&self, category : FindingCategory) {
+		let counter = match category {
+			FindingCategory::CompilerTimeout => &self.num_compiler_timeouts,
+			FindingCategory::CompilerFailure => &self.num_compiler_failures,
+			FindingCategory::RuntimeDiff => &self.num_runtime_diffs,
+			FindingCategory::ObjectLoadFailure => &self.num_obj_load_failures,
+			FindingCategory::GeneratorUB => &self.num_generator_ub
+		};
+		counter.fetch_add(1, Ordering::SeqCst);
+	}
+
+	pub fn num_bugs(&self) -> usize {
+		return self.num_compiler_timeouts.load(Ordering::SeqCst)
+			+ self.num_compiler_failures.load(Ordering::SeqCst)
+			+ self.num_runtime_diffs.load(Ordering::SeqCst)
+			+ self.num_obj_load_failures.load(Ordering::SeqCst);
+	}
+
+	pub fn add_compilation_timings(&self, timings : &CompilationTimings) {
+		for (entry_stats, entry_timings) in self.compilation_entries.iter().zip(timings.entries.iter()) {
+			if let Some(entry_timings) = entry_timings {
+				entry_stats.num_compiles.fetch_add(1, Ordering::SeqCst);
+				add_duration(&entry_stats.queue_wait_nanos, entry_timings.queue_wait_time);
+				add_duration(&entry_stats.compile_nanos, entry_timings.compile_time);
+				add_duration(&entry_stats.obj_parse_nanos, entry_timings.obj_parse_time);
+				match entry_timings.outcome {
+					CompileEntryOutcome::Success => {}
+					CompileEntryOutcome::Failure => { entry_stats.num_failures.fetch_add(1, Ordering::SeqCst); }
+					CompileEntryOutcome::Timeout => { entry_stats.num_timeouts.fetch_add(1, Ordering::SeqCst); }
+					CompileEntryOutcome::LoadFailure => { entry_stats.num_load_failures.fetch_add(1, Ordering::SeqCst); }
+				}
+				add_duration(&self.queue_wait_nanos, entry_timings.queue_wait_time);
+				add_duration(&self.compile_nanos, entry_timings.compile_time);
+				add_duration(&self.obj_parse_nanos, entry_timings.obj_parse_time);
+			}
+		}
+	}
+
+	// In the same order as PHASE_NAMES
+	pub fn get_phase_seconds(&self) -> [f64; 6] {
+		return [
+			load_seconds(&self.generate_nanos),
+			load_seconds(&self.queue_wait_nanos),
+			load_seconds(&self.compile_nanos),
+			load_seconds(&self.obj_parse_nanos),
+			load_seconds(&self.execute_nanos),
+			load_seconds(&self.minimize_nanos)
+		];
+	}
+
+	// Something like "gen 1.2% | queue 0.5% | compile 90.1% | ...", as a share of the time the threads have spent across all phases
+	pub fn get_phase_breakdown_string(&self) -> String {
+		let [generate, queue_wait, compile, obj_parse, execute, minimize] = self.get_phase_seconds();
+		let total = generate + queue_wait + compile + obj_parse + execute + minimize;
+		let percent = |seconds : f64| if total > 0.0 { seconds / total * 100.0 } else { 0.0 };
+
+		let num_inputs_executed = self.num_inputs_executed.load(Ordering::SeqCst);
+		let usec_per_input = if num_inputs_executed > 0 { execute / num_inputs_executed as f64 * 1_000_000.0 } else { 0.0 };
+
+		return format!("gen {:5.1}% | queue {:5.1}% | compile {:5.1}% | obj parse {:5.1}% | exec {:5.1}% ({:.1} us/input) | minimize {:5.1}%",
+			percent(generate), percent(queue_wait), percent(compile), percent(obj_parse), percent(execute), usec_per_input, percent(minimize));
+	}
+
+	pub fn to_json(&self, fuzzer_name : &str, elapsed : Duration, compilation_tests : &Vec<TestCompilation>) -> serde_json::Value {
+		let thread_num_cases : Vec<usize> = self.thread_num_cases.iter().map(|num_cases| num_cases.load(Ordering::SeqCst)).collect();
+
+		let compilations_json : Vec<serde_json::Value> = self.compilation_entries.iter().zip(compilation_tests.iter())
+			.map(|(entry_stats, compilation_test)| entry_stats.to_json(compilation_test)).collect();
+
+		let mut time_seconds_json = serde_json::Map::new();
+		for (phase_name, phase_seconds) in PHASE_NAMES.iter().zip(self.get_phase_seconds().iter()) {
+			time_seconds_json.insert(phase_name.to_string(), serde_json::json!(phase_seconds));
+		}
+
+		let num_cases = self.num_cases.load(Ordering::SeqCst);
+		let elapsed_seconds = elapsed.as_secs_f64();
+		return serde_json::json!({
+			"fuzzer": fuzzer_name,
+			"uptime_seconds": elapsed_seconds,
+			"num_cases": num_cases,
+			"cases_per_second": if elapsed_seconds > 0.0 { num_cases as f64 / elapsed_seconds } else { 0.0 },
+			"num_bytes_fuzzed": self.num_bytes_fuzzed.load(Ordering::SeqCst),
+			"thread_num_cases": thread_num_cases,
+			"num_inputs_executed": self.num_inputs_executed.load(Ordering::SeqCst),
+			"num_bugs": self.num_bugs(),
+			"num_generator_ub": self.num_generator_ub.load(Ordering::SeqCst),
+			"num_rejected_cases": self.num_rejected_cases.load(Ordering::SeqCst),
+			"bugs": {
+				FindingCategory::CompilerTimeout.dir_name(): self.num_compiler_timeouts.load(Ordering::SeqCst),
+				FindingCategory::CompilerFailure.dir_name(): self.num_compiler_failures.load(Ordering::SeqCst),
+				FindingCategory::RuntimeDiff.dir_name(): self.num_runtime_diffs.load(Ordering::SeqCst),
+				FindingCategory::ObjectLoadFailure.dir_name(): self.num_obj_load_failures.load(Ordering::SeqCst)
+			},
+			"time_seconds": time_seconds_json,
+			"compilations": compilations_json
+		});
+	}
+
+	// Prometheus text format, for node_exporter's textfile collector
+	pub fn to_prometheus(&self, fuzzer_name : &str, elapsed : Duration) -> String {
+		let mut prom = String::new();
+
+		prom.push_str("# TYPE codegen_fuzzer_uptime_seconds gauge\n");
+		prom.push_str(&format!("codegen_fuzzer_uptime_seconds{{fuzzer=\"{}\"}} {}\n", fuzzer_name, elapsed.as_secs_f64()));
+
+		prom.push_str("# TYPE codegen_fuzzer_cases_total counter\n");
+		for (thread_id, num_cases) in self.thread_num_cases.iter().enumerate() {
+			prom.push_str(&format!("codegen_fuzzer_cases_total{{fuzzer=\"{}\",thread=\"{}\"}} {}\n", fuzzer_name, thread_id, num_cases.load(Ordering::SeqCst)));
+		}
+
+		prom.push_str("# TYPE codegen_fuzzer_bytes_fuzzed_total counter\n");
+		prom.push_str(&format!("codegen_fuzzer_bytes_fuzzed_total{{fuzzer=\"{}\"}} {}\n", fuzzer_name, self.num_bytes_fuzzed.load(Ordering::SeqCst)));
+
+		prom.push_str("# TYPE codegen_fuzzer_bugs_total counter\n");
+		let bug_counters = [
+			(FindingCategory::CompilerTimeout, &self.num_compiler_timeouts),
+			(FindingCategory::CompilerFailure, &self.num_compiler_failures),
+			(FindingCategory::RuntimeDiff, &self.num_runtime_diffs),
+			(FindingCategory::ObjectLoadFailure, &self.num_obj_load_failures)
+		];
+		for (category, counter) in bug_counters.iter() {
+			prom.push_str(&format!("codegen_fuzzer_bugs_total{{fuzzer=\"{}\",category=\"{}\"}} {}\n", fuzzer_name, category.dir_name(), counter.load(Ordering::SeqCst)));
+		}
+
+		prom.push_str("# TYPE codegen_fuzzer_generator_ub_total counter\n");
+		prom.push_str(&format!("codegen_fuzzer_generator_ub_total{{fuzzer=\"{}\"}} {}\n", fuzzer_name, self.num_generator_ub.load(Ordering::SeqCst)));
+
+		prom.push_str("# TYPE codegen_fuzzer_rejected_cases_total counter\n");
+		prom.push_str(&format!("codegen_fuzzer_rejected_cases_total{{fuzzer=\"{}\"}} {}\n", fuzzer_name, self.num_rejected_cases.load(Ordering::SeqCst)));
+
+		prom.push_str("# TYPE codegen_fuzzer_inputs_executed_total counter\n");
+		prom.push_str(&format!("codegen_fuzzer_inputs_executed_total{{fuzzer=\"{}\"}} {}\n", fuzzer_name, self.num_inputs_executed.load(Ordering::SeqCst)));
+
+		prom.push_str("# TYPE codegen_fuzzer_phase_seconds_total counter\n");
+		for (phase_name, phase_seconds) in PHASE_NAMES.iter().zip(self.get_phase_seconds().iter()) {
+			prom.push_str(&format!("codegen_fuzzer_phase_seconds_total{{fuzzer=\"{}\",phase=\"{}\"}} {}\n", fuzzer_name, phase_name, phase_seconds));
+		}
+
+		prom.push_str("# TYPE codegen_fuzzer_compiles_total counter\n");
+		for (entry_idx, entry_stats) in self.compilation_entries.iter().enumerate() {
+			prom.push_str(&format!("codegen_fuzzer_compiles_total{{fuzzer=\"{}\",compilation=\"{}\"}} {}\n", fuzzer_name, entry_idx, entry_stats.num_compiles.load(Ordering::SeqCst)));
+		}
+
+		prom.push_str("# TYPE codegen_fuzzer_compilation_phase_seconds_total counter\n");
+		for (entry_idx, entry_stats) in self.compilation_entries.iter().enumerate() {
+			for (phase_name, counter) in [("queue_wait", &entry_stats.queue_wait_nanos), ("compile", &entry_stats.compile_nanos), ("obj_parse", &entry_stats.obj_parse_nanos)].iter() {
+				prom.push_str(&format!("codegen_fuzzer_compilation_phase_seconds_total{{fuzzer=\"{}\",compilation=\"{}\",phase=\"{}\"}} {}\n",
+					fuzzer_name, entry_idx, phase_name, load_seconds(counter)));
+			}
+		}
+
+		return prom;
+	}
+}
+
+// Write then rename, so whatever's reading the file never sees half of it
+pub fn write_stats_file(filename : &str, contents : &str) {
+	let tmp_filename = format!("{}.tmp", filename);
+	std::fs::write(&tmp_filename, contents).expect("couldn't write to file?");
+	std::fs::rename(&tmp_filename, filename).expect("couldn't rename stats file?");
+}
@@ -0,0 +1,547 @@
+
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use sha2::{Sha256, Digest};
+
+use crate::compilation_config::{test_generated_code_compilation, test_generated_code_compilation_with_all_failures, expand_placeholders, PlaceholderValues, RunTmpDir, CompilationTimings, CompilationConfig, TestCompilation, GenCodeResult, GenCodeFuzzMode, CompiledCodeOutput, CompilerIOThread, CompilerIOThreadHandle, get_default_max_compile_jobs};
+use crate::codegen_fuzzing::{CodegenFuzzer, CaseSeed};
+use crate::saved_findings::{SavedFinding, FindingCategory, FUZZ_ISSUES_DIR};
+use crate::sanitizer_check::{expand_sanitizer_placeholders, reclassify_runtime_diff};
+use crate::exec_mem::{ExecCrash, ExecUnavailable};
+use crate::fuzz_stats::{FuzzStats, add_duration, write_stats_file};
+
+// Options that apply to every fuzzer: a fixed --seed makes the run deterministic, --iterations caps the number of cases (not counting ones rejected for possible UB)
+// The time/bug budgets are checked by the stats loop, so they can overshoot by whatever cases are in flight when they're hit
+pub struct FuzzRunOptions {
+	pub seed : Option<u64>,
+	pub max_iterations : Option<u64>,
+	pub max_time : Option<Duration>,
+	pub stop_after_bugs : Option<usize>,
+	// Where to periodically dump stats to, and how often
+	pub stats_filename : Option<String>,
+	pub prometheus_filename : Option<String>,
+	pub stats_interval : Duration,
+	// How many compiler processes can be running at once across all threads, defaults to the number of CPUs.
+	// This is separate from the thread count, which is how many cases are being generated/executed at once
+	pub max_compile_jobs : Option<u32>
+}
+
+pub struct FuzzRunSummary {
+	pub num_cases : usize,
+	pub num_bugs : usize,
+	pub elapsed : Duration,
+	// Set if the run stopped because there was nowhere left to run the code (see ExecUnavailable)
+	pub exec_error : Option<String>
+}
+
+static SIGINT_RECEIVED : AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_sigint(_signal : libc::c_int) {
+	// The first Ctrl-C lets threads wrap up their current case, a second one means get out now
+	if SIGINT_RECEIVED.swap(true, Ordering::SeqCst) {
+		unsafe { libc::_exit(130); }
+	}
+}
+
+fn install_sigint_handler() {
+	unsafe {
+		libc::signal(libc::SIGINT, handle_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t);
+	}
+}
+
+pub fn get_initial_thread_seed(run_options : &FuzzRunOptions, thread_id : u32, initial_time : u64) -> u64 {
+	if let Some(seed) = run_options.seed {
+		return seed.wrapping_add(thread_id as u64);
+	}
+
+	// Some prime numbers beause they're better, or so I hear
+	return ((thread_id as u64) + 937) * 241 + initial_time;
+}
+
+// --iterations is the total for the run, so split it up between the threads
+pub fn get_thread_max_cases(run_options : &FuzzRunOptions, thread_id : u32, num_threads : u32) -> Option<u64> {
+	let max_iterations = run_options.max_iterations?;
+	let num_threads = num_threads as u64;
+	let thread_id = thread_id as u64;
+	return Some(max_iterations / num_threads + if thread_id < max_iterations % num_threads { 1 } else { 0 });
+}
+
+fn get_hex_hash_of_bytes(input : &[u8]) -> String {
+	let mut hasher = Sha256::new();
+	hasher.update(input);
+	let digest = hasher.finalize();
+	hex::encode(digest)
+}
+
+//use std::arch::x86_64::{_rdtsc};
+
+pub fn get_timestamp_for_seed() -> u64 {
+	SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+// Gives back the hash the finding got saved under
+fn save_out_failure_info(fuzzer_name : &str, case_seed : &CaseSeed, input_index : Option<u32>, orig_code : &str, min_code : &str, result : &GenCodeResult, metadata : &str) -> String {
+	let min_hex_hash_full = get_hex_hash_of_bytes(min_code.as_bytes());
+	let min_hex_hash = &min_hex_hash_full[0..10];
+
+	// Older checkouts won't have the directories for newer categories
+	let category = FindingCategory::from_result(result).expect("");
+	std::fs::create_dir_all(format!("fuzz_issues/{}", category.dir_name())).expect("couldn't create fuzz issues directory");
+
+	match result {
+		GenCodeResult::CompilerTimeout => {
+			let orig_code_filename = format!("fuzz_issues/compiler_timeouts/{}_orig.cpp", min_hex_hash);
+			let min_code_filename = format!("fuzz_issues/compiler_timeouts/{}_min.cpp", min_hex_hash);
+			
+			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
+			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
+		}
+		GenCodeResult::CompilerFailure(_,_,_) => {
+			let orig_code_filename = format!("fuzz_issues/compiler_fails/{}_orig.cpp", min_hex_hash);
+			let min_code_filename = format!("fuzz_issues/compiler_fails/{}_min.cpp", min_hex_hash);
+			
+			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
+			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
+		}
+		GenCodeResult::RuntimeDiff(input) => {
+			let orig_code_filename = format!("fuzz_issues/runtime_diffs/{}_orig.cpp", min_hex_hash);
+			let min_code_filename = format!("fuzz_issues/runtime_diffs/{}_min.cpp", min_hex_hash);
+			let input_filename = format!("fuzz_issues/runtime_diffs/{}_input.input", min_hex_hash);
+			let min_meta_filename = format!("fuzz_issues/runtime_diffs/{}_min_meta.meta", min_hex_hash);
+			
+			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
+			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
+			std::fs::write(input_filename, input).expect("couldn't write to file?");
+			std::fs::write(min_meta_filename, metadata).expect("couldn't write to file?");
+		}
+		GenCodeResult::ObjectLoadFailure(load_error, obj_data) => {
+			let orig_code_filename = format!("fuzz_issues/obj_load_fails/{}_orig.cpp", min_hex_hash);
+			let min_code_filename = format!("fuzz_issues/obj_load_fails/{}_min.cpp", min_hex_hash);
+			let obj_filename = format!("fuzz_issues/obj_load_fails/{}_orig.o", min_hex_hash);
+			let error_filename = format!("fuzz_issues/obj_load_fails/{}_error.txt", min_hex_hash);
+
+			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
+			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
+			std::fs::write(obj_filename, obj_data).expect("couldn't write to file?");
+			std::fs::write(error_filename, format!("{}\n", load_error)).expect("couldn't write to file?");
+		}
+		_ => panic!("uuhhhh....implement this")
+	}
+
+	// Keep track of which fuzzer found it and the seeds it came from, so we know how to re-run it later
+	// NOTE: The seeds regenerate the original case, not the minimized one
+	let info_filename = format!("fuzz_issues/{}/{}_info.json", category.dir_name(), min_hex_hash);
+	let info_json = serde_json::json!({
+		"fuzzer": fuzzer_name,
+		"thread_seed": case_seed.thread_seed,
+		"case_index": case_seed.case_index,
+		"input_index": input_index,
+		"replay_seed": case_seed.to_replay_string(input_index.unwrap_or(0))
+	});
+	std::fs::write(info_filename, serde_json::to_string_pretty(&info_json).expect("")).expect("couldn't write to file?");
+
+	return min_hex_hash.to_string();
+}
+
+// Minimizing a load failure has to keep the same error, so we don't end up chasing a different relocation
+fn is_same_compile_failure(result : &GenCodeResult, minim_result : &GenCodeResult) -> bool {
+	match (result, minim_result) {
+		(GenCodeResult::CompilerTimeout, GenCodeResult::CompilerTimeout) => true,
+		(GenCodeResult::CompilerFailure(_,_,_), GenCodeResult::CompilerFailure(_,_,_)) => true,
+		(GenCodeResult::ObjectLoadFailure(load_error, _), GenCodeResult::ObjectLoadFailure(minim_load_error, _)) => load_error == minim_load_error,
+		_ => false
+	}
+}
+
+// For timeouts, compiler failures and load failures: minimize down to something that still fails the same way with compilation_tests, and save it out
+fn minimize_and_save_compile_failure<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>(
+		fuzzer : &FuzzType, fuzzer_name : &str, case_seed : &CaseSeed, codegen_ctx : CodegenCtx, cpp_code : &str, code_meta : &CodeMeta,
+		result : &GenCodeResult, compilation_tests : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle, stats : &FuzzStats)
+	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput> {
+
+	let minim_checker = |this_fuzzer : &FuzzType, ctx: &CodegenCtx| {
+		let (minim_cpp_code, _) = this_fuzzer.generate_cpp_code(ctx);
+		let minim_res = test_generated_code_compilation(&minim_cpp_code, compilation_tests, io_thread_handle);
+		return is_same_compile_failure(result, &minim_res);
+	};
+
+	let minimize_start = Instant::now();
+	if let Some(min_ctx) = fuzzer.try_minimize(codegen_ctx, minim_checker) {
+		let (min_cpp_code,min_code_meta) = fuzzer.generate_cpp_code(&min_ctx);
+		let min_code_meta = fuzzer.save_meta_to_string(&min_code_meta);
+		save_out_failure_info(fuzzer_name, case_seed, None, cpp_code, &min_cpp_code, result, &min_code_meta);
+	}
+	else {
+		println!("Could not minimize for whatever reason");
+		let code_meta = fuzzer.save_meta_to_string(code_meta);
+		save_out_failure_info(fuzzer_name, case_seed, None, cpp_code, cpp_code, result, &code_meta);
+	}
+	add_duration(&stats.minimize_nanos, minimize_start.elapsed());
+
+	stats.add_bug(FindingCategory::from_result(result).expect(""));
+}
+
+// Runs the input through each compiled output, and checks if any of them disagree with the first one
+pub fn do_compiled_outputs_differ<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>(
+		fuzzer : &FuzzType, compiled_outputs : &Vec<CompiledCodeOutput>, code_meta : &CodeMeta, input : &FuzzerInput) -> bool
+	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput> {
+
+	let mut first_output : Option<FuzzerOutput> = None;
+	for compiled_out in compiled_outputs.iter() {
+		// None of the generated code should ever crash, so code crashing whatever process it ran in counts no matter what the others did
+		let output = match std::panic::catch_unwind(AssertUnwindSafe(|| fuzzer.execute(&compiled_out.code_page, code_meta, input))) {
+			Ok(output) => output,
+			Err(panic_payload) if panic_payload.is::<ExecCrash>() => { return true; }
+			Err(panic_payload) => { std::panic::resume_unwind(panic_payload); }
+		};
+		if let Some(ref first_output) = first_output {
+			if !fuzzer.are_outputs_the_same(first_output, &output) {
+				//println!("OUTPUT DIFF:");
+				//println!("O1: {:?}", first_output);
+				//println!("O2: {:?}", output);
+				return true;
+			}
+		}
+		else {
+			first_output = Some(output);
+		}
+	}
+
+	return false;
+}
+
+fn fuzz_simd_codegen_loop<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>(
+		fuzzer_name : &str, input : ThreadInput, compilation_test_templates : &Vec<TestCompilation>, sanitizer_compilation_template : Option<TestCompilation>,
+		placeholder_values : PlaceholderValues, fuzz_mode : GenCodeFuzzMode,
+		stats : Arc<FuzzStats>, thread_id : u32, io_thread_handle : CompilerIOThreadHandle, max_cases : Option<u64>, should_stop : Arc<AtomicBool>
+	)
+	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>, FuzzerOutput: Clone + std::fmt::Debug, CodegenCtx: Clone {
+	
+	let mut fuzzer = FuzzType::new_fuzzer_state(input);
+	
+	let num_inputs_per_codegen = fuzzer.num_inputs_per_codegen();
+	let thread_seed = fuzzer.get_thread_seed();
+	
+	// Rejected cases still use up a case index (so every index keeps meaning the same case for replay),
+	// but max_cases only counts the ones that actually got compiled
+	let mut num_compiled_cases = 0;
+	for case_index in 0..u64::MAX {
+		// Only checked between cases, so anything we find (and its minimizing) always gets saved out
+		if should_stop.load(Ordering::SeqCst) || max_cases.is_some_and(|max_cases| num_compiled_cases >= max_cases) {
+			break;
+		}
+
+		let case_seed = CaseSeed { thread_seed: thread_seed, case_index: case_index };
+		let case_placeholder_values = PlaceholderValues { case_id: Some(case_index), ..placeholder_values.clone() };
+		let compilation_tests = &expand_placeholders(compilation_test_templates, &case_placeholder_values);
+		let generate_start = Instant::now();
+		let codegen_ctx = fuzzer.generate_ctx(case_seed.ctx_seed());
+
+		// Compilers are allowed to disagree on these, so there's no point compiling them
+		if fuzzer.find_potential_ub(&codegen_ctx).len() > 0 {
+			add_duration(&stats.generate_nanos, generate_start.elapsed());
+			stats.num_rejected_cases.fetch_add(1, Ordering::SeqCst);
+			continue;
+		}
+		
+		let (cpp_code, code_meta) = fuzzer.generate_cpp_code(&codegen_ctx);
+		add_duration(&stats.generate_nanos, generate_start.elapsed());
+		num_compiled_cases += 1;
+
+		//println!("----------CODE-------------");
+		//println!("{}", cpp_code);
+		//println!("---------------------------");
+
+		let mut compilation_timings = CompilationTimings::default();
+		let (res, other_failures) = test_generated_code_compilation_with_all_failures(&cpp_code, compilation_tests, &io_thread_handle, &mut compilation_timings);
+		stats.add_compilation_timings(&compilation_timings);
+
+		// With parallel compilations, every other entry that failed gets saved too. Each one is minimized against just its own entry,
+		// since the whole set would keep giving back the first failure
+		for (compile_idx, other_failure) in other_failures.iter() {
+			let entry_compilation_tests = vec![compilation_tests[*compile_idx].clone()];
+			minimize_and_save_compile_failure(&fuzzer, fuzzer_name, &case_seed, codegen_ctx.clone(), &cpp_code, &code_meta, other_failure, &entry_compilation_tests, &io_thread_handle, &stats);
+		}
+
+		// TODO: UTF-8, bytes not necessarily same as chars, idk what rust gives but we only do ascii in this house so w/e
+		let num_cpp_bytes = cpp_code.len();
+
+		match res {
+			GenCodeResult::CompilerTimeout | GenCodeResult::CompilerFailure(_,_,_) | GenCodeResult::ObjectLoadFailure(_,_) => {
+				minimize_and_save_compile_failure(&fuzzer, fuzzer_name, &case_seed, codegen_ctx, &cpp_code, &code_meta, &res, compilation_tests, &io_thread_handle, &stats);
+			}
+			GenCodeResult::Success(ref compiled_outputs) => {
+				if matches!(fuzz_mode, GenCodeFuzzMode::CrashAndDiff) {
+					let execute_start = Instant::now();
+					let mut bad_input : Option<(u32, FuzzerInput)> = None;
+					for input_index in 0..num_inputs_per_codegen {
+						let input = fuzzer.generate_random_input(&code_meta, case_seed.input_seed(input_index));
+						stats.num_inputs_executed.fetch_add(1, Ordering::SeqCst);
+						if do_compiled_outputs_differ(&fuzzer, compiled_outputs, &code_meta, &input) {
+							bad_input = Some((input_index, input));
+							break;
+						}
+					}
+					add_duration(&stats.execute_nanos, execute_start.elapsed());
+					
+					if let Some((bad_input_index, bad_input)) = bad_input {
+						let minim_checker = |this_fuzzer : &FuzzType, ctx: &CodegenCtx| {
+							// Otherwise it could 'minimize' down to a different diff that's just UB
+							if this_fuzzer.find_potential_ub(ctx).len() > 0 {
+								return false;
+							}
+
+							let (minim_cpp_code, minim_code_meta) = this_fuzzer.generate_cpp_code(ctx);
+							let minim_res = test_generated_code_compilation(&minim_cpp_code, compilation_tests, &io_thread_handle);
+							if let GenCodeResult::Success(minim_compiled_outputs) = minim_res {
+								return do_compiled_outputs_differ(&fuzzer, &minim_compiled_outputs, &minim_code_meta, &bad_input);
+							}
+							
+							return false;
+						};
+						
+						let input_str = fuzzer.save_input_to_string(&bad_input);
+						let minimize_start = Instant::now();
+						let (saved_cpp_code, saved_hash) = if let Some(min_ctx) = fuzzer.try_minimize(codegen_ctx, minim_checker) {
+							let (min_cpp_code, min_meta) = fuzzer.generate_cpp_code(&min_ctx);
+							let min_meta = fuzzer.save_meta_to_string(&min_meta);
+							let min_hash = save_out_failure_info(fuzzer_name, &case_seed, Some(bad_input_index), &cpp_code, &min_cpp_code, &GenCodeResult::RuntimeDiff(input_str.clone()), &min_meta);
+							(min_cpp_code, min_hash)
+						}
+						else {
+							println!("Could not minimize for whatever reason");
+							let code_meta = fuzzer.save_meta_to_string(&code_meta);
+							let hash = save_out_failure_info(fuzzer_name, &case_seed, Some(bad_input_index), &cpp_code, &cpp_code, &GenCodeResult::RuntimeDiff(input_str.clone()), &code_meta);
+							(cpp_code.clone(), hash)
+						};
+						add_duration(&stats.minimize_nanos, minimize_start.elapsed());
+
+						// It's only a compiler bug if the code didn't have UB to begin with
+						let category = match &sanitizer_compilation_template {
+							Some(sanitizer_compilation_template) => {
+								let sanitizer_compilation = expand_sanitizer_placeholders(compilation_test_templates, sanitizer_compilation_template, &case_placeholder_values);
+								reclassify_runtime_diff(FUZZ_ISSUES_DIR, &saved_hash, &saved_cpp_code, &input_str, &sanitizer_compilation, &io_thread_handle)
+							}
+							None => FindingCategory::RuntimeDiff
+						};
+
+						stats.add_bug(category);
+					}
+				}
+			}
+			_ => { panic!("bad possible return type from compilation") }
+		};
+
+		stats.add_case(thread_id, num_cpp_bytes);
+	}
+}
+
+fn write_stats_files(fuzzer_name : &str, stats : &FuzzStats, elapsed : Duration, compilation_tests : &Vec<TestCompilation>, run_options : &FuzzRunOptions) {
+	if let Some(stats_filename) = &run_options.stats_filename {
+		let stats_json = stats.to_json(fuzzer_name, elapsed, compilation_tests);
+		write_stats_file(stats_filename, &serde_json::to_string_pretty(&stats_json).expect(""));
+	}
+
+	if let Some(prometheus_filename) = &run_options.prometheus_filename {
+		write_stats_file(prometheus_filename, &stats.to_prometheus(fuzzer_name, elapsed));
+	}
+}
+
+// Runs any CodegenFuzzer across num_threads threads until they're done or we're told to stop, printing stats every second.
+// make_thread_input gets called with each thread's seed, and should give back that thread's input
+pub fn run_fuzzer<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput,MakeThreadInput>(
+		fuzzer_name : &'static str, compilation_config : &CompilationConfig, num_threads : u32, run_options : &FuzzRunOptions,
+		make_thread_input : MakeThreadInput
+	) -> FuzzRunSummary
+	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput> + 'static, ThreadInput : Send + 'static,
+		CodegenCtx : Clone + 'static, CodeMeta : 'static, FuzzerInput : 'static, FuzzerOutput : Clone + std::fmt::Debug + 'static,
+		MakeThreadInput : Fn(u64) -> ThreadInput {
+
+	let fuzz_mode = compilation_config.fuzz_mode;
+
+	let mut thread_handles = Vec::<std::thread::JoinHandle<_>>::new();
+	print!("Launching fuzzer with {} threads\n", num_threads);
+
+	let stats = Arc::new(FuzzStats::new(num_threads, compilation_config.compilations.len()));
+	let should_stop = Arc::new(AtomicBool::new(false));
+
+	install_sigint_handler();
+	
+	// This should ensure subsequent runs don't re-use the same seeds for everything
+	let initial_time = get_timestamp_for_seed();//unsafe { _rdtsc() };
+	
+	let max_compile_jobs = run_options.max_compile_jobs.unwrap_or_else(get_default_max_compile_jobs);
+	print!("Running at most {} compiler processes at once\n", max_compile_jobs);
+	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread_with_max_compile_jobs(max_compile_jobs);
+	let run_tmp_dir = RunTmpDir::create(fuzzer_name);
+	
+	for thread_id in 0..num_threads {
+		let compilation_test_templates = compilation_config.compilations.clone();
+		let sanitizer_compilation_template = compilation_config.sanitizer_compilation.clone();
+		let placeholder_values = run_tmp_dir.placeholder_values(Some(thread_id));
+
+		let stats = stats.clone();
+		let should_stop = should_stop.clone();
+		
+		let initial_seed = get_initial_thread_seed(run_options, thread_id, initial_time);
+		let max_cases = get_thread_max_cases(run_options, thread_id, num_threads);
+		let thread_input = make_thread_input(initial_seed);
+		
+		let io_thread_handle = io_thread_handle.clone();
+		
+		let thread_handle = std::thread::spawn(move || -> Option<String> {
+			let loop_res = std::panic::catch_unwind(AssertUnwindSafe(|| fuzz_simd_codegen_loop::<FuzzType, ThreadInput, CodegenCtx, CodeMeta, FuzzerInput, FuzzerOutput>(
+				fuzzer_name, thread_input, &compilation_test_templates, sanitizer_compilation_template, placeholder_values, fuzz_mode, stats, thread_id, io_thread_handle, max_cases, should_stop.clone())));
+
+			// Every other thread is about to hit the same thing, so stop the whole run
+			match loop_res {
+				Ok(()) => { return None; }
+				Err(panic_payload) => match panic_payload.downcast::<ExecUnavailable>() {
+					Ok(exec_unavailable) => {
+						print!("Thread {} can't run code any more, stopping: {}\n", thread_id, exec_unavailable.0);
+						should_stop.store(true, Ordering::SeqCst);
+						return Some(exec_unavailable.0);
+					}
+					Err(panic_payload) => { std::panic::resume_unwind(panic_payload); }
+				}
+			}
+		});
+		thread_handles.push(thread_handle);
+	}
+	
+	print!("Done launching\n");
+	
+	let stats_label = fuzzer_name.to_uppercase();
+	let start_time = Instant::now();
+	let mut last_stats_file_time = start_time;
+	loop {
+		std::thread::sleep(Duration::from_secs(1));
+		let time_so_far = Instant::now().duration_since(start_time);
+		let seconds_so_far = time_so_far.as_secs_f32();
+		let num_cases_so_far = stats.num_cases.load(Ordering::SeqCst);
+		let avg_cases_per_second = num_cases_so_far as f32 / seconds_so_far;
+		let num_bugs_so_far = stats.num_bugs();
+
+		let num_bytes_so_far = stats.num_bytes_fuzzed.load(Ordering::SeqCst);
+		
+		const BYTES_PER_KB : f64 = 1024.0;
+		const BYTES_PER_GB : f64 = 1024.0 * 1024.0 * 1024.0;
+		let avg_kb_per_sec = (num_bytes_so_far as f64) / (seconds_so_far as f64) / BYTES_PER_KB;
+		let num_gb_so_far = (num_bytes_so_far as f64) / BYTES_PER_GB;
+
+		print!("{} | {:10.1} sec uptime | {:10} cases | {:10.2} cps | {:5} bugs | {:8.3} KB/s code fuzzed | {:8.4} GB code total\n",
+			stats_label, seconds_so_far, num_cases_so_far, avg_cases_per_second, num_bugs_so_far, avg_kb_per_sec, num_gb_so_far);
+		print!("{} | {}\n", stats_label, stats.get_phase_breakdown_string());
+
+		if last_stats_file_time.elapsed() >= run_options.stats_interval {
+			write_stats_files(fuzzer_name, &stats, time_so_far, &compilation_config.compilations, run_options);
+			last_stats_file_time = Instant::now();
+		}
+
+		if thread_handles.iter().all(|thread_handle| thread_handle.is_finished()) {
+			break;
+		}
+
+		if !should_stop.load(Ordering::SeqCst) {
+			let stop_reason = if SIGINT_RECEIVED.load(Ordering::SeqCst) {
+				Some("interrupted")
+			}
+			else if run_options.max_time.map_or(false, |max_time| time_so_far >= max_time) {
+				Some("hit --max-time")
+			}
+			else if run_options.stop_after_bugs.map_or(false, |max_bugs| num_bugs_so_far >= max_bugs) {
+				Some("hit --stop-after-bugs")
+			}
+			else {
+				None
+			};
+
+			if let Some(stop_reason) = stop_reason {
+				print!("Stopping ({}), waiting for threads to finish their current case...\n", stop_reason);
+				should_stop.store(true, Ordering::SeqCst);
+			}
+		}
+	}
+
+	let mut exec_error : Option<String> = None;
+	for thread_handle in thread_handles {
+		if let Some(thread_exec_error) = thread_handle.join().expect("could not join fuzzer thread") {
+			exec_error.get_or_insert(thread_exec_error);
+		}
+	}
+
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("could not join compiler IO thread");
+
+	let summary = FuzzRunSummary {
+		num_cases: stats.num_cases.load(Ordering::SeqCst),
+		num_bugs: stats.num_bugs(),
+		elapsed: start_time.elapsed(),
+		exec_error: exec_error
+	};
+
+	// One last snapshot so the files match the final summary
+	write_stats_files(fuzzer_name, &stats, summary.elapsed, &compilation_config.compilations, run_options);
+
+	print!("{} | Done: {} cases in {:.1} sec, {} bugs found\n", stats_label, summary.num_cases, summary.elapsed.as_secs_f32(), summary.num_bugs);
+	return summary;
+}
+
+pub fn get_runtime_diff_checker<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>(thread_input : ThreadInput, finding : &SavedFinding)
+		-> Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool>
+	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput> + 'static, CodeMeta : 'static, FuzzerInput : 'static {
+
+	let fuzzer = FuzzType::new_fuzzer_state(thread_input);
+	let code_meta = fuzzer.read_meta_from_string(finding.meta.as_ref().expect("runtime diff finding is missing its meta file"));
+	let input = fuzzer.read_input_from_string(finding.input.as_ref().expect("runtime diff finding is missing its input file"));
+
+	return Box::new(move |compiled_outputs| do_compiled_outputs_differ(&fuzzer, compiled_outputs, &code_meta, &input));
+}
+
+// Regenerates a single case from its seed and runs it the same way the fuzz loop would, minus minimizing and saving it out
+pub fn replay_case<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>(
+		thread_input : ThreadInput, case_seed : CaseSeed, input_index : u32, compilation_tests : &Vec<TestCompilation>, fuzz_mode : GenCodeFuzzMode,
+		replay_dir : &Path, io_thread_handle : &CompilerIOThreadHandle) -> Option<FindingCategory>
+	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput> {
+
+	let mut fuzzer = FuzzType::new_fuzzer_state(thread_input);
+	let codegen_ctx = fuzzer.generate_ctx(case_seed.ctx_seed());
+	// The fuzz loop would've skipped it, but it's still worth being able to look at
+	let potential_ub = fuzzer.find_potential_ub(&codegen_ctx);
+	if potential_ub.len() > 0 {
+		print!("NOTE: the fuzz loop would have rejected this case since it might have UB: {}\n", potential_ub.join(", "));
+	}
+	let (cpp_code, code_meta) = fuzzer.generate_cpp_code(&codegen_ctx);
+	let input = fuzzer.generate_random_input(&code_meta, case_seed.input_seed(input_index));
+
+	let replay_code_filename = replay_dir.join("replay.cpp");
+	let replay_input_filename = replay_dir.join("replay_input.input");
+	let replay_meta_filename = replay_dir.join("replay_meta.meta");
+	std::fs::write(&replay_code_filename, &cpp_code).expect("couldn't write to file?");
+	std::fs::write(&replay_input_filename, fuzzer.save_input_to_string(&input)).expect("couldn't write to file?");
+	std::fs::write(&replay_meta_filename, fuzzer.save_meta_to_string(&code_meta)).expect("couldn't write to file?");
+	print!("Wrote regenerated case to '{}', '{}' and '{}'\n", replay_code_filename.display(), replay_input_filename.display(), replay_meta_filename.display());
+
+	let res = test_generated_code_compilation(&cpp_code, compilation_tests, &io_thread_handle);
+	match res {
+		GenCodeResult::CompilerFailure(err_code, ref stdout, ref stderr) => {
+			print!("Compiler failed with code {}\n", err_code);
+			print!("---stdout---\n{}\n---stderr---\n{}\n", stdout, stderr);
+		}
+		GenCodeResult::ObjectLoadFailure(ref load_error, _) => {
+			print!("Could not load compiled object: {}\n", load_error);
+		}
+		GenCodeResult::Success(ref compiled_outputs) => {
+			if matches!(fuzz_mode, GenCodeFuzzMode::CrashAndDiff) && do_compiled_outputs_differ(&fuzzer, compiled_outputs, &code_meta, &input) {
+				return Some(FindingCategory::RuntimeDiff);
+			}
+		}
+		_ => {}
+	}
+
+	return FindingCategory::from_result(&res);
+}
@@ -0,0 +1,212 @@
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::compilation_config::{CompilationConfig, TestCompilation, CompiledCodeOutput, CompilerIOThreadHandle};
+use crate::codegen_fuzzing::CaseSeed;
+use crate::saved_findings::{SavedFinding, FindingCategory};
+use crate::fuzzer_driver::{run_fuzzer, get_runtime_diff_checker, replay_case, FuzzRunOptions, FuzzRunSummary};
+
+use crate::x86_parse_spec::parse_intel_intrinsics_xml;
+use crate::x86_intrinsics::{X86SIMDIntrinsic, X86SIMDType};
+use crate::x86_codegen_ctx::X86SIMDCodegenCtx;
+use crate::x86_codegen_fuzzing::{X86CodegenFuzzer, X86CodegenFuzzerThreadInput, X86CodegenFuzzerCodeMetadata, X86CodeFuzzerInputValues, X86SIMDOutputValues};
+
+use crate::arm_intrinsics::{ARMSIMDType, ARMSIMDIntrinsic};
+use crate::arm_parse_spec::parse_arm_intrinsics_json;
+use crate::arm_codegen_ctx::ARMSIMDCodegenCtx;
+use crate::arm_codegen_fuzzing::{ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues};
+
+use crate::loop_codegen_fuzzing::{LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata, LoopFuzzerInputValues, LoopFuzzerOutputValues, LoopFuzzer};
+use crate::inline_asm_codegen_fuzzing::{AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, AsmFuzzerInputValues, AsmFuzzerOutputValues, AsmFuzzer};
+
+// The type-erased entry points for a fuzzer, so subcommands can pick one by name.
+// To add a new fuzzer, implement CodegenFuzzer for it and add an entry to FUZZER_KINDS
+pub struct FuzzerKind {
+	// Used for 'fuzz-[name]', '--fuzzer [name]', tmp filenames, and saved in each finding's _info.json
+	pub name : &'static str,
+	// Errors if the fuzzer couldn't be set up (e.g. missing spec file)
+	pub fuzz : fn(&CompilationConfig, u32, &FuzzRunOptions) -> Result<FuzzRunSummary, String>,
+	// Checks if compiled outputs still disagree on a saved runtime diff's input
+	pub get_runtime_diff_checker : fn(&CompilationConfig, &SavedFinding) -> Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool>,
+	// Regenerates a case from its seed and runs it
+	pub replay : fn(&CompilationConfig, CaseSeed, u32, &Vec<TestCompilation>, &Path, &CompilerIOThreadHandle) -> Result<Option<FindingCategory>, String>
+}
+
+pub const FUZZER_KINDS : [FuzzerKind; 4] = [
+	FuzzerKind { name: "x86", fuzz: fuzz_x86, get_runtime_diff_checker: get_x86_runtime_diff_checker, replay: replay_x86 },
+	FuzzerKind { name: "arm", fuzz: fuzz_arm, get_runtime_diff_checker: get_arm_runtime_diff_checker, replay: replay_arm },
+	FuzzerKind { name: "loop", fuzz: fuzz_loop, get_runtime_diff_checker: get_loop_runtime_diff_checker, replay: replay_loop },
+	FuzzerKind { name: "asm", fuzz: fuzz_asm, get_runtime_diff_checker: get_asm_runtime_diff_checker, replay: replay_asm }
+];
+
+pub fn find_fuzzer_kind(name : &str) -> Option<&'static FuzzerKind> {
+	return FUZZER_KINDS.iter().find(|fuzzer_kind| fuzzer_kind.name == name);
+}
+
+// e.g. "x86|arm|loop|asm" for usage messages
+pub fn get_fuzzer_kind_names(prefix : &str) -> String {
+	let names : Vec<String> = FUZZER_KINDS.iter().map(|fuzzer_kind| format!("{}{}", prefix, fuzzer_kind.name)).collect();
+	return names.join("|");
+}
+
+fn load_x86_type_to_intrinsics_map() -> Option<HashMap<X86SIMDType, Vec<X86SIMDIntrinsic>>> {
+	// Open the data xml file for the intrinsics
+	let intrinsics_docs_filename = "data-3-6-1.xml";
+	let contents = std::fs::read_to_string(intrinsics_docs_filename);
+	
+	if contents.is_err() {
+		print!("Could not open X86 intrinsics docs file '{}'. Maybe you need to download it?\n", intrinsics_docs_filename);
+		return None;
+	}
+	let contents = contents.unwrap();
+
+	let intrinsics_list = parse_intel_intrinsics_xml(&contents);
+
+	let mut type_to_intrinsics_map = HashMap::<X86SIMDType, Vec<X86SIMDIntrinsic>>::new();
+	
+	for intrinsic in intrinsics_list {
+		let intrinsics_for_type = type_to_intrinsics_map.entry(intrinsic.return_type)
+			.or_insert_with(|| Vec::<X86SIMDIntrinsic>::with_capacity(4));
+			
+		intrinsics_for_type.push(intrinsic);
+	}
+
+	return Some(type_to_intrinsics_map);
+}
+
+fn load_arm_type_to_intrinsics_map(compilation_config : &CompilationConfig) -> Option<HashMap<ARMSIMDType, Vec<ARMSIMDIntrinsic>>> {
+	let intrinsics_docs_filename = "arm_intrinsics.json";
+	let contents = std::fs::read_to_string(intrinsics_docs_filename);
+	
+	if contents.is_err() {
+		print!("Could not open ARM intrinsics docs file '{}'. Maybe you need to download it?\n", intrinsics_docs_filename);
+		return None;
+	}
+	
+	let contents = contents.unwrap();
+
+	let intrinsics_list = parse_arm_intrinsics_json(&contents, &compilation_config.mitigations);
+	
+	let mut type_to_intrinsics_map = HashMap::<ARMSIMDType, Vec<ARMSIMDIntrinsic>>::new();
+	
+	for intrinsic in intrinsics_list {
+		let intrinsics_for_type = type_to_intrinsics_map.entry(intrinsic.return_type)
+			.or_insert_with(|| Vec::<ARMSIMDIntrinsic>::with_capacity(4));
+			
+		intrinsics_for_type.push(intrinsic);
+	}
+
+	return Some(type_to_intrinsics_map);
+}
+
+pub fn get_exe_server_connect_addr(compilation_config : &CompilationConfig) -> String {
+	let mut exe_server_connect_addr : String = "".to_string();
+	if let Some(extra_config) = compilation_config.extra_config.as_object() {
+		if let Some(connect_addr) = extra_config["exe_server"].as_str() {
+			exe_server_connect_addr = connect_addr.to_string();
+		}
+	}
+
+	return exe_server_connect_addr;
+}
+
+fn fuzz_x86(compilation_config : &CompilationConfig, num_threads : u32, run_options : &FuzzRunOptions) -> Result<FuzzRunSummary, String> {
+	let type_to_intrinsics_map = load_x86_type_to_intrinsics_map().ok_or("could not load X86 intrinsics")?;
+
+	return Ok(run_fuzzer::<X86CodegenFuzzer, X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuzzerCodeMetadata, X86CodeFuzzerInputValues, X86SIMDOutputValues, _>(
+		"x86", compilation_config, num_threads, run_options, |thread_seed| X86CodegenFuzzerThreadInput {
+			thread_seed : thread_seed,
+			type_to_intrinsics_map : type_to_intrinsics_map.clone()
+		}));
+}
+
+fn get_x86_runtime_diff_checker(_compilation_config : &CompilationConfig, finding : &SavedFinding) -> Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool> {
+	// Executing code doesn't need the intrinsics, so don't bother loading them
+	let thread_input = X86CodegenFuzzerThreadInput {
+		thread_seed : 0,
+		type_to_intrinsics_map : HashMap::<X86SIMDType, Vec<X86SIMDIntrinsic>>::new()
+	};
+	return get_runtime_diff_checker::<X86CodegenFuzzer, X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuzzerCodeMetadata, X86CodeFuzzerInputValues, X86SIMDOutputValues>(thread_input, finding);
+}
+
+fn replay_x86(compilation_config : &CompilationConfig, case_seed : CaseSeed, input_index : u32, compilation_tests : &Vec<TestCompilation>,
+		replay_dir : &Path, io_thread_handle : &CompilerIOThreadHandle) -> Result<Option<FindingCategory>, String> {
+	let type_to_intrinsics_map = load_x86_type_to_intrinsics_map().ok_or("could not load X86 intrinsics")?;
+	let thread_input = X86CodegenFuzzerThreadInput {
+		thread_seed : case_seed.thread_seed,
+		type_to_intrinsics_map : type_to_intrinsics_map
+	};
+	return Ok(replay_case::<X86CodegenFuzzer, X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuzzerCodeMetadata, X86CodeFuzzerInputValues, X86SIMDOutputValues>(
+		thread_input, case_seed, input_index, compilation_tests, compilation_config.fuzz_mode, replay_dir, io_thread_handle));
+}
+
+fn fuzz_arm(compilation_config : &CompilationConfig, num_threads : u32, run_options : &FuzzRunOptions) -> Result<FuzzRunSummary, String> {
+	let type_to_intrinsics_map = load_arm_type_to_intrinsics_map(compilation_config).ok_or("could not load ARM intrinsics")?;
+
+	let exe_server_connect_addr = get_exe_server_connect_addr(compilation_config);
+	return Ok(run_fuzzer::<ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues, _>(
+		"arm", compilation_config, num_threads, run_options, |thread_seed| ARMCodegenFuzzerThreadInput {
+			thread_seed : thread_seed,
+			type_to_intrinsics_map : type_to_intrinsics_map.clone(),
+			mode: compilation_config.fuzz_mode,
+			connect_addr: exe_server_connect_addr.clone()
+		}));
+}
+
+fn get_arm_runtime_diff_checker(compilation_config : &CompilationConfig, finding : &SavedFinding) -> Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool> {
+	let thread_input = ARMCodegenFuzzerThreadInput {
+		thread_seed : 0,
+		type_to_intrinsics_map : HashMap::<ARMSIMDType, Vec<ARMSIMDIntrinsic>>::new(),
+		mode: compilation_config.fuzz_mode,
+		connect_addr: get_exe_server_connect_addr(compilation_config)
+	};
+	return get_runtime_diff_checker::<ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues>(thread_input, finding);
+}
+
+fn replay_arm(compilation_config : &CompilationConfig, case_seed : CaseSeed, input_index : u32, compilation_tests : &Vec<TestCompilation>,
+		replay_dir : &Path, io_thread_handle : &CompilerIOThreadHandle) -> Result<Option<FindingCategory>, String> {
+	let type_to_intrinsics_map = load_arm_type_to_intrinsics_map(compilation_config).ok_or("could not load ARM intrinsics")?;
+	let thread_input = ARMCodegenFuzzerThreadInput {
+		thread_seed : case_seed.thread_seed,
+		type_to_intrinsics_map : type_to_intrinsics_map,
+		mode: compilation_config.fuzz_mode,
+		connect_addr: get_exe_server_connect_addr(compilation_config)
+	};
+	return Ok(replay_case::<ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues>(
+		thread_input, case_seed, input_index, compilation_tests, compilation_config.fuzz_mode, replay_dir, io_thread_handle));
+}
+
+fn fuzz_loop(compilation_config : &CompilationConfig, num_threads : u32, run_options : &FuzzRunOptions) -> Result<FuzzRunSummary, String> {
+	return Ok(run_fuzzer::<LoopFuzzer, LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata, LoopFuzzerInputValues, LoopFuzzerOutputValues, _>(
+		"loop", compilation_config, num_threads, run_options, |thread_seed| LoopFuzzerThreadInput { thread_seed : thread_seed }));
+}
+
+fn get_loop_runtime_diff_checker(_compilation_config : &CompilationConfig, finding : &SavedFinding) -> Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool> {
+	let thread_input = LoopFuzzerThreadInput { thread_seed : 0 };
+	return get_runtime_diff_checker::<LoopFuzzer, LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata, LoopFuzzerInputValues, LoopFuzzerOutputValues>(thread_input, finding);
+}
+
+fn replay_loop(compilation_config : &CompilationConfig, case_seed : CaseSeed, input_index : u32, compilation_tests : &Vec<TestCompilation>,
+		replay_dir : &Path, io_thread_handle : &CompilerIOThreadHandle) -> Result<Option<FindingCategory>, String> {
+	let thread_input = LoopFuzzerThreadInput { thread_seed : case_seed.thread_seed };
+	return Ok(replay_case::<LoopFuzzer, LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata, LoopFuzzerInputValues, LoopFuzzerOutputValues>(
+		thread_input, case_seed, input_index, compilation_tests, compilation_config.fuzz_mode, replay_dir, io_thread_handle));
+}
+
+fn fuzz_asm(compilation_config : &CompilationConfig, num_threads : u32, run_options : &FuzzRunOptions) -> Result<FuzzRunSummary, String> {
+	return Ok(run_fuzzer::<AsmFuzzer, AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, AsmFuzzerInputValues, AsmFuzzerOutputValues, _>(
+		"asm", compilation_config, num_threads, run_options, |thread_seed| AsmFuzzerThreadInput { thread_seed : thread_seed }));
+}
+
+fn get_asm_runtime_diff_checker(_compilation_config : &CompilationConfig, finding : &SavedFinding) -> Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool> {
+	let thread_input = AsmFuzzerThreadInput { thread_seed : 0 };
+	return get_runtime_diff_checker::<AsmFuzzer, AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, AsmFuzzerInputValues, AsmFuzzerOutputValues>(thread_input, finding);
+}
+
+fn replay_asm(compilation_config : &CompilationConfig, case_seed : CaseSeed, input_index : u32, compilation_tests : &Vec<TestCompilation>,
+		replay_dir : &Path, io_thread_handle : &CompilerIOThreadHandle) -> Result<Option<FindingCategory>, String> {
+	let thread_input = AsmFuzzerThreadInput { thread_seed : case_seed.thread_seed };
+	return Ok(replay_case::<AsmFuzzer, AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, AsmFuzzerInputValues, AsmFuzzerOutputValues>(
+		thread_input, case_seed, input_index, compilation_tests, compilation_config.fuzz_mode, replay_dir, io_thread_handle));
+}
@@ -0,0 +1,262 @@
+// The third way of running compiled code: the compile links a whole executable, with EXE_HARNESS_MAIN tacked onto the end of the source,
+// and each run is a new process that reads the inputs on stdin (in the same format as the fuzzers' write_to_str) and prints the output bytes.
+// This is by far the slowest, but it doesn't care about the ABI or what the code links against, so it works for sanitizers, LTO,
+// compilers targeting something we can't load objects for, etc.
+
+use std::convert::TryInto;
+use std::fmt::Write as FmtWrite;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::compilation_config::{spawn_process_watchdog, wait_for_exit_without_reaping, get_status_code};
+use crate::exec_mem::ExternalCall;
+use crate::parse_exe::LoadError;
+
+// Gets appended to the generated code for executable compilations. The overloads pick how to read the inputs from do_stuff's type,
+// so the same thing works for every fuzzer: the loop/asm ones take (inputs, outputs, count), and the intrinsics ones take
+// the int/float/double arrays and return a vector. Output is one byte per line in hex, which is the commented-out main() in the X86 generator
+pub const EXE_HARNESS_MAIN : &str = r#"
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static inline void simd_fuzz_parse_val(const char* token, float* val) { *val = strtof(token, NULL); }
+static inline void simd_fuzz_parse_val(const char* token, double* val) { *val = strtod(token, NULL); }
+
+template<typename T>
+static void simd_fuzz_parse_val(const char* token, T* val) {
+	*val = (T)(token[0] == '-' ? (unsigned long long)strtoll(token, NULL, 10) : strtoull(token, NULL, 10));
+}
+
+// Exactly the size asked for, so ASan catches anything reading/writing past the end (same as the in-process AlignedSlice).
+// posix_memalign since aligned_alloc wants the size to be a multiple of the alignment
+static void* simd_fuzz_alloc(size_t num_bytes) {
+	void* ptr = NULL;
+	if (posix_memalign(&ptr, 64, num_bytes > 0 ? num_bytes : 1) != 0) { exit(3); }
+	memset(ptr, 0, num_bytes);
+	return ptr;
+}
+
+// The count, and then that many values
+template<typename T>
+static T* simd_fuzz_read_vals(int* num_vals) {
+	char token[128];
+	if (scanf("%127s", token) != 1) { exit(2); }
+	*num_vals = atoi(token);
+
+	T* vals = (T*)simd_fuzz_alloc(*num_vals * sizeof(T));
+	for (int i = 0; i < *num_vals; i++) {
+		if (scanf("%127s", token) != 1) { exit(2); }
+		simd_fuzz_parse_val(token, &vals[i]);
+	}
+
+	return vals;
+}
+
+static void simd_fuzz_print_bytes(const void* bytes, size_t num_bytes) {
+	for (size_t i = 0; i < num_bytes; i++) {
+		printf("%02X\n", ((const unsigned char*)bytes)[i]);
+	}
+}
+
+template<typename T>
+static int simd_fuzz_run(void (*func)(const T*, T*, int)) {
+	int num_vals = 0;
+	T* inputs = simd_fuzz_read_vals<T>(&num_vals);
+	T* outputs = (T*)simd_fuzz_alloc(num_vals * sizeof(T));
+
+	func(inputs, outputs, num_vals);
+	simd_fuzz_print_bytes(outputs, num_vals * sizeof(T));
+
+	// So LeakSanitizer stays quiet when it's a sanitizer build
+	free(inputs);
+	free(outputs);
+	return 0;
+}
+
+template<typename Ret>
+static int simd_fuzz_run(Ret (*func)(const int*, const float*, const double*)) {
+	int num_i_vals = 0, num_f_vals = 0, num_d_vals = 0;
+	int* i_vals = simd_fuzz_read_vals<int>(&num_i_vals);
+	float* f_vals = simd_fuzz_read_vals<float>(&num_f_vals);
+	double* d_vals = simd_fuzz_read_vals<double>(&num_d_vals);
+
+	Ret ret = func(i_vals, f_vals, d_vals);
+	simd_fuzz_print_bytes(&ret, sizeof(ret));
+
+	free(i_vals);
+	free(f_vals);
+	free(d_vals);
+	return 0;
+}
+
+// e.g. the trivial code that check-config compiles
+static inline int simd_fuzz_run(void (*func)()) {
+	func();
+	return 0;
+}
+
+int main() {
+	return simd_fuzz_run(do_stuff);
+}
+"#;
+
+// Every exe gets moved to its own name once it's built, since the next compile on this thread goes to the same path
+static NEXT_EXE_ID : AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug)]
+pub enum ExeRunError {
+	Timeout,
+	// Exit code (or 128 + signal) and stderr
+	Failed(i32, String),
+	BadOutput(String)
+}
+
+impl std::fmt::Display for ExeRunError {
+	fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			ExeRunError::Timeout => write!(f, "timed out"),
+			ExeRunError::Failed(status_code, stderr) => write!(f, "exited with {}: {}", status_code, stderr),
+			ExeRunError::BadOutput(err) => write!(f, "bad output: {}", err)
+		}
+	}
+}
+
+// Owns the exe file, and deletes it when dropped
+#[derive(Debug)]
+pub struct GeneratedExe {
+	path : PathBuf,
+	timeout_seconds : i32
+}
+
+impl GeneratedExe {
+	// Takes what the compiler wrote to exe_file_name
+	pub fn take(exe_file_name : &str, timeout_seconds : i32) -> Result<GeneratedExe, LoadError> {
+		let path = PathBuf::from(format!("{}_{}", exe_file_name, NEXT_EXE_ID.fetch_add(1, Ordering::SeqCst)));
+		std::fs::rename(exe_file_name, &path).map_err(|err| LoadError::BadObjectFile(format!("could not read '{}': {}", exe_file_name, err)))?;
+		return Ok(GeneratedExe { path: path, timeout_seconds: timeout_seconds });
+	}
+
+	// Runs the exe once with input_str on stdin, and gives back the bytes it printed
+	pub fn run(&self, input_str : &str) -> Result<Vec<u8>, ExeRunError> {
+		return self.run_with_stderr(input_str).map(|(output_bytes, _)| output_bytes);
+	}
+
+	// Same as run, but also gives back stderr when it exits cleanly, since the sanitizers can still have printed a report
+	pub fn run_with_stderr(&self, input_str : &str) -> Result<(Vec<u8>, String), ExeRunError> {
+		let mut command = Command::new(&self.path);
+		command.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped());
+
+		// Own process group, same as the compilers, so the watchdog can kill it
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::CommandExt;
+			command.process_group(0);
+		}
+
+		let mut child = command.spawn().expect("could not start generated exe");
+
+		let timed_out = Arc::new(AtomicBool::new(false));
+		let (done_sender, watchdog_join_handle) = spawn_process_watchdog(child.id(), Duration::from_secs(self.timeout_seconds as u64), timed_out.clone());
+
+		// The input's small, but the exe could still be blocked writing output while we're blocked writing input
+		let mut stdin = child.stdin.take().expect("Failed to open child stdin");
+		let input_bytes = input_str.as_bytes().to_vec();
+		let stdin_thread = std::thread::spawn(move || {
+			// If it dies before reading everything, that'll show up in the exit status
+			let _ = stdin.write_all(&input_bytes);
+		});
+
+		let mut stderr = child.stderr.take().expect("Failed to open child stderr");
+		let stderr_thread = std::thread::spawn(move || {
+			let mut stderr_bytes = Vec::<u8>::new();
+			stderr.read_to_end(&mut stderr_bytes).expect("Could not read child stderr");
+			stderr_bytes
+		});
+
+		let mut stdout_str = String::new();
+		child.stdout.take().expect("Failed to open child stdout").read_to_string(&mut stdout_str).expect("Could not read child stdout");
+		let stderr_bytes = stderr_thread.join().expect("could not join stderr reader thread");
+		stdin_thread.join().expect("could not join stdin writer thread");
+
+		wait_for_exit_without_reaping(child.id());
+		drop(done_sender);
+		watchdog_join_handle.join().expect("could not join process watchdog thread");
+		let status = child.wait().expect("Could not finish waiting for child");
+
+		if timed_out.load(Ordering::SeqCst) {
+			return Err(ExeRunError::Timeout);
+		}
+
+		if !status.success() {
+			return Err(ExeRunError::Failed(get_status_code(&status), String::from_utf8_lossy(&stderr_bytes).to_string()));
+		}
+
+		let output_bytes = parse_exe_output(&stdout_str)?;
+		return Ok((output_bytes, String::from_utf8_lossy(&stderr_bytes).to_string()));
+	}
+
+	// Turns the raw args back into what the harness reads, and panics if the run doesn't work out.
+	// That's the same as what'd happen if the code crashed in-process, we just get a better message
+	pub fn call(&self, call_kind : ExternalCall, args : &[&[u8]], output_len : usize) -> Vec<u8> {
+		let input_str = match call_kind {
+			ExternalCall::U32Io => write_vals_str(args[0].chunks_exact(4).map(|val| u32::from_ne_bytes(val.try_into().expect("")))),
+			ExternalCall::U64Io => write_vals_str(args[0].chunks_exact(8).map(|val| u64::from_ne_bytes(val.try_into().expect("")))),
+			ExternalCall::Args128i | ExternalCall::Args256i => {
+				let i_vals_str = write_vals_str(args[0].chunks_exact(4).map(|val| i32::from_ne_bytes(val.try_into().expect(""))));
+				let f_vals_str = write_vals_str(args[1].chunks_exact(4).map(|val| f32::from_ne_bytes(val.try_into().expect(""))));
+				let d_vals_str = write_vals_str(args[2].chunks_exact(8).map(|val| f64::from_ne_bytes(val.try_into().expect(""))));
+				format!("{}\n{}\n{}", i_vals_str, f_vals_str, d_vals_str)
+			}
+		};
+
+		match self.run(&input_str) {
+			Ok(output_bytes) if output_bytes.len() == output_len => {
+				return output_bytes;
+			}
+			Ok(output_bytes) => {
+				panic!("EXE RUN ERR: '{}' printed {} bytes, expected {}", self.path.display(), output_bytes.len(), output_len);
+			}
+			Err(err) => {
+				panic!("EXE RUN ERR: '{}' {}", self.path.display(), err);
+			}
+		}
+	}
+}
+
+impl Drop for GeneratedExe {
+	fn drop(&mut self) {
+		// Could already be gone if the run's tmp dir got cleaned up first
+		let _ = std::fs::remove_file(&self.path);
+	}
+}
+
+// Same as the fuzzers' write_to_str
+fn write_vals_str<T : std::fmt::Display>(vals : impl ExactSizeIterator<Item = T>) -> String {
+	let mut out_str = String::with_capacity(4096);
+	write!(out_str, "{}\n", vals.len()).expect("");
+	for val in vals {
+		write!(out_str, "{} ", val).expect("");
+	}
+
+	return out_str;
+}
+
+fn parse_exe_output(stdout_str : &str) -> Result<Vec<u8>, ExeRunError> {
+	return stdout_str.split_whitespace()
+		.map(|byte_str| u8::from_str_radix(byte_str, 16).map_err(|_| ExeRunError::BadOutput(format!("'{}' is not a hex byte", byte_str))))
+		.collect();
+}
+
+#[test]
+fn test_parse_exe_output() {
+	assert_eq!(parse_exe_output("00\nFF\n7A\n").expect(""), vec![0x00, 0xFF, 0x7A]);
+	assert_eq!(parse_exe_output("").expect(""), Vec::<u8>::new());
+	assert!(parse_exe_output("00\nhello\n").is_err());
+}
@@ -2,10 +2,11 @@
 use std::fmt::Write;
 use std::collections::BTreeSet;
 
-use crate::codegen_fuzzing::CodegenFuzzer;
+use crate::codegen_fuzzing::{CodegenFuzzer, read_serialized_values};
 use crate::rand::Rand;
 
 use crate::exec_mem::ExecPage;
+use crate::ub_check::{UBChecker, Interval, IntType, loop_count_interval};
 
 
 pub struct AsmFuzzerCodeMetadata {
@@ -17,7 +18,7 @@ pub struct AsmFuzzerThreadInput {
 }
 
 pub struct AsmFuzzer {
-	outer_rng : Rand
+	thread_seed : u64
 }
 
 
@@ -37,6 +38,11 @@ impl AsmFuzzerInputValues {
 
 		return out_str;
 	}
+
+	pub fn read_from_str(serial : &str) -> Self {
+		let mut lines = serial.lines();
+		Self { vals: read_serialized_values::<u64>(&mut lines) }
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -552,6 +558,55 @@ impl AsmCodegenCtx {
 		self.loop_stride
 	}
 	
+	// Same idea as the loop fuzzer's, but the c_ vars are unsigned long long, and we don't look inside the asm,
+	// so whatever an asm block outputs to could be anything afterwards
+	pub fn find_potential_ub(&self) -> Vec<String> {
+		let mut checker = UBChecker::default();
+		let count = loop_count_interval();
+
+		checker.check_strided_loop_header(self.loop_stride, count);
+		for ii in 0..self.loop_stride {
+			checker.check_strided_loop_access(self.loop_stride, count, "inputs", ii);
+			checker.check_strided_loop_access(self.loop_stride, count, "outputs", ii);
+		}
+
+		let mut c_vars = vec![Interval::full(IntType::U64) ; self.loop_stride as usize];
+		for (node_idx, node) in self.nodes.iter().enumerate() {
+			match node {
+				AsmCodegenNode::Cpp(cpp) => {
+					let what = format!("node {} (writing c_{})", node_idx, cpp.dest_var);
+					let result = match cpp.op {
+						AsmCodegenCppOp::Add => c_vars[cpp.inputs[0] as usize].add(c_vars[cpp.inputs[1] as usize]),
+						AsmCodegenCppOp::Mul => c_vars[cpp.inputs[0] as usize].mul(c_vars[cpp.inputs[1] as usize]),
+						AsmCodegenCppOp::Shift(shift_amount) => {
+							let shift_amount = Interval::exact(shift_amount as i128);
+							if checker.check_shift_amount(shift_amount, IntType::U64, &what) {
+								c_vars[cpp.inputs[0] as usize].shl(shift_amount)
+							}
+							else {
+								Interval::full(IntType::U64)
+							}
+						}
+					};
+					c_vars[cpp.dest_var as usize] = checker.apply_type(result, IntType::U64, &what);
+				}
+				AsmCodegenNode::Asm(asm) => {
+					for stmt in asm.stmts.iter() {
+						match stmt.values[stmt.out_val_idx] {
+							AsmCodegenAsmValue::CVar(var_idx) | AsmCodegenAsmValue::CVarPtr(var_idx) => {
+								c_vars[var_idx as usize] = Interval::full(IntType::U64);
+							}
+							AsmCodegenAsmValue::AsmReg(_) => {}
+						}
+					}
+				}
+				AsmCodegenNode::NoOp => {}
+			}
+		}
+
+		return checker.problems;
+	}
+
 	pub fn generate_cpp_code(&self) -> String {
 		let mut cpp_code = String::with_capacity(32*1024);
 		
@@ -595,13 +650,16 @@ impl CodegenFuzzer<AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, A
 	// Each of these will go on a thread, can contain inputs like
 	// a parsed spec data, seed, flags, config, etc.
 	fn new_fuzzer_state(input_data : Self::ThreadInput) -> Self {
-		Self { outer_rng: Rand::new(input_data.thread_seed) }
+		Self { thread_seed: input_data.thread_seed }
+	}
+
+	fn get_thread_seed(&self) -> u64 {
+		return self.thread_seed;
 	}
 
 	// This generates some context struct that's basically analagous to the AST
-	fn generate_ctx(&mut self) -> Self::CodegenCtx {
-		let next_seed = self.outer_rng.rand_u64();
-		return AsmCodegenCtx::new(next_seed);
+	fn generate_ctx(&mut self, ctx_seed : u64) -> Self::CodegenCtx {
+		return AsmCodegenCtx::new(ctx_seed);
 	}
 
 	// Turn the AST/context into actual CPP code, along with any metadata (i.e. number of values to pass for SIMD's iVals pointer, return value, etc.)
@@ -613,9 +671,8 @@ impl CodegenFuzzer<AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, A
 		return (code, AsmFuzzerCodeMetadata { loop_stride: ctx.get_loop_stride() })
 	}
 
-	fn generate_random_input(&self, _code_meta : &Self::CodeMeta) -> Self::FuzzerInput {
-		// Meh, kinda wish we didn't need to just make a new one since it requires mutability
-		let mut rng = Rand::default();
+	fn generate_random_input(&self, _code_meta : &Self::CodeMeta, input_seed : u64) -> Self::FuzzerInput {
+		let mut rng = Rand::new(input_seed);
 
 		let num_values = 32 + rng.rand() % 32;
 		let mut values = Vec::with_capacity(num_values as usize);
@@ -633,6 +690,10 @@ impl CodegenFuzzer<AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, A
 		return Self::FuzzerInput {vals: values };
 	}
 
+	fn find_potential_ub(&self, ctx : &Self::CodegenCtx) -> Vec<String> {
+		ctx.find_potential_ub()
+	}
+
 	// uhh.....idk
 	fn try_minimize<F: Fn(&Self, &Self::CodegenCtx) -> bool>(&self, ctx: Self::CodegenCtx, func: F) -> Option<Self::CodegenCtx> {
 		let mut best_ctx = ctx.clone();
@@ -724,16 +785,16 @@ impl CodegenFuzzer<AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, A
 		input.write_to_str()
 	}
 
-	fn read_input_from_string(&self, _serial : &str) -> Self::FuzzerInput {
-		todo!()
+	fn read_input_from_string(&self, serial : &str) -> Self::FuzzerInput {
+		AsmFuzzerInputValues::read_from_str(serial)
 	}
 
 	fn save_meta_to_string(&self, meta: &Self::CodeMeta) -> String {
 		format!("{}", meta.loop_stride)
 	}
 
-	fn read_meta_from_string(&self, _serial: &str) -> Self::CodeMeta {
-		todo!()
+	fn read_meta_from_string(&self, serial: &str) -> Self::CodeMeta {
+		AsmFuzzerCodeMetadata { loop_stride: serial.trim().parse::<u32>().unwrap() }
 	}
 
 	fn num_inputs_per_codegen(&self) -> u32 {
@@ -741,6 +802,17 @@ impl CodegenFuzzer<AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, A
 	}
 }
 
-
-
-
+#[test]
+fn test_find_potential_ub_rejects_bad_shift() {
+	let mut ctx = AsmCodegenCtx::new(1234);
+	let ok_shift = AsmCodegenNodeCpp { op: AsmCodegenCppOp::Shift(63), dest_var: 0, inputs: vec![1] };
+	ctx.nodes.push(AsmCodegenNode::Cpp(ok_shift));
+	assert_eq!(ctx.find_potential_ub(), Vec::<String>::new());
+
+	// Shifting a 64-bit value by 64 is UB, so the case should get thrown out
+	let bad_shift = AsmCodegenNodeCpp { op: AsmCodegenCppOp::Shift(64), dest_var: 0, inputs: vec![1] };
+	ctx.nodes.push(AsmCodegenNode::Cpp(bad_shift));
+	let problems = ctx.find_potential_ub();
+	assert_eq!(problems.len(), 1);
+	assert!(problems[0].contains("writing c_0"));
+}
@@ -1,10 +1,11 @@
 
 use std::fmt::Write;
 
-use crate::codegen_fuzzing::CodegenFuzzer;
+use crate::codegen_fuzzing::{CodegenFuzzer, read_serialized_values};
 use crate::rand::Rand;
 
 use crate::exec_mem::ExecPage;
+use crate::ub_check::{UBChecker, Interval, IntType, loop_count_interval};
 
 
 // TODO:
@@ -25,7 +26,7 @@ pub struct LoopFuzzerThreadInput {
 }
 
 pub struct LoopFuzzer {
-	outer_rng : Rand
+	thread_seed : u64
 }
 
 #[derive(Clone, Debug)]
@@ -44,6 +45,11 @@ impl LoopFuzzerInputValues {
 
 		return out_str;
 	}
+
+	pub fn read_from_str(serial : &str) -> Self {
+		let mut lines = serial.lines();
+		Self { vals: read_serialized_values::<u32>(&mut lines) }
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -81,6 +87,15 @@ impl LoopCodegenValue {
 			cpp_code.push_str(" & 0x0f)");
 		}
 	}
+
+	// Everything's an unsigned int, and inputs could be anything
+	fn get_interval(&self, registers : &[Interval]) -> Interval {
+		match self {
+			Self::Input(_) => Interval::full(IntType::U32),
+			Self::Register(reg) => registers[*reg],
+			Self::ConstantValue(imm_val) => Interval::exact(*imm_val as i128)
+		}
+	}
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -168,6 +183,11 @@ struct LoopCodegenNode {
 }
 
 impl LoopCodegenNode {
+	// Shift amounts get masked, since they're just random values
+	fn masks_src2(&self) -> bool {
+		return matches!(self.op, LoopCodegenOp::ShiftLeft | LoopCodegenOp::ShiftRight);
+	}
+
 	pub fn write_to_code(&self, cpp_code: &mut String) {
 		if matches!(self.op, LoopCodegenOp::NoOp) {
 			return;
@@ -187,8 +207,7 @@ impl LoopCodegenNode {
 		//println!("{:?} -> {}", self.op, op_symbol);
 		write!(cpp_code, " {} ", op_symbol).expect("");
 		
-		let do_and = match self.op { LoopCodegenOp::ShiftLeft | LoopCodegenOp::ShiftRight => true, _ => false };
-		self.src2.write_to_code(cpp_code, do_and);
+		self.src2.write_to_code(cpp_code, self.masks_src2());
 		
 		cpp_code.push_str(";\n");
 		
@@ -294,6 +313,55 @@ impl LoopCodegenCtx {
 		};
 	}
 	
+	// Runs through the same code generate_cpp_code writes, keeping track of what each register could hold (see ub_check)
+	pub fn find_potential_ub(&self) -> Vec<String> {
+		let mut checker = UBChecker::default();
+		let stride = NUM_REGISTERS as u32;
+		let count = loop_count_interval();
+
+		checker.check_strided_loop_header(stride, count);
+		for ii in 0..NUM_INPUTS {
+			checker.check_strided_loop_access(stride, count, "inputs", ii as u32);
+		}
+		// outputs is an int, but adding an unsigned int to it is just a conversion, not signed overflow
+		for ii in 0..NUM_REGISTERS {
+			checker.check_strided_loop_access(stride, count, "outputs", (ii % NUM_INPUTS) as u32);
+		}
+
+		let mut registers = vec![Interval::full(IntType::U32) ; NUM_REGISTERS];
+		for (node_idx, node) in self.nodes.iter().enumerate() {
+			if matches!(node.op, LoopCodegenOp::NoOp) {
+				continue;
+			}
+
+			let what = format!("node {} (writing r{})", node_idx, node.dest_register);
+			let src1 = node.src1.get_interval(&registers);
+			let mut src2 = node.src2.get_interval(&registers);
+			if node.masks_src2() {
+				src2 = src2.bit_and(Interval::exact(0x0f));
+			}
+
+			let result = match node.op {
+				LoopCodegenOp::NoOp => panic!("NoOp should have been skipped"),
+				LoopCodegenOp::Add => src1.add(src2),
+				LoopCodegenOp::Sub => src1.sub(src2),
+				LoopCodegenOp::Mul => src1.mul(src2),
+				LoopCodegenOp::BitAnd => src1.bit_and(src2),
+				LoopCodegenOp::BitOr => src1.bit_or(src2),
+				LoopCodegenOp::BitXor => src1.bit_xor(src2),
+				LoopCodegenOp::ShiftLeft | LoopCodegenOp::ShiftRight if !checker.check_shift_amount(src2, IntType::U32, &what) => Interval::full(IntType::U32),
+				LoopCodegenOp::ShiftLeft => src1.shl(src2),
+				LoopCodegenOp::ShiftRight => src1.shr(src2)
+			};
+			let result = checker.apply_type(result, IntType::U32, &what);
+
+			// If it's conditional, it could also still be whatever it was before
+			registers[node.dest_register] = if node.cond.is_some() { registers[node.dest_register].join(result) } else { result };
+		}
+
+		return checker.problems;
+	}
+
 	pub fn generate_cpp_code(&self) -> String {
 		let mut cpp_code = String::with_capacity(32*1024);
 		
@@ -331,12 +399,16 @@ impl CodegenFuzzer<LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata
 	// Each of these will go on a thread, can contain inputs like
 	// a parsed spec data, seed, flags, config, etc.
 	fn new_fuzzer_state(input_data : Self::ThreadInput) -> Self {
-		Self { outer_rng: Rand::new(input_data.thread_seed) }
+		Self { thread_seed: input_data.thread_seed }
+	}
+
+	fn get_thread_seed(&self) -> u64 {
+		return self.thread_seed;
 	}
 
 	// This generates some context struct that's basically analagous to the AST
-	fn generate_ctx(&mut self) -> Self::CodegenCtx {
-		Self::CodegenCtx::new(self.outer_rng.rand_u64())
+	fn generate_ctx(&mut self, ctx_seed : u64) -> Self::CodegenCtx {
+		Self::CodegenCtx::new(ctx_seed)
 	}
 
 	// Turn the AST/context into actual CPP code, along with any metadata (i.e. number of values to pass for SIMD's iVals pointer, return value, etc.)
@@ -350,9 +422,8 @@ impl CodegenFuzzer<LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata
 		(cpp_code, LoopFuzzerCodeMetadata { loop_inner_stride: 4 })
 	}
 
-	fn generate_random_input(&self, _code_meta : &Self::CodeMeta) -> Self::FuzzerInput {
-		// Meh, kinda wish we didn't need to just make a new one since it requires mutability
-		let mut rng = Rand::default();
+	fn generate_random_input(&self, _code_meta : &Self::CodeMeta, input_seed : u64) -> Self::FuzzerInput {
+		let mut rng = Rand::new(input_seed);
 
 		let num_values = 32 + rng.rand() % 16;
 		let mut values = Vec::with_capacity(num_values as usize);
@@ -370,6 +441,10 @@ impl CodegenFuzzer<LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata
 		return LoopFuzzerInputValues {vals: values };
 	}
 
+	fn find_potential_ub(&self, ctx : &Self::CodegenCtx) -> Vec<String> {
+		ctx.find_potential_ub()
+	}
+
 	// uhh.....idk
 	fn try_minimize<F: Fn(&Self, &Self::CodegenCtx) -> bool>(&self, ctx: Self::CodegenCtx, func: F) -> Option<Self::CodegenCtx> {
 		let mut best_ctx = ctx.clone();
@@ -428,16 +503,16 @@ impl CodegenFuzzer<LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata
 		input.write_to_str()
 	}
 
-	fn read_input_from_string(&self, _serial : &str) -> Self::FuzzerInput {
-		todo!()
+	fn read_input_from_string(&self, serial : &str) -> Self::FuzzerInput {
+		LoopFuzzerInputValues::read_from_str(serial)
 	}
 
-	fn save_meta_to_string(&self, _meta: &Self::CodeMeta) -> String {
-		todo!()
+	fn save_meta_to_string(&self, meta: &Self::CodeMeta) -> String {
+		format!("{}", meta.loop_inner_stride)
 	}
 
-	fn read_meta_from_string(&self, _serial: &str) -> Self::CodeMeta {
-		todo!()
+	fn read_meta_from_string(&self, serial: &str) -> Self::CodeMeta {
+		LoopFuzzerCodeMetadata { loop_inner_stride: serial.trim().parse::<usize>().unwrap() }
 	}
 
 	fn num_inputs_per_codegen(&self) -> u32 {
@@ -10,11 +10,7 @@
 #![feature(associated_type_defaults)]
 
 use std::collections::HashMap;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::{Duration, Instant, SystemTime};
-
-use sha2::{Sha256, Digest};
+use std::time::Duration;
 
 mod rand;
 
@@ -22,389 +18,185 @@ mod aligned_slice;
 use aligned_slice::AlignedSlice;
 
 mod compilation_config;
-use compilation_config::{test_generated_code_compilation, parse_compiler_config};
-use compilation_config::{TestCompilation, GenCodeResult, GenCodeFuzzMode, CompilerIOThread, CompilerIOThreadHandle};
+use compilation_config::{test_generated_code_compilation, expand_placeholders, RunTmpDir};
+use compilation_config::{CompilationConfig, GenCodeResult, CompiledCodeOutput, CompilerIOThread};
+
+mod config_loader;
+use config_loader::{load_compiler_config_file, find_compiler_exe, ConfigReport};
+
+mod saved_findings;
+use saved_findings::{SavedFinding, FindingCategory, FUZZ_ISSUES_DIR, list_saved_findings};
+
+mod compiler_bisect;
+use compiler_bisect::{bisect_finding, get_bisect_compiler_list};
+
+mod regression_suite;
+use regression_suite::{run_regression_suite, regression_results_to_json, regression_results_to_junit, RegressionStatus};
+
+mod fuzz_stats;
+
+mod fuzzer_driver;
+use fuzzer_driver::FuzzRunOptions;
+
+mod fuzzer_kinds;
+use fuzzer_kinds::{find_fuzzer_kind, get_fuzzer_kind_names};
 
 mod x86_parse_spec;
-use x86_parse_spec::parse_intel_intrinsics_xml;
 
 mod x86_intrinsics;
-use x86_intrinsics::{X86SIMDIntrinsic, X86SIMDType};
 
 mod parse_exe;
 
+mod obj_linker;
+
+mod arm_relocs;
+
 mod x86_codegen_ctx;
-use x86_codegen_ctx::{X86SIMDCodegenCtx};
 
 mod exec_mem;
 
+mod page_pool;
+
+mod shared_lib_exec;
+use shared_lib_exec::{run_shared_lib_worker, SHARED_LIB_WORKER_METHOD};
+
+mod generated_exe;
+
+mod sanitizer_check;
+mod ub_check;
+use sanitizer_check::{expand_sanitizer_placeholders, reclassify_runtime_diff};
+
 mod codegen_fuzzing;
-use codegen_fuzzing::CodegenFuzzer;
+use codegen_fuzzing::{CodegenFuzzer, CaseSeed};
 
 mod x86_codegen_fuzzing;
-use x86_codegen_fuzzing::{X86CodegenFuzzer, X86CodegenFuzzerThreadInput, X86CodegenFuzzerCodeMetadata, X86CodeFuzzerInputValues, X86SIMDOutputValues };
 
 mod arm_intrinsics;
 use arm_intrinsics::{ARMSIMDType, ARMSIMDIntrinsic};
 
 mod arm_parse_spec;
-use arm_parse_spec::parse_arm_intrinsics_json;
 
 mod arm_codegen_ctx;
-use arm_codegen_ctx::ARMSIMDCodegenCtx;
 
 mod arm_codegen_fuzzing;
-use arm_codegen_fuzzing::{ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues};
+use arm_codegen_fuzzing::{ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMSIMDOutputValues};
 
 mod code_exe_server_conn;
+use code_exe_server_conn::run_code_exe_server;
 
 mod loop_codegen_fuzzing;
-use loop_codegen_fuzzing::{LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata, LoopFuzzerInputValues, LoopFuzzerOutputValues, LoopFuzzer};
 
 mod inline_asm_codegen_fuzzing;
-use inline_asm_codegen_fuzzing::{AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, AsmFuzzerInputValues, AsmFuzzerOutputValues, AsmFuzzer};
 
-fn get_hex_hash_of_bytes(input : &[u8]) -> String {
-	let mut hasher = Sha256::new();
-	hasher.update(input);
-	let digest = hasher.finalize();
-	hex::encode(digest)
+fn load_compilation_config(config_filename : &str) -> Option<CompilationConfig> {
+	let (compilation_config, report) = load_compiler_config_file(config_filename);
+	print_config_report(config_filename, &report);
+	return compilation_config;
 }
 
-//use std::arch::x86_64::{_rdtsc};
+fn print_config_report(config_filename : &str, report : &ConfigReport) {
+	for warning in report.warnings.iter() {
+		print!("{}: warning: {}\n", config_filename, warning);
+	}
 
-fn get_timestamp_for_seed() -> u64 {
-	SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
+	for error in report.errors.iter() {
+		print!("{}: error: {}\n", config_filename, error);
+	}
 }
 
-fn save_out_failure_info(orig_code : &str, min_code : &str, result : &GenCodeResult, metadata : &str) {
-	match result {
-		GenCodeResult::CompilerTimeout => {
-			let min_hex_hash_full = get_hex_hash_of_bytes(min_code.as_bytes());
-			let min_hex_hash = &min_hex_hash_full[0..10];
-			let orig_code_filename = format!("fuzz_issues/compiler_timeouts/{}_orig.cpp", min_hex_hash);
-			let min_code_filename = format!("fuzz_issues/compiler_timeouts/{}_min.cpp", min_hex_hash);
-			
-			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
-			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
-		}
-		GenCodeResult::CompilerFailure(_,_,_) => {
-			let min_hex_hash_full = get_hex_hash_of_bytes(min_code.as_bytes());
-			let min_hex_hash = &min_hex_hash_full[0..10];
-			let orig_code_filename = format!("fuzz_issues/compiler_fails/{}_orig.cpp", min_hex_hash);
-			let min_code_filename = format!("fuzz_issues/compiler_fails/{}_min.cpp", min_hex_hash);
-			
-			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
-			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
-		}
-		GenCodeResult::RuntimeDiff(input) => {
-			let min_hex_hash_full = get_hex_hash_of_bytes(min_code.as_bytes());
-			let min_hex_hash = &min_hex_hash_full[0..10];
-			let orig_code_filename = format!("fuzz_issues/runtime_diffs/{}_orig.cpp", min_hex_hash);
-			let min_code_filename = format!("fuzz_issues/runtime_diffs/{}_min.cpp", min_hex_hash);
-			let input_filename = format!("fuzz_issues/runtime_diffs/{}_input.input", min_hex_hash);
-			let min_meta_filename = format!("fuzz_issues/runtime_diffs/{}_min_meta.meta", min_hex_hash);
-			
-			std::fs::write(orig_code_filename, orig_code).expect("couldn't write to file?");
-			std::fs::write(min_code_filename, min_code).expect("couldn't write to file?");
-			std::fs::write(input_filename, input).expect("couldn't write to file?");
-			std::fs::write(min_meta_filename, metadata).expect("couldn't write to file?");
+// Goes through the config and makes sure every compiler is there and can actually compile something, before we spend a night fuzzing with it
+fn check_config(config_filename : &str) {
+	let compilation_config = match load_compilation_config(config_filename) {
+		Some(compilation_config) => compilation_config,
+		None => {
+			std::process::exit(1);
 		}
-		_ => panic!("uuhhhh....implement this")
-	}
-}
+	};
 
-fn fuzz_simd_codegen_loop<FuzzType,ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>(
-		input : ThreadInput, compilation_tests : &Vec<TestCompilation>, fuzz_mode : GenCodeFuzzMode,
-		total_num_cases_done : Arc<AtomicUsize>, total_bugs_found : Arc<AtomicUsize>, num_bytes_fuzzed : Arc<AtomicUsize>,
-		io_thread_handle : CompilerIOThreadHandle
-	)
-	where FuzzType : CodegenFuzzer<ThreadInput,CodegenCtx,CodeMeta,FuzzerInput,FuzzerOutput>, FuzzerOutput: Clone + std::fmt::Debug, CodegenCtx: Clone {
-	
-	let mut fuzzer = FuzzType::new_fuzzer_state(input);
-	
-	let num_inputs_per_codegen = fuzzer.num_inputs_per_codegen();
-	
-	loop {
-		let codegen_ctx = fuzzer.generate_ctx();
-		
-		let (cpp_code, code_meta) = fuzzer.generate_cpp_code(&codegen_ctx);
+	const TRIVIAL_CODE : &str = "extern \"C\" void do_stuff() { }\n";
 
-		//println!("----------CODE-------------");
-		//println!("{}", cpp_code);
-		//println!("---------------------------");
+	let run_tmp_dir = RunTmpDir::create("check_config");
+	let mut compilation_tests = expand_placeholders(&compilation_config.compilations, &run_tmp_dir.placeholder_values(None));
+	let mut compile_labels : Vec<String> = (0..compilation_tests.len()).map(|compile_idx| compile_idx.to_string()).collect();
+	if let Some(sanitizer_compilation) = &compilation_config.sanitizer_compilation {
+		compilation_tests.push(expand_sanitizer_placeholders(&compilation_config.compilations, sanitizer_compilation, &run_tmp_dir.placeholder_values(None)));
+		compile_labels.push("sanitizer".to_string());
+	}
 
-		let res = test_generated_code_compilation(&cpp_code, compilation_tests, &io_thread_handle);
+	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread();
 
-		// TODO: UTF-8, bytes not necessarily same as chars, idk what rust gives but we only do ascii in this house so w/e
-		let num_cpp_bytes = cpp_code.len();
+	let mut num_problems = 0;
+	for (compile_label, compilation_test) in compile_labels.iter().zip(compilation_tests.iter()) {
+		print!("[{}] {} {}\n", compile_label, compilation_test.compiler_exe, compilation_test.compiler_args.join(" "));
 
-		match res {
-			GenCodeResult::CompilerTimeout => {
-				let minim_checker = |this_fuzzer : &FuzzType, ctx: &CodegenCtx| {
-					let (minim_cpp_code, _) = this_fuzzer.generate_cpp_code(ctx);
-					let minim_res = test_generated_code_compilation(&minim_cpp_code, compilation_tests, &io_thread_handle);
-					return matches!(minim_res, GenCodeResult::CompilerTimeout);
-				};
-
-				if let Some(min_ctx) = fuzzer.try_minimize(codegen_ctx, minim_checker) {
-					let (min_cpp_code,min_code_meta) = fuzzer.generate_cpp_code(&min_ctx);
-					let min_code_meta = fuzzer.save_meta_to_string(&min_code_meta);
-					save_out_failure_info(&cpp_code, &min_cpp_code, &res, &min_code_meta);
-				}
-				else {
-					println!("Could not minimize for whatever reason");
-					let code_meta = fuzzer.save_meta_to_string(&code_meta);
-					save_out_failure_info(&cpp_code, &cpp_code, &res, &code_meta);
-				}
+		if find_compiler_exe(&compilation_test.compiler_exe).is_none() {
+			print!("    error: could not find compiler '{}'\n", compilation_test.compiler_exe);
+			num_problems += 1;
+			continue;
+		}
 
-				total_bugs_found.fetch_add(1, Ordering::SeqCst);
+		let start_time = std::time::Instant::now();
+		match test_generated_code_compilation(TRIVIAL_CODE, &vec![compilation_test.clone()], &io_thread_handle) {
+			GenCodeResult::Success(_) => {
+				print!("    ok ({:.0} ms)\n", start_time.elapsed().as_secs_f64() * 1000.0);
 			}
-			GenCodeResult::CompilerFailure(_err_code,_,_) => {
-				let minim_checker = |this_fuzzer : &FuzzType, ctx: &CodegenCtx| {
-					let (minim_cpp_code, _) = this_fuzzer.generate_cpp_code(ctx);
-					let minim_res = test_generated_code_compilation(&minim_cpp_code, compilation_tests, &io_thread_handle);
-					return matches!(minim_res, GenCodeResult::CompilerFailure(_,_,_));
-				};
-				
-				if let Some(min_ctx) = fuzzer.try_minimize(codegen_ctx, minim_checker) {
-					let (min_cpp_code,min_code_meta) = fuzzer.generate_cpp_code(&min_ctx);
-					let min_code_meta = fuzzer.save_meta_to_string(&min_code_meta);
-					save_out_failure_info(&cpp_code, &min_cpp_code, &res, &min_code_meta);
-				}
-				else {
-					println!("Could not minimize for whatever reason");
-					let code_meta = fuzzer.save_meta_to_string(&code_meta);
-					save_out_failure_info(&cpp_code, &cpp_code, &res, &code_meta);
-				}
-				
-				total_bugs_found.fetch_add(1, Ordering::SeqCst);
+			GenCodeResult::CompilerFailure(err_code, _, stderr) => {
+				print!("    error: failed to compile a trivial file (exit code {})\n{}\n", err_code, stderr);
+				num_problems += 1;
 			}
-			GenCodeResult::Success(ref compiled_outputs) => {
-				if matches!(fuzz_mode, GenCodeFuzzMode::CrashAndDiff) {
-					let mut bad_input : Option<FuzzerInput> = None;
-					for _ in 0..num_inputs_per_codegen {
-						let input = fuzzer.generate_random_input(&code_meta);
-
-						// TODO: Code dup
-						let mut all_outputs_same = true;
-						let mut first_output : Option<FuzzerOutput> = None;
-						for compiled_out in compiled_outputs.iter(){
-							let output = fuzzer.execute(&compiled_out.code_page, &code_meta, &input);
-							if let Some(ref first_output) = first_output {
-								if !fuzzer.are_outputs_the_same(first_output, &output) {
-									//println!("OUTPUT DIFF:");
-									//println!("O1: {:?}", first_output);
-									//println!("O2: {:?}", output);
-									all_outputs_same = false;
-									break;
-								}
-							}
-							else {
-								first_output = Some(output);
-							}
-						}
-						
-						if !all_outputs_same {
-							bad_input = Some(input);
-							break;
-						}
-					}
-					
-					if let Some(bad_input) = bad_input {
-						let minim_checker = |this_fuzzer : &FuzzType, ctx: &CodegenCtx| {
-							let (minim_cpp_code, minim_code_meta) = this_fuzzer.generate_cpp_code(ctx);
-							let minim_res = test_generated_code_compilation(&minim_cpp_code, compilation_tests, &io_thread_handle);
-							if let GenCodeResult::Success(minim_compiled_outputs) = minim_res {
-
-								// TODO: Code dup with above
-								let mut all_outputs_same = true;
-								let mut first_output : Option<FuzzerOutput> = None;
-								for compiled_out in minim_compiled_outputs.iter(){
-									let output = fuzzer.execute(&compiled_out.code_page, &minim_code_meta, &bad_input);
-									if let Some(ref first_output) = first_output {
-										if !fuzzer.are_outputs_the_same(first_output, &output) {
-											//println!("OUTPUT DIFF:");
-											//println!("O1: {:?}", first_output);
-											//println!("O2: {:?}", output);
-											all_outputs_same = false;
-											break;
-										}
-									}
-									else {
-										first_output = Some(output);
-									}
-								}
-								
-								return !all_outputs_same;
-							}
-							
-							return false;
-						};
-						
-						let input_str = fuzzer.save_input_to_string(&bad_input);
-						if let Some(min_ctx) = fuzzer.try_minimize(codegen_ctx, minim_checker) {
-							let (min_cpp_code, min_meta) = fuzzer.generate_cpp_code(&min_ctx);
-							let min_meta = fuzzer.save_meta_to_string(&min_meta);
-							save_out_failure_info(&cpp_code, &min_cpp_code, &GenCodeResult::RuntimeDiff(input_str), &min_meta);
-						}
-						else {
-							println!("Could not minimize for whatever reason");
-							let code_meta = fuzzer.save_meta_to_string(&code_meta);
-							save_out_failure_info(&cpp_code, &cpp_code, &GenCodeResult::RuntimeDiff(input_str), &code_meta);
-						}
-						
-						total_bugs_found.fetch_add(1, Ordering::SeqCst);
-					}
-				}
+			GenCodeResult::CompilerTimeout => {
+				print!("    error: timed out compiling a trivial file after {} seconds\n", compilation_test.timeout_seconds);
+				num_problems += 1;
+			}
+			GenCodeResult::ObjectLoadFailure(load_error, _) => {
+				print!("    error: compiled a trivial file, but could not load it: {}\n", load_error);
+				num_problems += 1;
+			}
+			_ => {
+				print!("    error: unexpected result compiling a trivial file\n");
+				num_problems += 1;
 			}
-			_ => { panic!("bad possible return type from compilation") }
-		};
-
-		total_num_cases_done.fetch_add(1, Ordering::SeqCst);
-		num_bytes_fuzzed.fetch_add(num_cpp_bytes, Ordering::SeqCst);
-	}
-}
-
-fn fuzz_x86_simd_codegen(config_filename : &str, num_threads : u32) {
-	// Open the data xml file for the intrinsics
-	let intrinsics_docs_filename = "data-3-6-1.xml";
-	let contents = std::fs::read_to_string(intrinsics_docs_filename);
-	
-	if contents.is_err() {
-		print!("Could not open X86 intrinsics docs file '{}'. Maybe you need to download it?\n", intrinsics_docs_filename);
-		return;
-	}
-	let contents = contents.unwrap();
-	
-	let config_contents = std::fs::read_to_string(config_filename);
-	if config_contents.is_err() {
-		print!("Could not open config file '{}'\n", config_filename);
-		return;
-	}
-	let config_contents = config_contents.unwrap();
-	
-	let intrinsics_list = parse_intel_intrinsics_xml(&contents);
-
-	let type_to_intrinsics_map = {
-		let mut type_to_intrinsics_map = HashMap::<X86SIMDType, Vec<X86SIMDIntrinsic>>::new();
-		
-		for intrinsic in intrinsics_list {
-			let intrinsics_for_type = type_to_intrinsics_map.entry(intrinsic.return_type)
-				.or_insert_with(|| Vec::<X86SIMDIntrinsic>::with_capacity(4));
-				
-			intrinsics_for_type.push(intrinsic);
 		}
+	}
 
-		type_to_intrinsics_map
-	};
-	
-	let compilation_config = parse_compiler_config(&config_contents);
-	let compilation_tests = compilation_config.compilations;
-	let fuzz_mode = compilation_config.fuzz_mode;
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("could not join compiler IO thread");
+	drop(run_tmp_dir);
 
-	let mut thread_handles = Vec::<std::thread::JoinHandle<_>>::new();
-	print!("Launching fuzzer with {} threads\n", num_threads);
+	if num_problems > 0 {
+		print!("{} of {} compilations have problems\n", num_problems, compilation_tests.len());
+		std::process::exit(1);
+	}
 
-	let num_cases_state = Arc::new(AtomicUsize::new(0));
-	let num_bugs_found = Arc::new(AtomicUsize::new(0));
-	let num_bytes_fuzzed = Arc::new(AtomicUsize::new(0));
-	
-	// This should ensure subsequent runs don't re-use the same seeds for everything
-	let initial_time = get_timestamp_for_seed();//unsafe { _rdtsc() };
-	
-	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread();
-	
-	for thread_id in 0..num_threads {
-		let mut compilation_tests = compilation_tests.clone();
-		
-		for compilation_test in compilation_tests.iter_mut() {
-			if compilation_test.use_tmp_file {
-				let tmp_filename = format!("tmp/x86_tmp_thr{}.o", thread_id);
-				for arg in compilation_test.compiler_args.iter_mut() {
-					*arg = arg.replace("^TMP_FILENAME^", &tmp_filename);
-				}
-				compilation_test.tmp_file_name = Some(tmp_filename);
-			}
-		}
+	print!("Config looks good\n");
+}
 
-		let own_type_to_intrinsics_map = type_to_intrinsics_map.clone();
-		let fuzz_mode = fuzz_mode.clone();
-		let num_cases_state = num_cases_state.clone();
-		let num_bugs_found = num_bugs_found.clone();
-		let num_bytes_fuzzed = num_bytes_fuzzed.clone();
-		
-		// Some prime numbers beause they're better, or so I hear
-		let initial_seed = ((thread_id as u64) + 937) * 241 + initial_time;
-		
-		let thread_input = X86CodegenFuzzerThreadInput {
-			thread_seed : initial_seed,
-			type_to_intrinsics_map : own_type_to_intrinsics_map
-		};
-		
-		let io_thread_handle = io_thread_handle.clone();
-		
-		let thread_handle = std::thread::spawn(move || {
-			fuzz_simd_codegen_loop::<X86CodegenFuzzer, X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuzzerCodeMetadata, X86CodeFuzzerInputValues, X86SIMDOutputValues>(
-				thread_input, &compilation_tests, fuzz_mode, num_cases_state, num_bugs_found, num_bytes_fuzzed, io_thread_handle);
-		});
-		thread_handles.push(thread_handle);
-	}
-	
-	print!("Done launching\n");
-	
-	let start_time = Instant::now();
-	loop {
-		std::thread::sleep(Duration::from_secs(1));
-		let time_so_far = Instant::now().duration_since(start_time);
-		let seconds_so_far = time_so_far.as_secs_f32();
-		let num_cases_so_far = num_cases_state.load(Ordering::SeqCst);
-		let avg_cases_per_second = num_cases_so_far as f32 / seconds_so_far;
-		let num_bugs_so_far = num_bugs_found.load(Ordering::SeqCst);
-
-		let num_bytes_so_far = num_bytes_fuzzed.load(Ordering::SeqCst);
-		
-		const BYTES_PER_KB : f64 = 1024.0;
-		const BYTES_PER_GB : f64 = 1024.0 * 1024.0 * 1024.0;
-		let avg_kb_per_sec = (num_bytes_so_far as f64) / (seconds_so_far as f64) / BYTES_PER_KB;
-		let num_gb_so_far = (num_bytes_so_far as f64) / BYTES_PER_GB;
-
-		print!("X86 | {:10.1} sec uptime | {:10} cases | {:10.2} cps | {:5} bugs | {:8.3} KB/s code fuzzed | {:8.4} GB code total\n",
-			seconds_so_far, num_cases_so_far, avg_cases_per_second, num_bugs_so_far, avg_kb_per_sec, num_gb_so_far);
-	}
+// Runtime diffs need the fuzzer that found them to execute the code and compare outputs, other findings don't
+fn get_runtime_diff_checker_for_fuzzer(fuzzer_name : &str, finding : &SavedFinding, compilation_config : &CompilationConfig)
+		-> Option<Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool>> {
+	let fuzzer_kind = find_fuzzer_kind(fuzzer_name)?;
+	return Some((fuzzer_kind.get_runtime_diff_checker)(compilation_config, finding));
 }
 
 fn repro_arm_simd_codegen(config_filename : &str, repro_filename : &str, meta_filename : &str, input_filename : &str) {
 	println!("Reproing ARM simd on file {}", repro_filename);
 	
-	let config_contents = std::fs::read_to_string(config_filename);
-	if config_contents.is_err() {
-		print!("Could not open config file '{}'\n", config_filename);
-		return;
-	}
-	let config_contents = config_contents.unwrap();
+	let compilation_config = match load_compilation_config(config_filename) {
+		Some(compilation_config) => compilation_config,
+		None => return
+	};
 	
 	let repro_code = std::fs::read_to_string(repro_filename).expect("could not read repro code file");
 	let serial_meta = std::fs::read_to_string(meta_filename).expect("could not read code meta file");
 	let input_txt = std::fs::read_to_string(input_filename).expect("could not read code meta file");
-
-	let compilation_config = parse_compiler_config(&config_contents);
 	
-	let mut compilation_tests = compilation_config.compilations;
+	let run_tmp_dir = RunTmpDir::create("repro");
+	let compilation_tests = expand_placeholders(&compilation_config.compilations, &run_tmp_dir.placeholder_values(None));
 	let fuzz_mode = compilation_config.fuzz_mode;
 
 	println!("~~~~~~~~~");
 	println!("{:?}", compilation_tests);
 	println!("~~~~~~~~~");
-	for compilation_test in compilation_tests.iter_mut() {
-		if compilation_test.use_tmp_file {
-			let tmp_filename = "tmp/x86_tmp_thr_repro.o";
-			for arg in compilation_test.compiler_args.iter_mut() {
-				*arg = arg.replace("^TMP_FILENAME^", tmp_filename);
-			}
-			compilation_test.tmp_file_name = Some(tmp_filename.to_string());
-		}
-	}
 
 	let mut exe_server_connect_addr : String = "".to_string();
 	if let Some(extra_config) = compilation_config.extra_config.as_object() {
@@ -426,7 +218,7 @@ fn repro_arm_simd_codegen(config_filename : &str, repro_filename : &str, meta_fi
 	
 	let res = test_generated_code_compilation(&repro_code, &compilation_tests, &io_thread_handle);
 
-	match res {
+	let exit_code = match res {
 		GenCodeResult::Success(ref compiled_outputs) => {
 			let code_meta = fuzzer.read_meta_from_string(&serial_meta);
 			let input = fuzzer.read_input_from_string(&input_txt);
@@ -458,266 +250,348 @@ fn repro_arm_simd_codegen(config_filename : &str, repro_filename : &str, meta_fi
 			
 			if all_outputs_same {
 				println!("ALL SAME: {:?}", first_output.unwrap());
-				std::process::exit(1);
+				1
 			}
 			else {
 				println!("Succeeded in repro'ing the issue...different results on outputs");
-				std::process::exit(0);
+				0
 			}
 		}
 		_ => {
 			println!("did not succeed in compiling repro case...is that expected?");
-			std::process::exit(1);
+			1
 		}
-	}
+	};
 	
 	io_thread_handle.kill_thread();
 	
 	io_thread_join_handle.join().expect("could not join compiler IO thread");
+
+	// exit() doesn't run destructors, so clean up the tmp dir first
+	drop(run_tmp_dir);
+	std::process::exit(exit_code);
 }
 
-fn fuzz_arm_simd_codegen(config_filename : &str, num_threads : u32) {
-	let mut thread_handles = Vec::<std::thread::JoinHandle<_>>::new();
-	print!("Launching fuzzer with {} threads\n", num_threads);
+fn bisect_compilers(config_filename : &str, finding_filename : &str, compiler_args : &[String]) {
+	let compilation_config = match load_compilation_config(config_filename) {
+		Some(compilation_config) => compilation_config,
+		None => return
+	};
 
-	let num_cases_state = Arc::new(AtomicUsize::new(0));
-	let num_bugs_found = Arc::new(AtomicUsize::new(0));
-	let num_bytes_fuzzed = Arc::new(AtomicUsize::new(0));
-	
-	let intrinsics_docs_filename = "arm_intrinsics.json";
-	let contents = std::fs::read_to_string(intrinsics_docs_filename);
-	
-	if contents.is_err() {
-		print!("Could not open ARM intrinsics docs file '{}'. Maybe you need to download it?\n", intrinsics_docs_filename);
+	let finding = match SavedFinding::load(finding_filename) {
+		Ok(finding) => finding,
+		Err(err) => {
+			print!("Could not load finding: {}\n", err);
+			return;
+		}
+	};
+
+	if compilation_config.compilations.is_empty() {
+		print!("Config has no compilations to bisect with\n");
 		return;
 	}
-	
-	let contents = contents.unwrap();
 
-	let config_contents = std::fs::read_to_string(config_filename);
-	if config_contents.is_err() {
-		print!("Could not open config file '{}'\n", config_filename);
+	if finding.category == FindingCategory::RuntimeDiff && (finding.input.is_none() || finding.meta.is_none()) {
+		print!("Runtime diff finding {} is missing its input or meta file, so there's nothing to run\n", finding.hash);
 		return;
 	}
-	let config_contents = config_contents.unwrap();
-	
-	let compilation_config = parse_compiler_config(&config_contents);
-	let compilation_tests = compilation_config.compilations;
-	let fuzz_mode = compilation_config.fuzz_mode;
-	
-	let intrinsics_list = parse_arm_intrinsics_json(&contents, &compilation_config.mitigations);
-	
-	let type_to_intrinsics_map = {
-		let mut type_to_intrinsics_map = HashMap::<ARMSIMDType, Vec<ARMSIMDIntrinsic>>::new();
-		
-		for intrinsic in intrinsics_list {
-			let intrinsics_for_type = type_to_intrinsics_map.entry(intrinsic.return_type)
-				.or_insert_with(|| Vec::<ARMSIMDIntrinsic>::with_capacity(4));
-				
-			intrinsics_for_type.push(intrinsic);
+
+	let run_tmp_dir = RunTmpDir::create("bisect");
+	let compilation_tests = expand_placeholders(&compilation_config.compilations, &run_tmp_dir.placeholder_values(None));
+
+	// By default each candidate replaces every compiler in the config, and install dirs are expected to have it under bin/
+	let replace_exe = get_arg_value("--replace-exe");
+	let compiler_rel_path = get_arg_value("--compiler-rel-path").unwrap_or_else(|| {
+		let exe = replace_exe.clone().unwrap_or_else(|| compilation_tests[0].compiler_exe.clone());
+		let exe_name = std::path::Path::new(&exe).file_name().expect("bad compiler exe").to_string_lossy().to_string();
+		format!("bin/{}", exe_name)
+	});
+
+	let compilers = get_bisect_compiler_list(compiler_args, &compiler_rel_path);
+	if compilers.is_empty() {
+		print!("No compilers to bisect across\n");
+		return;
+	}
+
+	let runtime_diff_check : Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool> = if finding.category == FindingCategory::RuntimeDiff {
+		let fuzzer_name = finding.fuzzer.clone().or_else(|| get_arg_value("--fuzzer"));
+		match get_runtime_diff_checker_for_fuzzer(fuzzer_name.as_deref().unwrap_or(""), &finding, &compilation_config) {
+			Some(runtime_diff_check) => runtime_diff_check,
+			None => {
+				print!("Bisecting a runtime diff needs '--fuzzer [{}]' to know how to execute it\n", get_fuzzer_kind_names(""));
+				return;
+			}
 		}
-	
-		type_to_intrinsics_map
+	}
+	else {
+		Box::new(|_| false)
 	};
-	
-	let mut exe_server_connect_addr : String = "".to_string();
-	if let Some(extra_config) = compilation_config.extra_config.as_object() {
-		if let Some(connect_addr) = extra_config["exe_server"].as_str() {
-			exe_server_connect_addr = connect_addr.to_string();
+
+	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread();
+
+	bisect_finding(&finding, &compilers, &compilation_tests, replace_exe.as_deref(), &io_thread_handle, &*runtime_diff_check);
+
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("could not join compiler IO thread");
+}
+
+fn regress_findings(config_filename : &str) {
+	let compilation_config = match load_compilation_config(config_filename) {
+		Some(compilation_config) => compilation_config,
+		None => return
+	};
+
+	let run_tmp_dir = RunTmpDir::create("regress");
+	let compilation_tests = expand_placeholders(&compilation_config.compilations, &run_tmp_dir.placeholder_values(None));
+
+	let issues_dir = get_arg_value("--issues-dir").unwrap_or(FUZZ_ISSUES_DIR.to_string());
+	let mut findings = Vec::<SavedFinding>::new();
+	for finding_filename in list_saved_findings(&issues_dir) {
+		match SavedFinding::load(&finding_filename) {
+			Ok(finding) => findings.push(finding),
+			Err(err) => print!("Skipping finding '{}': {}\n", finding_filename, err)
 		}
 	}
-	
+
+	print!("Re-running {} findings from '{}'\n", findings.len(), issues_dir);
+
+	// Older findings don't record which fuzzer found them, so let the command line fill that in
+	let default_fuzzer = get_arg_value("--fuzzer");
+
 	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread();
-	
-	// This should ensure subsequent runs don't re-use the same seeds for everything
-	let initial_time = get_timestamp_for_seed();//unsafe { _rdtsc() };
-	
-	for thread_id in 0..num_threads {
-		let num_cases_state = num_cases_state.clone();
-		let num_bugs_found = num_bugs_found.clone();
-		let num_bytes_fuzzed = num_bytes_fuzzed.clone();
-		
-		let compilation_tests = compilation_tests.clone();
-		let type_to_intrinsics_map = type_to_intrinsics_map.clone();
-		let exe_server_connect_addr = exe_server_connect_addr.clone();
-		
-		// Some prime numbers beause they're better, or so I hear
-		let initial_seed = ((thread_id as u64) + 937) * 241 + initial_time;
-		
-		let io_thread_handle = io_thread_handle.clone();
-		
-		let thread_handle = std::thread::spawn(move || {
-			
-			let thread_input = ARMCodegenFuzzerThreadInput {
-				thread_seed : initial_seed,
-				type_to_intrinsics_map : type_to_intrinsics_map,
-				mode: fuzz_mode,
-				connect_addr: exe_server_connect_addr
-			};
-			
-			fuzz_simd_codegen_loop::<ARMCodegenFuzzer, ARMCodegenFuzzerThreadInput, ARMSIMDCodegenCtx, ARMCodegenFuzzerCodeMetadata, ARMCodeFuzzerInputValues, ARMSIMDOutputValues>(
-				thread_input, &compilation_tests, fuzz_mode, num_cases_state, num_bugs_found, num_bytes_fuzzed, io_thread_handle);
-		});
-		thread_handles.push(thread_handle);
+
+	let results = run_regression_suite(&findings, &compilation_tests, &io_thread_handle, |finding| {
+		if finding.category != FindingCategory::RuntimeDiff {
+			return Some(Box::new(|_| false));
+		}
+
+		if finding.input.is_none() || finding.meta.is_none() {
+			return None;
+		}
+
+		let fuzzer_name = finding.fuzzer.clone().or_else(|| default_fuzzer.clone());
+		return get_runtime_diff_checker_for_fuzzer(fuzzer_name.as_deref().unwrap_or(""), finding, &compilation_config);
+	});
+
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("could not join compiler IO thread");
+	drop(run_tmp_dir);
+
+	let results_json = regression_results_to_json(&results);
+	print!("{} findings: {} still reproduce, {} fixed, {} changed category, {} skipped\n",
+		results_json["total"], results_json["still_reproduces"], results_json["fixed"], results_json["changed_category"], results_json["skipped"]);
+
+	for result in results.iter() {
+		if let RegressionStatus::ChangedCategory(new_category) = result.status {
+			print!("  {}/{} is now a {} issue\n", result.category.dir_name(), result.hash, new_category.dir_name());
+		}
 	}
-	
-	print!("Done launching\n");
-
-	let start_time = Instant::now();
-	loop {
-		std::thread::sleep(Duration::from_secs(1));
-		let time_so_far = Instant::now().duration_since(start_time);
-		let seconds_so_far = time_so_far.as_secs_f32();
-		let num_cases_so_far = num_cases_state.load(Ordering::SeqCst);
-		let avg_cases_per_second = num_cases_so_far as f32 / seconds_so_far;
-		let num_bugs_so_far = num_bugs_found.load(Ordering::SeqCst);
-		let num_bytes_so_far = num_bytes_fuzzed.load(Ordering::SeqCst);
-		
-		const BYTES_PER_KB : f64 = 1024.0;
-		let avg_kb_per_sec = (num_bytes_so_far as f64) / (seconds_so_far as f64) / BYTES_PER_KB;
-
-		print!("ARM | {:10.1} sec uptime | {:10} cases | {:10.2} cps | {:5} bugs | {:8.3} KB/s code fuzzed\n",
-			seconds_so_far, num_cases_so_far, avg_cases_per_second, num_bugs_so_far, avg_kb_per_sec);
+
+	if let Some(json_filename) = get_arg_value("--json-out") {
+		std::fs::write(&json_filename, serde_json::to_string_pretty(&results_json).expect("")).expect("couldn't write to file?");
+		print!("Wrote JSON report to '{}'\n", json_filename);
+	}
+
+	if let Some(junit_filename) = get_arg_value("--junit-out") {
+		std::fs::write(&junit_filename, regression_results_to_junit(&results)).expect("couldn't write to file?");
+		print!("Wrote JUnit report to '{}'\n", junit_filename);
+	}
+
+	// Non-zero exit so nightly qualification can gate on it
+	if results.iter().any(|result| result.is_failure()) {
+		std::process::exit(1);
 	}
 }
 
-fn fuzz_loop_codegen(config_filename : &str, num_threads : u32) {
-	let mut thread_handles = Vec::<std::thread::JoinHandle<_>>::new();
-	print!("Launching fuzzer with {} threads\n", num_threads);
+// Re-checks every saved runtime diff with the config's sanitizer_compilation, and moves the ones that turn out to be UB in the generated code over to generator_ub.
+// The fuzz loop already does this for new findings, this is for ones found before there was a sanitizer_compilation
+fn check_findings_for_ub(config_filename : &str) {
+	let compilation_config = match load_compilation_config(config_filename) {
+		Some(compilation_config) => compilation_config,
+		None => return
+	};
 
-	let num_cases_state = Arc::new(AtomicUsize::new(0));
-	let num_bugs_found = Arc::new(AtomicUsize::new(0));
-	let num_bytes_fuzzed = Arc::new(AtomicUsize::new(0));
+	let sanitizer_compilation = match &compilation_config.sanitizer_compilation {
+		Some(sanitizer_compilation) => sanitizer_compilation,
+		None => {
+			print!("Config has no sanitizer_compilation to check findings with\n");
+			return;
+		}
+	};
 
-	let config_contents = std::fs::read_to_string(config_filename);
-	if config_contents.is_err() {
-		print!("Could not open config file '{}'\n", config_filename);
-		return;
+	let run_tmp_dir = RunTmpDir::create("check_ub");
+	let sanitizer_compilation = expand_sanitizer_placeholders(&compilation_config.compilations, sanitizer_compilation, &run_tmp_dir.placeholder_values(None));
+
+	let issues_dir = get_arg_value("--issues-dir").unwrap_or(FUZZ_ISSUES_DIR.to_string());
+	let mut findings = Vec::<SavedFinding>::new();
+	for finding_filename in list_saved_findings(&issues_dir) {
+		match SavedFinding::load(&finding_filename) {
+			Ok(finding) if finding.category == FindingCategory::RuntimeDiff => findings.push(finding),
+			Ok(_) => {}
+			Err(err) => print!("Skipping finding '{}': {}\n", finding_filename, err)
+		}
 	}
-	let config_contents = config_contents.unwrap();
-	
-	let compilation_config = parse_compiler_config(&config_contents);
-	let compilation_tests = compilation_config.compilations;
-	let fuzz_mode = compilation_config.fuzz_mode;
-	
-	let initial_time = get_timestamp_for_seed();//unsafe { _rdtsc() };
-	
+
+	print!("Checking {} runtime diffs from '{}' for UB\n", findings.len(), issues_dir);
+
 	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread();
-	
-	for thread_id in 0..num_threads {
-		let num_cases_state = num_cases_state.clone();
-		let num_bugs_found = num_bugs_found.clone();
-		let num_bytes_fuzzed = num_bytes_fuzzed.clone();
-		let compilation_tests = compilation_tests.clone();
-
-		// Some prime numbers beause they're better, or so I hear
-		let initial_seed = ((thread_id as u64) + 937) * 241 + initial_time;
-		
-		let io_thread_handle = io_thread_handle.clone();
-		
-		let thread_handle = std::thread::spawn(move || {
-			
-			let thread_input = LoopFuzzerThreadInput {
-				thread_seed : initial_seed
-			};
-			
-			fuzz_simd_codegen_loop::<LoopFuzzer, LoopFuzzerThreadInput, LoopCodegenCtx, LoopFuzzerCodeMetadata, LoopFuzzerInputValues, LoopFuzzerOutputValues>(
-				thread_input, &compilation_tests, fuzz_mode, num_cases_state, num_bugs_found, num_bytes_fuzzed, io_thread_handle);
-		});
-		thread_handles.push(thread_handle);
-	}
-	
-	print!("Done launching\n");
-
-	let start_time = Instant::now();
-	loop {
-		std::thread::sleep(Duration::from_secs(1));
-		let time_so_far = Instant::now().duration_since(start_time);
-		let seconds_so_far = time_so_far.as_secs_f32();
-		let num_cases_so_far = num_cases_state.load(Ordering::SeqCst);
-		let avg_cases_per_second = num_cases_so_far as f32 / seconds_so_far;
-		let num_bugs_so_far = num_bugs_found.load(Ordering::SeqCst);
-		let num_bytes_so_far = num_bytes_fuzzed.load(Ordering::SeqCst);
-		
-		const BYTES_PER_KB : f64 = 1024.0;
-		let avg_kb_per_sec = (num_bytes_so_far as f64) / (seconds_so_far as f64) / BYTES_PER_KB;
-
-		print!("LOOP | {:10.1} sec uptime | {:10} cases | {:10.2} cps | {:5} bugs | {:8.3} KB/s code fuzzed\n",
-			seconds_so_far, num_cases_so_far, avg_cases_per_second, num_bugs_so_far, avg_kb_per_sec);
+
+	let mut num_generator_ub = 0;
+	for finding in findings.iter() {
+		let input_str = match &finding.input {
+			Some(input_str) => input_str,
+			None => {
+				print!("Skipping runtime diff {}, it has no saved input\n", finding.hash);
+				continue;
+			}
+		};
+
+		if reclassify_runtime_diff(&issues_dir, &finding.hash, &finding.code, input_str, &sanitizer_compilation, &io_thread_handle) == FindingCategory::GeneratorUB {
+			num_generator_ub += 1;
+		}
 	}
+
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("could not join compiler IO thread");
+	drop(run_tmp_dir);
+
+	print!("{} of {} runtime diffs were UB in the generated code\n", num_generator_ub, findings.len());
 }
 
-fn fuzz_asm_codegen(config_filename : &str, num_threads : u32) {
-	print!("Launching fuzzer with {} threads\n", num_threads);
+fn replay_seed(config_filename : &str, seed_str : &str) {
+	let (case_seed, input_index) = match CaseSeed::parse_replay_string(seed_str) {
+		Some(parsed) => parsed,
+		None => {
+			print!("Could not parse seed '{}', expected [thread_seed]:[case_index]:[input_index]\n", seed_str);
+			return;
+		}
+	};
 
-	let num_cases_state = Arc::new(AtomicUsize::new(0));
-	let num_bugs_found = Arc::new(AtomicUsize::new(0));
-	let num_bytes_fuzzed = Arc::new(AtomicUsize::new(0));
+	let fuzzer_kind = match get_arg_value("--fuzzer").as_deref().and_then(find_fuzzer_kind) {
+		Some(fuzzer_kind) => fuzzer_kind,
+		None => {
+			print!("Replaying needs '--fuzzer [{}]' to know how to regenerate the case\n", get_fuzzer_kind_names(""));
+			return;
+		}
+	};
+
+	let compilation_config = match load_compilation_config(config_filename) {
+		Some(compilation_config) => compilation_config,
+		None => return
+	};
+
+	let run_tmp_dir = RunTmpDir::create("replay");
+	let compilation_tests = expand_placeholders(&compilation_config.compilations, &run_tmp_dir.placeholder_values(None));
 
-	let config_contents = std::fs::read_to_string(config_filename);
-	if config_contents.is_err() {
-		print!("Could not open config file '{}'\n", config_filename);
-		return;
-	}
-	let config_contents = config_contents.unwrap();
-	
-	let compilation_config = parse_compiler_config(&config_contents);
-	let compilation_tests = compilation_config.compilations;
-	let fuzz_mode = compilation_config.fuzz_mode;
-	
-	let initial_time = get_timestamp_for_seed();//unsafe { _rdtsc() };
-	
 	let (io_thread_handle, io_thread_join_handle) = CompilerIOThread::spawn_io_thread();
-	
-	let mut thread_handles = Vec::<std::thread::JoinHandle<_>>::new();
-	for thread_id in 0..num_threads {
-		let num_cases_state = num_cases_state.clone();
-		let num_bugs_found = num_bugs_found.clone();
-		let num_bytes_fuzzed = num_bytes_fuzzed.clone();
-		let compilation_tests = compilation_tests.clone();
-
-		// Some prime numbers beause they're better, or so I hear
-		let initial_seed = ((thread_id as u64) + 937) * 241 + initial_time;
-		
-		let io_thread_handle = io_thread_handle.clone();
-		
-		let thread_handle = std::thread::spawn(move || {
-			
-			let thread_input = AsmFuzzerThreadInput {
-				thread_seed : initial_seed
-			};
-			
-			fuzz_simd_codegen_loop::<AsmFuzzer, AsmFuzzerThreadInput, AsmCodegenCtx, AsmFuzzerCodeMetadata, AsmFuzzerInputValues, AsmFuzzerOutputValues>(
-				thread_input, &compilation_tests, fuzz_mode, num_cases_state, num_bugs_found, num_bytes_fuzzed, io_thread_handle);
-		});
-		thread_handles.push(thread_handle);
-	}
-	
-	print!("Done launching\n");
-
-	let start_time = Instant::now();
-	loop {
-		std::thread::sleep(Duration::from_secs(1));
-		let time_so_far = Instant::now().duration_since(start_time);
-		let seconds_so_far = time_so_far.as_secs_f32();
-		let num_cases_so_far = num_cases_state.load(Ordering::SeqCst);
-		let avg_cases_per_second = num_cases_so_far as f32 / seconds_so_far;
-		let num_bugs_so_far = num_bugs_found.load(Ordering::SeqCst);
-		let num_bytes_so_far = num_bytes_fuzzed.load(Ordering::SeqCst);
-		
-		const BYTES_PER_KB : f64 = 1024.0;
-		let avg_kb_per_sec = (num_bytes_so_far as f64) / (seconds_so_far as f64) / BYTES_PER_KB;
-
-		print!("ASM | {:10.1} sec uptime | {:10} cases | {:10.2} cps | {:5} bugs | {:8.3} KB/s code fuzzed\n",
-			seconds_so_far, num_cases_so_far, avg_cases_per_second, num_bugs_so_far, avg_kb_per_sec);
+
+	print!("Replaying case {} input {} from thread seed {}\n", case_seed.case_index, input_index, case_seed.thread_seed);
+
+	let replay_result = (fuzzer_kind.replay)(&compilation_config, case_seed, input_index, &compilation_tests, &run_tmp_dir.path, &io_thread_handle);
+
+	io_thread_handle.kill_thread();
+	io_thread_join_handle.join().expect("could not join compiler IO thread");
+	// The regenerated case is in there, along with whatever the compilers left behind
+	let replay_dir = run_tmp_dir.keep();
+	print!("Left the replay's files in '{}'\n", replay_dir.display());
+
+	match replay_result {
+		Ok(Some(category)) => {
+			print!("Case reproduces as a {} issue\n", category.dir_name());
+			std::process::exit(1);
+		}
+		Ok(None) => {
+			print!("Case ran fine\n");
+		}
+		Err(err) => {
+			print!("Could not replay case: {}\n", err);
+			std::process::exit(2);
+		}
 	}
 }
 
 fn print_usage() {
-	print!("usage: [exe] [fuzz-x86|fuzz-arm|fuzz-loop|fuzz-asm] [config_filename] [--threads NUM_THREADS]\n");
+	let fuzzer_names = get_fuzzer_kind_names("");
+	print!("usage: [exe] [{}] [config_filename] [--threads NUM_THREADS] [--seed SEED] [--iterations NUM_CASES]\n", get_fuzzer_kind_names("fuzz-"));
+	print!("             [--max-cases NUM_CASES] [--max-time SECONDS] [--stop-after-bugs NUM_BUGS]\n");
+	print!("             [--stats-file FILE] [--prometheus-file FILE] [--stats-interval SECONDS]\n");
+	print!("             [--max-compile-jobs NUM_JOBS]\n");
+	print!("       [exe] bisect [config_filename] [finding_filename] [compiler_dir | compiler_exe...] [--fuzzer {}]\n", fuzzer_names);
+	print!("             [--replace-exe COMPILER_EXE] [--compiler-rel-path REL_PATH]\n");
+	print!("       [exe] regress [config_filename] [--fuzzer {}] [--issues-dir DIR] [--json-out FILE] [--junit-out FILE]\n", fuzzer_names);
+	print!("       [exe] replay [config_filename] --fuzzer {} --seed THREAD_SEED:CASE_INDEX[:INPUT_INDEX]\n", fuzzer_names);
+	print!("       [exe] check-ub [config_filename] [--issues-dir DIR]\n");
+	print!("       [exe] check-config [config_filename]\n");
+	print!("       [exe] exe-server [listen_addr]\n");
+}
+
+// All of our flags take a value, e.g. '--threads 8'
+fn get_arg_value(flag : &str) -> Option<String> {
+	let args : Vec<String> = std::env::args().collect();
+	for (ii, arg) in args.iter().enumerate() {
+		if arg == flag {
+			if ii == args.len() - 1 {
+				panic!("{} was the last argument", flag);
+			}
+
+			return Some(args[ii + 1].clone());
+		}
+	}
+
+	return None;
+}
+
+// Everything after the method that isn't a flag or a flag's value
+fn get_positional_args() -> Vec<String> {
+	let mut positional_args = Vec::<String>::new();
+	let mut skip_next = false;
+	for arg in std::env::args().skip(2) {
+		if skip_next {
+			skip_next = false;
+		}
+		else if arg.starts_with("--") {
+			skip_next = true;
+		}
+		else {
+			positional_args.push(arg);
+		}
+	}
+
+	return positional_args;
 }
 
-fn get_num_threads() -> u32 {
+fn get_fuzz_run_options() -> FuzzRunOptions {
+	let seed = get_arg_value("--seed").map(|seed_str| seed_str.parse::<u64>().expect("--seed was not followed by a number"));
+	// --max-cases is the same thing as --iterations, it just reads better next to the other budgets
+	let max_iterations = get_arg_value("--iterations").or_else(|| get_arg_value("--max-cases"))
+		.map(|iter_str| iter_str.parse::<u64>().expect("--iterations/--max-cases was not followed by a number"));
+	let max_time = get_arg_value("--max-time").map(|time_str| Duration::from_secs(time_str.parse::<u64>().expect("--max-time was not followed by a number of seconds")));
+	let stop_after_bugs = get_arg_value("--stop-after-bugs").map(|bugs_str| bugs_str.parse::<usize>().expect("--stop-after-bugs was not followed by a number"));
+	let stats_interval = get_arg_value("--stats-interval").map_or(10, |interval_str| interval_str.parse::<u64>().expect("--stats-interval was not followed by a number of seconds"));
+
+	return FuzzRunOptions {
+		seed: seed,
+		max_iterations: max_iterations,
+		max_time: max_time,
+		stop_after_bugs: stop_after_bugs,
+		stats_filename: get_arg_value("--stats-file"),
+		prometheus_filename: get_arg_value("--prometheus-file"),
+		stats_interval: Duration::from_secs(stats_interval),
+		max_compile_jobs: get_arg_value("--max-compile-jobs").map(|jobs_str| jobs_str.parse::<u32>().expect("--max-compile-jobs was not followed by a number"))
+	};
+}
+
+fn get_num_threads(run_options : &FuzzRunOptions) -> u32 {
+	// Each thread's cases are deterministic on their own, but the order they interleave in isn't,
+	// so a fixed seed runs single-threaded to keep the whole run (and its timing) comparable
+	if run_options.seed.is_some() {
+		if get_arg_value("--threads").is_some() {
+			print!("--seed forces a single thread, ignoring --threads\n");
+		}
+
+		return 1;
+	}
+
 	for (ii,arg) in std::env::args().enumerate() {
 		if arg == "--threads" {
 			if ii == std::env::args().len() - 1 {
@@ -748,15 +622,32 @@ fn main() {
 	}
 	
 	let method = std::env::args().nth(1).expect("no args?");
-	if method == "fuzz-x86" {
+	if let Some(fuzzer_kind) = method.strip_prefix("fuzz-").and_then(find_fuzzer_kind) {
 		let config_filename = std::env::args().nth(2).expect("missing config?");
-		let num_threads = get_num_threads();
-		fuzz_x86_simd_codegen(&config_filename, num_threads);
-	}
-	else if method == "fuzz-arm" {
-		let config_filename = std::env::args().nth(2).expect("missing config?");
-		let num_threads = get_num_threads();
-		fuzz_arm_simd_codegen(&config_filename, num_threads);
+		let compilation_config = match load_compilation_config(&config_filename) {
+			Some(compilation_config) => compilation_config,
+			None => return
+		};
+
+		let run_options = get_fuzz_run_options();
+		let num_threads = get_num_threads(&run_options);
+		match (fuzzer_kind.fuzz)(&compilation_config, num_threads, &run_options) {
+			Ok(summary) => {
+				if let Some(exec_error) = summary.exec_error {
+					print!("Fuzzer stopped early, could not run code: {}\n", exec_error);
+					std::process::exit(2);
+				}
+
+				// Non-zero exit if we found anything, so a nightly job can tell without reading fuzz_issues
+				if summary.num_bugs > 0 {
+					std::process::exit(1);
+				}
+			}
+			Err(err) => {
+				print!("Could not run fuzzer: {}\n", err);
+				std::process::exit(2);
+			}
+		}
 	}
 	else if method == "repro-arm" {
 		let config_filename = std::env::args().nth(2).expect("missing config?");
@@ -765,15 +656,42 @@ fn main() {
 		let input_filename = std::env::args().nth(5).expect("missing input filename?");
 		repro_arm_simd_codegen(&config_filename, &repro_filename, &meta_filename, &input_filename);
 	}
-	else if method == "fuzz-loop" {
+	else if method == "bisect" {
+		let positional_args = get_positional_args();
+		if positional_args.len() < 3 {
+			print_usage();
+			return;
+		}
+
+		bisect_compilers(&positional_args[0], &positional_args[1], &positional_args[2..]);
+	}
+	else if method == "replay" {
+		let config_filename = std::env::args().nth(2).expect("missing config?");
+		let seed_str = get_arg_value("--seed").expect("replay needs a --seed");
+		replay_seed(&config_filename, &seed_str);
+	}
+	else if method == "regress" {
 		let config_filename = std::env::args().nth(2).expect("missing config?");
-		let num_threads = get_num_threads();
-		fuzz_loop_codegen(&config_filename, num_threads);
+		regress_findings(&config_filename);
 	}
-	else if method == "fuzz-asm" {
+	else if method == "check-ub" {
 		let config_filename = std::env::args().nth(2).expect("missing config?");
-		let num_threads = get_num_threads();
-		fuzz_asm_codegen(&config_filename, num_threads);
+		check_findings_for_ub(&config_filename);
+	}
+	else if method == "check-config" {
+		let config_filename = std::env::args().nth(2).expect("missing config?");
+		check_config(&config_filename);
+	}
+	else if method == "exe-server" {
+		// What extra_config.exe_server connects to, e.g. 'exe-server 0.0.0.0:7777' on the ARM box
+		let listen_addr = std::env::args().nth(2).expect("missing listen address?");
+		let listener = std::net::TcpListener::bind(&listen_addr).expect("could not listen on address");
+		print!("Exe server listening on {}\n", listener.local_addr().expect(""));
+		run_code_exe_server(listener);
+	}
+	else if method == SHARED_LIB_WORKER_METHOD {
+		// Not something to run by hand, this is what compilations with "exec_backend": "shared_lib" get run in
+		run_shared_lib_worker();
 	}
 	else {
 		print_usage();
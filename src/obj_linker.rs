@@ -0,0 +1,478 @@
+
+// A tiny in-process linker for the objects we load, so generated code can call out to libc/libm (memcpy, memset, sinf, etc.)
+// Symbols that aren't in the object are either a builtin stub we put in the page ourselves, or whatever the host process has (via dlsym).
+// Since the page could be anywhere, calls out to the host go through PLT entries we add after the sections, and GOT relocations get a GOT we make up.
+// This goes by the object's format rather than the host, so e.g. COFF objects from clang --target=x86_64-pc-windows-msvc can run on Linux
+
+use object::{Object, ObjectSection, ObjectSymbol, RelocationKind, RelocationEncoding, RelocationTarget, SectionKind};
+use object::read::{SectionIndex, SymbolIndex};
+
+use std::collections::HashMap;
+
+use crate::exec_mem::{ExecPage, CallConv, DataLayout};
+use crate::parse_exe::{LoadError, align_vec, get_c_symbol_name, get_symbol_name, get_symbol_offset_in_memory};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolAddr {
+	// Offset from the start of the exec page
+	InPage(usize),
+	// Absolute address somewhere else in our process
+	Host(usize)
+}
+
+impl SymbolAddr {
+	pub fn get_absolute_addr(&self, page_base : usize) -> usize {
+		match self {
+			SymbolAddr::InPage(offset) => page_base + offset,
+			SymbolAddr::Host(addr) => *addr
+		}
+	}
+}
+
+// What a relocation points at. ELF and COFF always go through a symbol, but Mach-O can point straight at a section
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocTarget {
+	Symbol(SymbolIndex),
+	Section(SectionIndex)
+}
+
+pub fn get_reloc_target(reloc : &object::Relocation) -> Result<RelocTarget, LoadError> {
+	match reloc.target() {
+		RelocationTarget::Symbol(symbol_index) => Ok(RelocTarget::Symbol(symbol_index)),
+		RelocationTarget::Section(section_index) => Ok(RelocTarget::Section(section_index)),
+		reloc_target => Err(LoadError::UnsupportedRelocation(format!("{:?} with target {:?}", reloc.kind(), reloc_target)))
+	}
+}
+
+// For error messages
+pub fn get_reloc_target_name(obj_file : &object::File, target : RelocTarget) -> Result<String, LoadError> {
+	match target {
+		RelocTarget::Symbol(symbol_index) => {
+			let symbol = obj_file.symbol_by_index(symbol_index).map_err(|_| LoadError::BadObjectFile(format!("bad symbol index {}", symbol_index.0)))?;
+			return get_symbol_name(&symbol);
+		}
+		RelocTarget::Section(section_index) => {
+			return Ok(format!("<section {}>", section_index.0));
+		}
+	}
+}
+
+// COFF and Mach-O keep the addend in the bytes getting relocated instead of in the relocation itself
+pub fn read_implicit_addend(bytes_loaded_into_memory : &[u8], offset : usize, size_bits : u8) -> Result<i64, LoadError> {
+	let size_bytes = size_bits as usize / 8;
+	if size_bits % 8 != 0 || size_bytes == 0 || size_bytes > 8 || offset + size_bytes > bytes_loaded_into_memory.len() {
+		return Err(LoadError::UnsupportedRelocation(format!("implicit addend of {} bits at offset {:#x}", size_bits, offset)));
+	}
+
+	let mut addend_bytes = [0u8; 8];
+	addend_bytes[..size_bytes].copy_from_slice(&bytes_loaded_into_memory[offset..offset+size_bytes]);
+	// Sign extend it
+	let shift = 64 - size_bits as u32;
+	return Ok((i64::from_le_bytes(addend_bytes) << shift) >> shift);
+}
+
+// Things we don't want to call the real version of. __stack_chk_fail never returns, so it's just a trap: a smashed canary stops right there
+// as a plain SIGILL instead of carrying on through the broken epilogue (or libc printing and aborting from inside the loader's page).
+// A hardcoded guard is fine since nothing's actually attacking us. ___chkstk_darwin is what macOS probes big stack frames with,
+// our stacks are already there so it doesn't need to do anything
+const X86_64_BUILTIN_STUBS : [(&str, &[u8]); 3] = [
+	("__stack_chk_fail", &[0x0F, 0x0B]), // ud2
+	("__stack_chk_guard", &[0u8; 8]),
+	("__chkstk_darwin", &[0xC3])
+];
+
+// MS ABI versions, since we can't call the host's libc from a COFF object if the host isn't Windows
+const MEMSET_WIN64_BYTES : [u8 ; 28] = [
+  0x48, 0x89, 0xc8,                    // mov    %rcx,%rax
+  0x4d, 0x85, 0xc0,                    // test   %r8,%r8
+  0x74, 0x13,                          // je     1b <?my_memset@@YAPEAXPEAXH_K@Z+0x1b>
+  0x31, 0xc9,                          // xor    %ecx,%ecx
+  0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00,  // nopw   0x0(%rax,%rax,1)
+  0x88, 0x14, 0x08,                    // mov    %dl,(%rax,%rcx,1)
+  0x48, 0xff, 0xc1,                    // inc    %rcx
+  0x49, 0x39, 0xc8,                    // cmp    %rcx,%r8
+  0x75, 0xf5,                          // jne    10 <?my_memset@@YAPEAXPEAXH_K@Z+0x10>
+  0xc3                                 // retq
+];
+
+const MEMCPY_WIN64_BYTES : [u8 ; 29] = [
+  0x48, 0x89, 0xc8,                    // mov    %rcx,%rax
+  0x4d, 0x85, 0xc0,                    // test   %r8,%r8
+  0x74, 0x14,                          // je     1c
+  0x45, 0x31, 0xc9,                    // xor    %r9d,%r9d
+  0x46, 0x0f, 0xb6, 0x14, 0x0a,        // movzbl (%rdx,%r9,1),%r10d
+  0x46, 0x88, 0x14, 0x08,              // mov    %r10b,(%rax,%r9,1)
+  0x49, 0xff, 0xc1,                    // inc    %r9
+  0x4d, 0x39, 0xc1,                    // cmp    %r8,%r9
+  0x75, 0xef,                          // jne    b
+  0xc3                                 // retq
+];
+
+#[cfg(target_os = "windows")]
+const CHKSTK_WIN64_BYTES : [u8 ; 78] = [
+	0x48, 0x83, 0xEC, 0x10,                                //sub         rsp,10h  
+	0x4C, 0x89, 0x14, 0x24,                                //mov         qword ptr [rsp],r10  
+	0x4C, 0x89, 0x5C, 0x24, 0x08,                          //mov         qword ptr [rsp+8],r11  
+	0x4D, 0x33, 0xDB,                                      //xor         r11,r11  
+	0x4C, 0x8D, 0x54, 0x24, 0x18,                          //lea         r10,[rsp+18h]  
+	0x4C, 0x2B, 0xD0,                                      //sub         r10,rax  
+	0x4D, 0x0F, 0x42, 0xD3,                                //cmovb       r10,r11  
+	0x65, 0x4C, 0x8B, 0x1C, 0x25, 0x10, 0x00, 0x00, 0x00,  //mov         r11,qword ptr gs:[10h]  
+	0x4D, 0x3B, 0xD3,                                      //cmp         r10,r11  
+	0x73, 0x16,                                            //jae         cs10+10h (07FF6E4AE7AB0h)  
+	0x66, 0x41, 0x81, 0xE2, 0x00, 0xF0,                    //and         r10w,0F000h  
+	0x4D, 0x8D, 0x9B, 0x00, 0xF0, 0xFF, 0xFF,              //lea         r11,[r11-1000h]  
+	0x41, 0xC6, 0x03, 0x00,                                //mov         byte ptr [r11],0  
+	0x4D, 0x3B, 0xD3,                                      //cmp         r10,r11  
+	0x75, 0xF0,                                            //jne         cs10 (07FF6E4AE7AA0h)  
+	0x4C, 0x8B, 0x14, 0x24,                                //mov         r10,qword ptr [rsp]  
+	0x4C, 0x8B, 0x5C, 0x24, 0x08,                          //mov         r11,qword ptr [rsp+8]  
+	0x48, 0x83, 0xC4, 0x10,                                //add         rsp,10h  
+	0xC3,                                                  //ret  
+];
+
+// Outside of Windows there's no TEB to read the stack limit from (gs:[10h] would just fault),
+// but the stack doesn't need to be touched a page at a time either, so __chkstk can be a no-op
+#[cfg(not(target_os = "windows"))]
+const CHKSTK_WIN64_BYTES : [u8 ; 1] = [0xC3];
+
+// COFF objects only ever get these, never anything from the host
+const X86_64_COFF_BUILTIN_STUBS : [(&str, &[u8]); 5] = [
+	("__chkstk", &CHKSTK_WIN64_BYTES),
+	("memset", &MEMSET_WIN64_BYTES),
+	("memcpy", &MEMCPY_WIN64_BYTES),
+	("__security_check_cookie", &[0xC3]),
+	("__security_cookie", &[0u8; 8])
+];
+
+// jmp qword ptr [rip+0], followed by the 8-byte address to jump to
+const X86_64_PLT_ENTRY_BYTES : [u8; 6] = [0xFF, 0x25, 0x00, 0x00, 0x00, 0x00];
+
+// We don't use anything from libm ourselves, so it might not be loaded into the process yet
+#[cfg(target_os = "linux")]
+const HOST_EXTRA_LIBRARIES : [&str; 1] = ["libm.so.6"];
+
+#[cfg(not(target_os = "linux"))]
+const HOST_EXTRA_LIBRARIES : [&str; 0] = [];
+
+// dlopen handles for HOST_EXTRA_LIBRARIES (as usizes so they can be shared between threads), we never close them
+#[cfg(unix)]
+fn get_host_extra_library_handles() -> &'static Vec<usize> {
+	static HANDLES : std::sync::OnceLock<Vec<usize>> = std::sync::OnceLock::new();
+	return HANDLES.get_or_init(|| {
+		HOST_EXTRA_LIBRARIES.iter().filter_map(|library_name| {
+			let library_name_c = std::ffi::CString::new(*library_name).expect("");
+			let handle = unsafe { libc::dlopen(library_name_c.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+			if handle.is_null() { None } else { Some(handle as usize) }
+		}).collect()
+	});
+}
+
+#[cfg(unix)]
+pub fn lookup_host_symbol(symbol_name : &str) -> Option<usize> {
+	let symbol_name_c = std::ffi::CString::new(symbol_name).ok()?;
+
+	let addr = unsafe { libc::dlsym(libc::RTLD_DEFAULT, symbol_name_c.as_ptr()) };
+	if !addr.is_null() {
+		return Some(addr as usize);
+	}
+
+	for handle in get_host_extra_library_handles().iter() {
+		let addr = unsafe { libc::dlsym(*handle as *mut libc::c_void, symbol_name_c.as_ptr()) };
+		if !addr.is_null() {
+			return Some(addr as usize);
+		}
+	}
+
+	return None;
+}
+
+#[cfg(not(unix))]
+pub fn lookup_host_symbol(_symbol_name : &str) -> Option<usize> {
+	// TODO: GetProcAddress on the CRT, for now Windows only gets the builtin stubs
+	return None;
+}
+
+// Unwind tables that object calls regular data (ELF's .eh_frame already gets its own kind)
+const UNWIND_SECTION_PREFIXES : [&str; 4] = [".pdata", ".xdata", "__eh_frame", "__compact_unwind"];
+
+// Only the sections that actually get used at runtime, debug info and unwind tables can keep pointing wherever
+pub fn should_relocate_section(section : &object::Section) -> bool {
+	if let Ok(section_name) = section.name() {
+		if UNWIND_SECTION_PREFIXES.iter().any(|prefix| section_name.starts_with(prefix)) {
+			return false;
+		}
+	}
+
+	return matches!(section.kind(), SectionKind::Text | SectionKind::Data | SectionKind::ReadOnlyData | SectionKind::ReadOnlyString);
+}
+
+// Checks that a relocated value fits in the bits it's getting written to
+pub fn check_reloc_value_fits(value : i64, size_bits : u8, signed : bool, symbol_name : &str) -> Result<(), LoadError> {
+	if size_bits >= 64 {
+		return Ok(());
+	}
+
+	let fits = if signed {
+		let min_value = -(1i64 << (size_bits - 1));
+		let max_value = (1i64 << (size_bits - 1)) - 1;
+		value >= min_value && value <= max_value
+	}
+	else {
+		value >= 0 && value < (1i64 << size_bits)
+	};
+
+	if !fits {
+		return Err(LoadError::BadRelocationValue(format!("{:#x} does not fit in {} {} bits, for '{}'", value, if signed { "signed" } else { "unsigned" }, size_bits, symbol_name)));
+	}
+
+	return Ok(());
+}
+
+// Everything we add after the object's sections, and where each symbol ended up
+#[derive(Default)]
+pub struct ObjLinker {
+	symbol_addrs : HashMap<SymbolIndex, SymbolAddr>,
+	builtin_stub_offsets : HashMap<String, usize>,
+	got_slots : HashMap<SymbolIndex, usize>,
+	// Keyed by host address, since several symbols can end up at the same place
+	plt_entries : HashMap<usize, usize>
+}
+
+impl ObjLinker {
+	pub fn resolve_symbol(&mut self, obj_file : &object::File, symbol : &object::Symbol, section_to_memory_addr : &HashMap<SectionIndex, usize>,
+			builtin_stubs : &[(&str, &[u8])], use_host_symbols : bool, bytes_loaded_into_memory : &mut Vec<u8>) -> Result<SymbolAddr, LoadError> {
+		if let Some(symbol_addr) = self.symbol_addrs.get(&symbol.index()) {
+			return Ok(*symbol_addr);
+		}
+
+		let symbol_addr = if symbol.section_index().is_some() {
+			SymbolAddr::InPage(get_symbol_offset_in_memory(obj_file, symbol, section_to_memory_addr)? as usize)
+		}
+		else {
+			let symbol_name = get_symbol_name(symbol)?;
+			let c_symbol_name = get_c_symbol_name(obj_file, &symbol_name);
+			if let Some(stub_offset) = self.builtin_stub_offsets.get(c_symbol_name) {
+				SymbolAddr::InPage(*stub_offset)
+			}
+			else if let Some((_, stub_bytes)) = builtin_stubs.iter().find(|(stub_name, _)| *stub_name == c_symbol_name) {
+				align_vec(bytes_loaded_into_memory, 16);
+				let stub_offset = bytes_loaded_into_memory.len();
+				bytes_loaded_into_memory.extend_from_slice(stub_bytes);
+				self.builtin_stub_offsets.insert(c_symbol_name.to_string(), stub_offset);
+				SymbolAddr::InPage(stub_offset)
+			}
+			else {
+				let host_addr = if use_host_symbols { lookup_host_symbol(c_symbol_name) } else { None };
+				SymbolAddr::Host(host_addr.ok_or_else(|| LoadError::UnresolvedSymbol(symbol_name.clone()))?)
+			}
+		};
+
+		self.symbol_addrs.insert(symbol.index(), symbol_addr);
+		return Ok(symbol_addr);
+	}
+
+	pub fn add_got_slot(&mut self, symbol_index : SymbolIndex) {
+		// Offsets get filled in once we know how many there are
+		self.got_slots.entry(symbol_index).or_insert(0);
+	}
+
+	pub fn add_plt_entry(&mut self, host_addr : usize) {
+		self.plt_entries.entry(host_addr).or_insert(0);
+	}
+
+	// First pass over the relocations, to resolve every symbol and figure out which GOT slots and PLT entries we need
+	pub fn resolve_relocations<UsesGot, UsesPlt>(&mut self, obj_file : &object::File, section_to_memory_addr : &HashMap<SectionIndex, usize>,
+			builtin_stubs : &[(&str, &[u8])], use_host_symbols : bool, bytes_loaded_into_memory : &mut Vec<u8>, uses_got : UsesGot, uses_plt : UsesPlt) -> Result<(), LoadError>
+		where UsesGot : Fn(&object::Relocation) -> bool, UsesPlt : Fn(&object::Relocation) -> bool {
+		for section in obj_file.sections().filter(|section| should_relocate_section(section) && section_to_memory_addr.contains_key(&section.index())) {
+			for (_, reloc) in section.relocations() {
+				// Sections are already where they're going to be, so there's nothing to resolve
+				let target_symbol_index = match get_reloc_target(&reloc)? {
+					RelocTarget::Symbol(symbol_index) => symbol_index,
+					RelocTarget::Section(_) => { continue; }
+				};
+
+				let target_symbol = obj_file.symbol_by_index(target_symbol_index)
+					.map_err(|_| LoadError::BadObjectFile(format!("bad symbol index {}", target_symbol_index.0)))?;
+				let symbol_addr = self.resolve_symbol(obj_file, &target_symbol, section_to_memory_addr, builtin_stubs, use_host_symbols, bytes_loaded_into_memory)?;
+
+				if uses_got(&reloc) {
+					self.add_got_slot(target_symbol.index());
+				}
+				else if let SymbolAddr::Host(host_addr) = symbol_addr {
+					if uses_plt(&reloc) {
+						self.add_plt_entry(host_addr);
+					}
+				}
+			}
+		}
+
+		return Ok(());
+	}
+
+	// Lays out the GOT and the PLT entries after everything else, the GOT gets filled in by fill_got once we know where the page is.
+	// Each PLT entry is plt_entry_code followed by the 8-byte address it jumps to
+	pub fn append_got_and_plt(&mut self, bytes_loaded_into_memory : &mut Vec<u8>, plt_entry_code : &[u8]) {
+		// Sorted so the layout doesn't change from run to run
+		let mut got_symbols : Vec<SymbolIndex> = self.got_slots.keys().cloned().collect();
+		got_symbols.sort_by_key(|symbol_index| symbol_index.0);
+
+		align_vec(bytes_loaded_into_memory, 8);
+		for symbol_index in got_symbols {
+			self.got_slots.insert(symbol_index, bytes_loaded_into_memory.len());
+			bytes_loaded_into_memory.extend_from_slice(&[0u8; 8]);
+		}
+
+		let mut plt_host_addrs : Vec<usize> = self.plt_entries.keys().cloned().collect();
+		plt_host_addrs.sort();
+
+		align_vec(bytes_loaded_into_memory, 16);
+		for host_addr in plt_host_addrs {
+			self.plt_entries.insert(host_addr, bytes_loaded_into_memory.len());
+			bytes_loaded_into_memory.extend_from_slice(plt_entry_code);
+			bytes_loaded_into_memory.extend_from_slice(&(host_addr as u64).to_le_bytes());
+		}
+	}
+
+	// GOT slots and PLT entries hold absolute addresses, so with either of them the page only works where it got linked
+	pub fn has_absolute_addrs(&self) -> bool {
+		return !self.got_slots.is_empty() || !self.plt_entries.is_empty();
+	}
+
+	pub fn fill_got(&self, exec_page : &mut ExecPage, page_base : usize) {
+		for (symbol_index, got_slot) in self.got_slots.iter() {
+			let symbol_addr = self.symbol_addrs.get(symbol_index).expect("GOT slot for a symbol that never got resolved");
+			exec_page.fix_up_redirect(*got_slot, 64, symbol_addr.get_absolute_addr(page_base) as i64, false);
+		}
+	}
+
+	// Absolute address of whatever a relocation points at. With via_plt, host symbols go to their PLT entry instead since they're probably out of range
+	pub fn get_target_addr(&self, obj_file : &object::File, target : RelocTarget, section_to_memory_addr : &HashMap<SectionIndex, usize>,
+			page_base : usize, via_plt : bool) -> Result<i64, LoadError> {
+		match target {
+			RelocTarget::Symbol(symbol_index) => {
+				let symbol_addr = match *self.symbol_addrs.get(&symbol_index).expect("symbol never got resolved") {
+					SymbolAddr::Host(host_addr) if via_plt => SymbolAddr::InPage(*self.plt_entries.get(&host_addr).expect("host symbol never got a PLT entry")),
+					symbol_addr => symbol_addr
+				};
+				return Ok(symbol_addr.get_absolute_addr(page_base) as i64);
+			}
+			RelocTarget::Section(section_index) => {
+				// Same as the symbols, this is where address 0 in the object would be, so that the section's address lands on where we put it
+				let section = obj_file.section_by_index(section_index).map_err(|_| LoadError::BadObjectFile(format!("bad section index {}", section_index.0)))?;
+				let section_offset_in_memory = section_to_memory_addr.get(&section_index)
+					.ok_or_else(|| LoadError::MissingSection(section.name().unwrap_or("<unnamed>").to_string()))?;
+				return Ok((page_base + section_offset_in_memory) as i64 - section.address() as i64);
+			}
+		}
+	}
+
+	pub fn get_got_slot_addr(&self, target : RelocTarget, page_base : usize) -> Result<i64, LoadError> {
+		match target {
+			RelocTarget::Symbol(symbol_index) => {
+				return Ok((page_base + *self.got_slots.get(&symbol_index).expect("symbol never got a GOT slot")) as i64);
+			}
+			RelocTarget::Section(section_index) => {
+				return Err(LoadError::UnsupportedRelocation(format!("GOT relocation to section {}", section_index.0)));
+			}
+		}
+	}
+}
+
+fn x86_64_reloc_uses_got(reloc : &object::Relocation) -> bool {
+	match reloc.kind() {
+		RelocationKind::GotRelative => true,
+		RelocationKind::Elf(object::elf::R_X86_64_GOTPCRELX) | RelocationKind::Elf(object::elf::R_X86_64_REX_GOTPCRELX) => true,
+		_ => false
+	}
+}
+
+// PC32 (and the Mach-O/COFF equivalents) to a host symbol is almost always a call without @PLT, so it gets the PLT entry too.
+// object leaves Mach-O's SIGNED_1/2/4 alone, but they only differ from SIGNED in the bytes after the displacement
+fn x86_64_reloc_is_pc_relative(reloc : &object::Relocation) -> bool {
+	match reloc.kind() {
+		RelocationKind::Relative | RelocationKind::PltRelative => true,
+		RelocationKind::MachO { value: object::macho::X86_64_RELOC_SIGNED_1 | object::macho::X86_64_RELOC_SIGNED_2 | object::macho::X86_64_RELOC_SIGNED_4, relative: true } => true,
+		_ => false
+	}
+}
+
+// Loads an x86-64 object into a page, resolving everything against the host and filling in the GOT/PLT.
+// Handles PC32/PLT32, GOTPCREL(X)/REX_GOTPCRELX, PC64 and the absolute ones (as long as the page happens to be somewhere they fit),
+// and whatever COFF and Mach-O call those. COFF objects use the MS ABI, so they only get our own stubs and get called as win64
+pub fn link_x86_64(obj_file : &object::File, mut bytes_loaded_into_memory : Vec<u8>, section_to_memory_addr : &HashMap<SectionIndex, usize>,
+		func_offset : usize, data_layout : DataLayout) -> Result<ExecPage, LoadError> {
+	let (builtin_stubs, use_host_symbols, call_conv) = if obj_file.format() == object::BinaryFormat::Coff {
+		(&X86_64_COFF_BUILTIN_STUBS[..], false, CallConv::Win64)
+	}
+	else {
+		(&X86_64_BUILTIN_STUBS[..], true, CallConv::Host)
+	};
+
+	let mut linker = ObjLinker::default();
+	linker.resolve_relocations(obj_file, section_to_memory_addr, builtin_stubs, use_host_symbols, &mut bytes_loaded_into_memory, x86_64_reloc_uses_got, x86_64_reloc_is_pc_relative)?;
+	linker.append_got_and_plt(&mut bytes_loaded_into_memory, &X86_64_PLT_ENTRY_BYTES[..]);
+
+	let mut exec_page = ExecPage::new(bytes_loaded_into_memory.len() / (16*1024) + 1);
+	exec_page.load_with_code(&bytes_loaded_into_memory[..], func_offset);
+	exec_page.data_layout = data_layout;
+	exec_page.call_conv = call_conv;
+	let page_base = exec_page.page.as_ptr() as usize;
+	linker.fill_got(&mut exec_page, page_base);
+
+	exec_page.needs_server_link = linker.has_absolute_addrs() || data_layout.writable_len > 0;
+
+	for section in obj_file.sections().filter(|section| should_relocate_section(section) && section_to_memory_addr.contains_key(&section.index())) {
+		let section_offset_in_memory = *section_to_memory_addr.get(&section.index()).expect("");
+		for (reloc_addr, reloc) in section.relocations() {
+			let target = get_reloc_target(&reloc)?;
+			let target_name = get_reloc_target_name(obj_file, target)?;
+
+			let reloc_insert_offset_in_memory = section_offset_in_memory + reloc_addr as usize;
+			let place = (page_base + reloc_insert_offset_in_memory) as i64;
+
+			// Mach-O relocations straight to a section have the target's address in the object baked in (relative to the place's address, for the PC-relative ones),
+			// so the place's address stands in for the usual -4
+			let mut addend = reloc.addend();
+			if let RelocTarget::Section(_) = target {
+				if x86_64_reloc_is_pc_relative(&reloc) {
+					addend = (section.address() + reloc_addr) as i64;
+				}
+			}
+			if reloc.has_implicit_addend() {
+				addend += read_implicit_addend(&bytes_loaded_into_memory, reloc_insert_offset_in_memory, reloc.size())?;
+			}
+
+			// S + A - P, L + A - P, G + A - P, S + A, etc. going by the psABI names
+			let (value, size_bits, signed) = match reloc.kind() {
+				_ if x86_64_reloc_is_pc_relative(&reloc) => {
+					let target_addr = linker.get_target_addr(obj_file, target, section_to_memory_addr, page_base, reloc.size() == 32)?;
+					(target_addr + addend - place, reloc.size(), true)
+				}
+				RelocationKind::GotRelative | RelocationKind::Elf(object::elf::R_X86_64_GOTPCRELX) | RelocationKind::Elf(object::elf::R_X86_64_REX_GOTPCRELX) => {
+					// NOTE: We could relax these to a lea like a real linker would, but the GOT works just as well
+					(linker.get_got_slot_addr(target, page_base)? + addend - place, 32, true)
+				}
+				RelocationKind::Elf(object::elf::R_X86_64_PC64) => {
+					(linker.get_target_addr(obj_file, target, section_to_memory_addr, page_base, false)? + addend - place, 64, true)
+				}
+				RelocationKind::Absolute => {
+					exec_page.needs_server_link = true;
+					let signed = reloc.encoding() == RelocationEncoding::X86Signed;
+					(linker.get_target_addr(obj_file, target, section_to_memory_addr, page_base, false)? + addend, reloc.size(), signed)
+				}
+				reloc_kind => {
+					return Err(LoadError::UnsupportedRelocation(format!("x86-64 {:?} {:?} to '{}'", obj_file.format(), reloc_kind, target_name)));
+				}
+			};
+
+			check_reloc_value_fits(value, size_bits, signed, &target_name)?;
+			exec_page.fix_up_redirect(reloc_insert_offset_in_memory, size_bits as usize, value, false);
+		}
+	}
+
+	exec_page.make_executable();
+
+	return Ok(exec_page);
+}
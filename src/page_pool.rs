@@ -0,0 +1,258 @@
+// The memory that ExecPages load code into. Each mapping has a guard page on either side, and no part of it is ever writable and executable
+// at the same time: it's all RW while we load and relocate, and then only the writable data stays that way.
+// Mappings get handed back to a per-thread pool when they're dropped instead of being unmapped,
+// since we go through one for every compiled output and mmap'ing fresh ones each time adds up
+
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+
+// Sizes are in these, since that's what macOS on ARM uses (and it's a multiple of everyone else's)
+pub const EXEC_PAGE_SIZE : usize = 16 * 1024;
+
+// Past this, mappings just get unmapped when they're dropped
+const MAX_POOLED_MAPPINGS_PER_THREAD : usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MappingProtection {
+	ReadWrite,
+	ReadOnly,
+	ReadExecute
+}
+
+// The whole reservation (guard pages included), and the usable part in the middle
+#[derive(Debug, Clone, Copy)]
+struct RawMapping {
+	base : *mut u8,
+	total_len : usize,
+	ptr : *mut u8,
+	len : usize
+}
+
+#[cfg(unix)]
+fn get_system_page_size() -> usize {
+	return unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+}
+
+#[cfg(unix)]
+impl RawMapping {
+	fn map(len : usize) -> RawMapping {
+		let guard_len = get_system_page_size();
+		let total_len = len + 2 * guard_len;
+
+		// Everything starts out inaccessible, and then the middle gets opened up
+		let base = unsafe { libc::mmap(std::ptr::null_mut(), total_len, libc::PROT_NONE, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0) };
+		if base == libc::MAP_FAILED {
+			panic!("could not map {} bytes for exec page: {}", total_len, std::io::Error::last_os_error());
+		}
+
+		let base = base as *mut u8;
+		return RawMapping { base: base, total_len: total_len, ptr: unsafe { base.add(guard_len) }, len: len };
+	}
+
+	fn protect(&self, offset : usize, len : usize, protection : MappingProtection) {
+		let prot = match protection {
+			MappingProtection::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+			MappingProtection::ReadOnly => libc::PROT_READ,
+			MappingProtection::ReadExecute => libc::PROT_READ | libc::PROT_EXEC
+		};
+
+		let ret = unsafe { libc::mprotect(self.ptr.add(offset) as *mut libc::c_void, len, prot) };
+		if ret != 0 {
+			panic!("could not mprotect exec page to {:?}: {}", protection, std::io::Error::last_os_error());
+		}
+	}
+
+	fn unmap(self) {
+		unsafe { libc::munmap(self.base as *mut libc::c_void, self.total_len); }
+	}
+}
+
+#[cfg(windows)]
+fn get_system_page_size() -> usize {
+	let mut system_info : winapi::um::sysinfoapi::SYSTEM_INFO = unsafe { std::mem::zeroed() };
+	unsafe { winapi::um::sysinfoapi::GetSystemInfo(&mut system_info); }
+	return system_info.dwPageSize as usize;
+}
+
+#[cfg(windows)]
+impl RawMapping {
+	fn map(len : usize) -> RawMapping {
+		use winapi::um::memoryapi::VirtualAlloc;
+		use winapi::um::winnt::{MEM_RESERVE, MEM_COMMIT, PAGE_NOACCESS, PAGE_READWRITE};
+
+		let guard_len = get_system_page_size();
+		let total_len = len + 2 * guard_len;
+
+		// Reserve the whole thing, and only commit the middle so the guard pages stay inaccessible
+		let base = unsafe { VirtualAlloc(std::ptr::null_mut(), total_len, MEM_RESERVE, PAGE_NOACCESS) } as *mut u8;
+		if base.is_null() {
+			panic!("could not reserve {} bytes for exec page: {}", total_len, std::io::Error::last_os_error());
+		}
+
+		let ptr = unsafe { VirtualAlloc(base.add(guard_len) as *mut _, len, MEM_COMMIT, PAGE_READWRITE) } as *mut u8;
+		if ptr.is_null() {
+			panic!("could not commit {} bytes for exec page: {}", len, std::io::Error::last_os_error());
+		}
+
+		return RawMapping { base: base, total_len: total_len, ptr: ptr, len: len };
+	}
+
+	fn protect(&self, offset : usize, len : usize, protection : MappingProtection) {
+		use winapi::um::memoryapi::VirtualProtect;
+		use winapi::um::winnt::{PAGE_READWRITE, PAGE_READONLY, PAGE_EXECUTE_READ};
+
+		let new_protect = match protection {
+			MappingProtection::ReadWrite => PAGE_READWRITE,
+			MappingProtection::ReadOnly => PAGE_READONLY,
+			MappingProtection::ReadExecute => PAGE_EXECUTE_READ
+		};
+
+		let mut old_protect = 0;
+		let ret = unsafe { VirtualProtect(self.ptr.add(offset) as *mut _, len, new_protect, &mut old_protect) };
+		if ret == 0 {
+			panic!("could not VirtualProtect exec page to {:?}: {}", protection, std::io::Error::last_os_error());
+		}
+	}
+
+	fn unmap(self) {
+		use winapi::um::memoryapi::VirtualFree;
+		use winapi::um::winnt::MEM_RELEASE;
+
+		unsafe { VirtualFree(self.base as *mut _, 0, MEM_RELEASE); }
+	}
+}
+
+// Unmaps whatever's left in the pool when the thread exits
+struct MappingPool {
+	mappings : Vec<RawMapping>
+}
+
+impl Drop for MappingPool {
+	fn drop(&mut self) {
+		for mapping in self.mappings.drain(..) {
+			mapping.unmap();
+		}
+	}
+}
+
+thread_local! {
+	static MAPPING_POOL : RefCell<MappingPool> = RefCell::new(MappingPool { mappings: Vec::new() });
+}
+
+#[derive(Debug)]
+pub struct ExecMapping {
+	raw : RawMapping,
+	protection : MappingProtection
+}
+
+// The pointers are only ever used by whoever owns the mapping
+unsafe impl Send for ExecMapping {}
+
+impl ExecMapping {
+	// Zeroed and writable, from the pool if there's one big enough
+	pub fn new(num_pages : usize) -> ExecMapping {
+		let system_page_size = get_system_page_size();
+		let len = ((num_pages.max(1) * EXEC_PAGE_SIZE + system_page_size - 1) / system_page_size) * system_page_size;
+
+		// Smallest one that fits, so one big output doesn't end up getting used for every small one after it
+		let pooled_mapping = MAPPING_POOL.with(|pool| {
+			let mut pool = pool.borrow_mut();
+			let best_index = pool.mappings.iter().enumerate()
+				.filter(|(_, mapping)| mapping.len >= len)
+				.min_by_key(|(_, mapping)| mapping.len)
+				.map(|(index, _)| index);
+			best_index.map(|index| pool.mappings.swap_remove(index))
+		});
+
+		let raw = match pooled_mapping {
+			Some(raw) => {
+				raw.protect(0, raw.len, MappingProtection::ReadWrite);
+				// Nothing from whatever ran in here last gets to leak into the next one
+				unsafe { std::ptr::write_bytes(raw.ptr, 0, raw.len); }
+				raw
+			}
+			None => {
+				let raw = RawMapping::map(len);
+				raw.protect(0, raw.len, MappingProtection::ReadWrite);
+				raw
+			}
+		};
+
+		return ExecMapping { raw: raw, protection: MappingProtection::ReadWrite };
+	}
+
+	pub fn make_writable(&mut self) {
+		if self.protection != MappingProtection::ReadWrite {
+			self.raw.protect(0, self.raw.len, MappingProtection::ReadWrite);
+			self.protection = MappingProtection::ReadWrite;
+		}
+	}
+
+	// The first writable_len bytes stay RW, the next read_only_len become read-only, and everything after that is the code.
+	// Both have to be multiples of EXEC_PAGE_SIZE, since that's the granularity the protection works at
+	pub fn make_executable(&mut self, writable_len : usize, read_only_len : usize) {
+		assert!(writable_len % EXEC_PAGE_SIZE == 0 && read_only_len % EXEC_PAGE_SIZE == 0);
+		assert!(writable_len + read_only_len <= self.raw.len);
+
+		if self.protection != MappingProtection::ReadExecute {
+			if read_only_len > 0 {
+				self.raw.protect(writable_len, read_only_len, MappingProtection::ReadOnly);
+			}
+			let code_offset = writable_len + read_only_len;
+			if code_offset < self.raw.len {
+				self.raw.protect(code_offset, self.raw.len - code_offset, MappingProtection::ReadExecute);
+			}
+			self.protection = MappingProtection::ReadExecute;
+		}
+	}
+
+	pub fn is_executable(&self) -> bool {
+		return self.protection == MappingProtection::ReadExecute;
+	}
+
+	pub fn as_ptr(&self) -> *const u8 {
+		return self.raw.ptr;
+	}
+
+	pub fn as_mut_ptr(&mut self) -> *mut u8 {
+		return self.raw.ptr;
+	}
+
+	pub fn len(&self) -> usize {
+		return self.raw.len;
+	}
+}
+
+impl Deref for ExecMapping {
+	type Target = [u8];
+	fn deref(&self) -> &[u8] {
+		return unsafe { std::slice::from_raw_parts(self.raw.ptr, self.raw.len) };
+	}
+}
+
+// NOTE: Only safe to actually write through while it's writable, otherwise it'll fault (which is kinda the point)
+impl DerefMut for ExecMapping {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		return unsafe { std::slice::from_raw_parts_mut(self.raw.ptr, self.raw.len) };
+	}
+}
+
+impl Drop for ExecMapping {
+	fn drop(&mut self) {
+		let raw = self.raw;
+
+		// try_with since this could be getting dropped while the thread's going away
+		let was_pooled = MAPPING_POOL.try_with(|pool| {
+			let mut pool = pool.borrow_mut();
+			if pool.mappings.len() < MAX_POOLED_MAPPINGS_PER_THREAD {
+				pool.mappings.push(raw);
+				return true;
+			}
+			return false;
+		}).unwrap_or(false);
+
+		if !was_pooled {
+			raw.unmap();
+		}
+	}
+}
@@ -0,0 +1,175 @@
+
+use std::time::{Duration, Instant};
+
+use crate::compilation_config::{TestCompilation, CompiledCodeOutput, CompilerIOThreadHandle};
+use crate::saved_findings::{SavedFinding, FindingCategory};
+use crate::compiler_bisect::get_finding_category_for_compilations;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegressionStatus {
+	StillReproduces,
+	Fixed,
+	// Still broken, but in a different way (e.g. a crash that now turns into a runtime diff)
+	ChangedCategory(FindingCategory),
+	// Couldn't be run at all (e.g. a runtime diff with no idea which fuzzer found it)
+	Skipped(String)
+}
+
+impl RegressionStatus {
+	pub fn name(&self) -> &'static str {
+		match self {
+			RegressionStatus::StillReproduces => "still_reproduces",
+			RegressionStatus::Fixed => "fixed",
+			RegressionStatus::ChangedCategory(_) => "changed_category",
+			RegressionStatus::Skipped(_) => "skipped"
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct RegressionResult {
+	pub category : FindingCategory,
+	pub hash : String,
+	pub status : RegressionStatus,
+	pub duration : Duration
+}
+
+impl RegressionResult {
+	pub fn is_failure(&self) -> bool {
+		match self.status {
+			RegressionStatus::StillReproduces | RegressionStatus::ChangedCategory(_) => true,
+			RegressionStatus::Fixed | RegressionStatus::Skipped(_) => false
+		}
+	}
+}
+
+// get_runtime_diff_check returns None if we don't know how to execute the finding, in which case it gets skipped
+pub fn run_regression_suite<F>(findings : &Vec<SavedFinding>, compilation_tests : &Vec<TestCompilation>, io_thread_handle : &CompilerIOThreadHandle,
+		get_runtime_diff_check : F) -> Vec<RegressionResult>
+	where F : Fn(&SavedFinding) -> Option<Box<dyn Fn(&Vec<CompiledCodeOutput>) -> bool>> {
+
+	let mut results = Vec::<RegressionResult>::new();
+	for (ii, finding) in findings.iter().enumerate() {
+		let start_time = Instant::now();
+
+		let status = match get_runtime_diff_check(finding) {
+			Some(runtime_diff_check) => {
+				match get_finding_category_for_compilations(&finding.code, compilation_tests, io_thread_handle, &*runtime_diff_check) {
+					Some(category) if category == finding.category => RegressionStatus::StillReproduces,
+					Some(category) => RegressionStatus::ChangedCategory(category),
+					None => RegressionStatus::Fixed
+				}
+			}
+			None => RegressionStatus::Skipped("runtime diff is missing its input/meta, or its fuzzer is unknown".to_string())
+		};
+
+		print!("  [{}/{}] {}/{} -> {}\n", ii + 1, findings.len(), finding.category.dir_name(), finding.hash, status.name());
+
+		results.push(RegressionResult {
+			category: finding.category,
+			hash: finding.hash.clone(),
+			status: status,
+			duration: start_time.elapsed()
+		});
+	}
+
+	return results;
+}
+
+pub fn regression_results_to_json(results : &Vec<RegressionResult>) -> serde_json::Value {
+	let mut results_json = Vec::<serde_json::Value>::new();
+	for result in results {
+		let mut result_json = serde_json::json!({
+			"category": result.category.dir_name(),
+			"hash": result.hash,
+			"status": result.status.name(),
+			"duration_seconds": result.duration.as_secs_f64()
+		});
+
+		match &result.status {
+			RegressionStatus::ChangedCategory(new_category) => { result_json["new_category"] = serde_json::json!(new_category.dir_name()); }
+			RegressionStatus::Skipped(reason) => { result_json["reason"] = serde_json::json!(reason); }
+			_ => {}
+		}
+
+		results_json.push(result_json);
+	}
+
+	let count_status = |name : &str| results.iter().filter(|result| result.status.name() == name).count();
+
+	return serde_json::json!({
+		"total": results.len(),
+		"still_reproduces": count_status("still_reproduces"),
+		"fixed": count_status("fixed"),
+		"changed_category": count_status("changed_category"),
+		"skipped": count_status("skipped"),
+		"results": results_json
+	});
+}
+
+fn escape_xml(text : &str) -> String {
+	return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;");
+}
+
+// Each finding is a test case that passes once the compiler is fixed, so a finding that still reproduces is a failure
+pub fn regression_results_to_junit(results : &Vec<RegressionResult>) -> String {
+	let num_failures = results.iter().filter(|result| result.is_failure()).count();
+	let num_skipped = results.iter().filter(|result| matches!(result.status, RegressionStatus::Skipped(_))).count();
+	let total_time : f64 = results.iter().map(|result| result.duration.as_secs_f64()).sum();
+
+	let mut junit = String::new();
+	junit.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	junit.push_str(&format!("<testsuite name=\"fuzz_issues\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+		results.len(), num_failures, num_skipped, total_time));
+
+	for result in results {
+		junit.push_str(&format!("  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+			escape_xml(result.category.dir_name()), escape_xml(&result.hash), result.duration.as_secs_f64()));
+
+		match &result.status {
+			RegressionStatus::Fixed => {
+				junit.push_str("/>\n");
+			}
+			RegressionStatus::StillReproduces => {
+				junit.push_str(">\n");
+				junit.push_str(&format!("    <failure message=\"still reproduces as {}\"/>\n", escape_xml(result.category.dir_name())));
+				junit.push_str("  </testcase>\n");
+			}
+			RegressionStatus::ChangedCategory(new_category) => {
+				junit.push_str(">\n");
+				junit.push_str(&format!("    <failure message=\"changed from {} to {}\"/>\n", escape_xml(result.category.dir_name()), escape_xml(new_category.dir_name())));
+				junit.push_str("  </testcase>\n");
+			}
+			RegressionStatus::Skipped(reason) => {
+				junit.push_str(">\n");
+				junit.push_str(&format!("    <skipped message=\"{}\"/>\n", escape_xml(reason)));
+				junit.push_str("  </testcase>\n");
+			}
+		}
+	}
+
+	junit.push_str("</testsuite>\n");
+	return junit;
+}
+
+#[test]
+fn test_regression_results_output() {
+	let results = vec![
+		RegressionResult { category: FindingCategory::CompilerFailure, hash: "aaa".to_string(), status: RegressionStatus::Fixed, duration: Duration::from_millis(1500) },
+		RegressionResult { category: FindingCategory::RuntimeDiff, hash: "bbb".to_string(), status: RegressionStatus::StillReproduces, duration: Duration::from_millis(250) }
+	];
+
+	let results_json = regression_results_to_json(&results);
+	assert_eq!(results_json["total"], 2);
+	assert_eq!(results_json["fixed"], 1);
+	assert_eq!(results_json["still_reproduces"], 1);
+	assert_eq!(results_json["results"][0]["hash"], "aaa");
+	assert_eq!(results_json["results"][1]["status"], "still_reproduces");
+	assert_eq!(results_json["results"][1]["category"], "runtime_diffs");
+
+	let junit = regression_results_to_junit(&results);
+	assert!(junit.contains("<testsuite name=\"fuzz_issues\" tests=\"2\" failures=\"1\" skipped=\"0\" time=\"1.750\">"));
+	assert!(junit.contains("<testcase classname=\"compiler_fails\" name=\"aaa\" time=\"1.500\"/>"));
+	assert!(junit.contains("<testcase classname=\"runtime_diffs\" name=\"bbb\" time=\"0.250\">\n    <failure message=\"still reproduces as runtime_diffs\"/>\n  </testcase>"));
+	assert!(junit.ends_with("</testsuite>\n"));
+}
@@ -0,0 +1,88 @@
+// Runtime diffs aren't always the compiler's fault: if the generated code does something undefined (signed overflow, reading past the inputs, etc.)
+// then every compiler is allowed to do whatever it wants with it, and they'll happily disagree. So before calling a runtime diff a compiler bug,
+// we build it with the config's sanitizer_compilation (an executable with -fsanitize=undefined,address) and run the saved input through it
+
+use crate::compilation_config::{test_generated_code_compilation, expand_placeholders, PlaceholderValues, TestCompilation, GenCodeResult, CompilerIOThreadHandle};
+use crate::exec_mem::ExternalCode;
+use crate::generated_exe::ExeRunError;
+use crate::saved_findings::{FindingCategory, move_finding_to_category};
+
+pub enum SanitizerCheckResult {
+	// Has the sanitizer's report
+	GeneratorUB(String),
+	Clean,
+	// The sanitizer build failed, or it died some other way, so we can't say either way
+	CouldNotCheck(String)
+}
+
+// The sanitizer compilation goes after the config's compilations, so its files don't collide with theirs
+pub fn expand_sanitizer_placeholders(compilation_tests : &Vec<TestCompilation>, sanitizer_compilation : &TestCompilation, values : &PlaceholderValues) -> TestCompilation {
+	let mut all_compilation_tests = compilation_tests.clone();
+	all_compilation_tests.push(sanitizer_compilation.clone());
+	return expand_placeholders(&all_compilation_tests, values).pop().expect("");
+}
+
+// ASan ends its reports with 'SUMMARY: AddressSanitizer: ...', but UBSan with -fno-sanitize-recover just prints the 'runtime error:' line and exits
+fn is_sanitizer_report(stderr : &str) -> bool {
+	return stderr.lines().any(|line| (line.starts_with("SUMMARY: ") && line.contains("Sanitizer")) || line.contains(": runtime error: "));
+}
+
+// sanitizer_compilation should already have its placeholders expanded, and input_str is the finding's saved input
+pub fn check_for_generator_ub(code : &str, input_str : &str, sanitizer_compilation : &TestCompilation, io_thread_handle : &CompilerIOThreadHandle) -> SanitizerCheckResult {
+	let compiled_outputs = match test_generated_code_compilation(code, &vec![sanitizer_compilation.clone()], io_thread_handle) {
+		GenCodeResult::Success(compiled_outputs) => compiled_outputs,
+		GenCodeResult::CompilerFailure(err_code, _, stderr) => {
+			return SanitizerCheckResult::CouldNotCheck(format!("sanitizer build failed with {}: {}", err_code, stderr));
+		}
+		GenCodeResult::CompilerTimeout => {
+			return SanitizerCheckResult::CouldNotCheck("sanitizer build timed out".to_string());
+		}
+		GenCodeResult::ObjectLoadFailure(load_error, _) => {
+			return SanitizerCheckResult::CouldNotCheck(format!("sanitizer build has no executable: {}", load_error));
+		}
+		GenCodeResult::RuntimeDiff(_) => {
+			panic!("compiling can't give back a runtime diff");
+		}
+	};
+
+	let exe = match compiled_outputs[0].code_page.get_external_code() {
+		Some(ExternalCode::Executable(exe)) => exe,
+		_ => panic!("sanitizer compilation didn't give back an executable")
+	};
+
+	// Recovering sanitizers (or anything that slipped past -fno-sanitize-recover) still print their report, they just exit 0
+	match exe.run_with_stderr(input_str) {
+		Ok((_, stderr)) if is_sanitizer_report(&stderr) => SanitizerCheckResult::GeneratorUB(stderr),
+		Ok(_) => SanitizerCheckResult::Clean,
+		Err(ExeRunError::Failed(_, stderr)) if is_sanitizer_report(&stderr) => SanitizerCheckResult::GeneratorUB(stderr),
+		Err(err) => SanitizerCheckResult::CouldNotCheck(format!("sanitizer build {}", err))
+	}
+}
+
+// For a runtime diff that's already been saved out: if the sanitizer fires, it gets moved over to generator_ub along with the report.
+// Gives back whichever category it ends up in
+pub fn reclassify_runtime_diff(issues_dir : &str, hash : &str, code : &str, input_str : &str, sanitizer_compilation : &TestCompilation,
+		io_thread_handle : &CompilerIOThreadHandle) -> FindingCategory {
+	match check_for_generator_ub(code, input_str, sanitizer_compilation, io_thread_handle) {
+		SanitizerCheckResult::GeneratorUB(report) => {
+			let ub_dir = move_finding_to_category(issues_dir, hash, FindingCategory::RuntimeDiff, FindingCategory::GeneratorUB).expect("could not move finding to generator_ub");
+			std::fs::write(ub_dir.join(format!("{}_sanitizer.txt", hash)), report).expect("couldn't write to file?");
+			print!("Runtime diff {} is UB in the generated code, moved it to '{}'\n", hash, ub_dir.display());
+			return FindingCategory::GeneratorUB;
+		}
+		SanitizerCheckResult::Clean => {
+			return FindingCategory::RuntimeDiff;
+		}
+		SanitizerCheckResult::CouldNotCheck(err) => {
+			print!("Could not check runtime diff {} for UB, leaving it as is: {}\n", hash, err);
+			return FindingCategory::RuntimeDiff;
+		}
+	}
+}
+
+#[test]
+fn test_is_sanitizer_report() {
+	assert!(is_sanitizer_report("tmp/main_c1.cpp:12:20: runtime error: signed integer overflow: 2147483647 + 1 cannot be represented in type 'int'\n"));
+	assert!(is_sanitizer_report("==123==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000020\nSUMMARY: AddressSanitizer: heap-buffer-overflow tmp/main_c1.cpp:8 in do_stuff\n"));
+	assert!(!is_sanitizer_report("Segmentation fault\n"));
+}
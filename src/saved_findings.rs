@@ -0,0 +1,197 @@
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::compilation_config::GenCodeResult;
+
+// The fuzz_issues directory is laid out as fuzz_issues/[category]/[hash]_[artifact],
+// where every finding has at least a _orig.cpp and _min.cpp, and runtime diffs also have the input and meta.
+// Object load failures have the object file we couldn't load (_orig.o, from the original code) and the error.
+// Generator UB findings are runtime diffs that set off the sanitizers, so they have a runtime diff's files plus the _sanitizer.txt report
+pub const FUZZ_ISSUES_DIR : &str = "fuzz_issues";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindingCategory {
+	CompilerTimeout,
+	CompilerFailure,
+	RuntimeDiff,
+	ObjectLoadFailure,
+	// Not a compiler bug, the generated code had UB (see sanitizer_check)
+	GeneratorUB
+}
+
+impl FindingCategory {
+	pub fn dir_name(&self) -> &'static str {
+		match self {
+			FindingCategory::CompilerTimeout => "compiler_timeouts",
+			FindingCategory::CompilerFailure => "compiler_fails",
+			FindingCategory::RuntimeDiff => "runtime_diffs",
+			FindingCategory::ObjectLoadFailure => "obj_load_fails",
+			FindingCategory::GeneratorUB => "generator_ub"
+		}
+	}
+
+	pub fn from_dir_name(dir_name : &str) -> Option<FindingCategory> {
+		match dir_name {
+			"compiler_timeouts" => Some(FindingCategory::CompilerTimeout),
+			"compiler_fails" => Some(FindingCategory::CompilerFailure),
+			"runtime_diffs" => Some(FindingCategory::RuntimeDiff),
+			"obj_load_fails" => Some(FindingCategory::ObjectLoadFailure),
+			"generator_ub" => Some(FindingCategory::GeneratorUB),
+			_ => None
+		}
+	}
+
+	// Success doesn't map to a category, since it's not a finding. Neither does generator UB, that only comes from re-checking a runtime diff
+	pub fn from_result(result : &GenCodeResult) -> Option<FindingCategory> {
+		match result {
+			GenCodeResult::CompilerTimeout => Some(FindingCategory::CompilerTimeout),
+			GenCodeResult::CompilerFailure(_,_,_) => Some(FindingCategory::CompilerFailure),
+			GenCodeResult::RuntimeDiff(_) => Some(FindingCategory::RuntimeDiff),
+			GenCodeResult::ObjectLoadFailure(_,_) => Some(FindingCategory::ObjectLoadFailure),
+			GenCodeResult::Success(_) => None
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedFinding {
+	pub category : FindingCategory,
+	pub hash : String,
+	pub dir : PathBuf,
+	pub code : String,
+	pub input : Option<String>,
+	pub meta : Option<String>,
+	// Which fuzzer found it, if it was saved with an _info.json (older findings won't have one)
+	pub fuzzer : Option<String>
+}
+
+impl SavedFinding {
+	// Takes the path to any of the finding's files (usually [hash]_min.cpp),
+	// and figures out the rest from the directory it's in and the hash prefix
+	pub fn load(finding_filename : &str) -> Result<SavedFinding, String> {
+		let finding_path = Path::new(finding_filename);
+
+		let dir = finding_path.parent().ok_or_else(|| format!("finding '{}' has no parent directory", finding_filename))?;
+		let dir_name = dir.file_name().and_then(|name| name.to_str()).unwrap_or("");
+		let category = FindingCategory::from_dir_name(dir_name)
+			.ok_or_else(|| format!("finding '{}' is not in a known category directory (got '{}')", finding_filename, dir_name))?;
+
+		let file_name = finding_path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+		let hash = file_name.split('_').next().unwrap_or("").to_string();
+		if hash.is_empty() {
+			return Err(format!("could not get hash from finding filename '{}'", finding_filename));
+		}
+
+		let mut finding = SavedFinding {
+			category: category,
+			hash: hash,
+			dir: dir.to_path_buf(),
+			code: String::new(),
+			input: None,
+			meta: None,
+			fuzzer: None
+		};
+
+		// Prefer the minimized code, but fall back to whatever was passed in
+		let min_code_filename = finding.artifact_filename("min.cpp");
+		let code_filename = if min_code_filename.exists() { min_code_filename } else { finding_path.to_path_buf() };
+		finding.code = std::fs::read_to_string(&code_filename).map_err(|err| format!("could not read '{}': {}", code_filename.display(), err))?;
+
+		if category == FindingCategory::RuntimeDiff || category == FindingCategory::GeneratorUB {
+			finding.input = std::fs::read_to_string(finding.artifact_filename("input.input")).ok();
+			finding.meta = std::fs::read_to_string(finding.artifact_filename("min_meta.meta")).ok();
+		}
+
+		if let Ok(info_contents) = std::fs::read_to_string(finding.artifact_filename("info.json")) {
+			let info_json : serde_json::Value = serde_json::from_str(&info_contents).map_err(|err| format!("bad info file for finding '{}': {}", finding.hash, err))?;
+			finding.fuzzer = info_json["fuzzer"].as_str().map(|fuzzer| fuzzer.to_string());
+		}
+
+		return Ok(finding);
+	}
+
+	pub fn artifact_filename(&self, suffix : &str) -> PathBuf {
+		self.dir.join(format!("{}_{}", self.hash, suffix))
+	}
+}
+
+// Moves all of a finding's files over to another category's directory, and gives back that directory
+pub fn move_finding_to_category(issues_dir : &str, hash : &str, from_category : FindingCategory, to_category : FindingCategory) -> std::io::Result<PathBuf> {
+	let from_dir = Path::new(issues_dir).join(from_category.dir_name());
+	let to_dir = Path::new(issues_dir).join(to_category.dir_name());
+	std::fs::create_dir_all(&to_dir)?;
+
+	let file_prefix = format!("{}_", hash);
+	for entry in std::fs::read_dir(&from_dir)? {
+		let file_name = entry?.file_name();
+		if file_name.to_string_lossy().starts_with(&file_prefix) {
+			std::fs::rename(from_dir.join(&file_name), to_dir.join(&file_name))?;
+		}
+	}
+
+	return Ok(to_dir);
+}
+
+// Returns the _min.cpp file for every finding under issues_dir, or its _orig.cpp if it never got minimized (e.g. one saved by hand).
+// Sorted so reports are stable across runs. Generator UB isn't included, since there's no compiler bug there to check up on
+pub fn list_saved_findings(issues_dir : &str) -> Vec<String> {
+	let mut finding_filenames = Vec::<String>::new();
+	for category in [FindingCategory::CompilerTimeout, FindingCategory::CompilerFailure, FindingCategory::RuntimeDiff, FindingCategory::ObjectLoadFailure] {
+		let category_dir = Path::new(issues_dir).join(category.dir_name());
+
+		// Hash -> (has a _min.cpp, has an _orig.cpp)
+		let mut code_files = BTreeMap::<String, (bool, bool)>::new();
+		if let Ok(dir_entries) = std::fs::read_dir(&category_dir) {
+			for entry in dir_entries {
+				let file_name = entry.expect("could not read fuzz issues directory entry").file_name().to_string_lossy().to_string();
+				if let Some((hash, artifact)) = file_name.split_once('_') {
+					let (has_min, has_orig) = code_files.entry(hash.to_string()).or_insert((false, false));
+					*has_min |= artifact == "min.cpp";
+					*has_orig |= artifact == "orig.cpp";
+				}
+			}
+		}
+
+		for (hash, (has_min, has_orig)) in code_files {
+			if has_min || has_orig {
+				let code_filename = category_dir.join(format!("{}_{}", hash, if has_min { "min.cpp" } else { "orig.cpp" }));
+				finding_filenames.push(code_filename.to_string_lossy().to_string());
+			}
+			else {
+				print!("Skipping finding {}/{}, it doesn't have a _min.cpp or _orig.cpp\n", category.dir_name(), hash);
+			}
+		}
+	}
+
+	finding_filenames.sort();
+	return finding_filenames;
+}
+
+#[test]
+fn test_list_saved_findings() {
+	use crate::compilation_config::RunTmpDir;
+
+	let issues_dir = RunTmpDir::create("test_saved_findings");
+	let write_artifact = |category : FindingCategory, file_name : &str| {
+		let category_dir = issues_dir.path.join(category.dir_name());
+		std::fs::create_dir_all(&category_dir).expect("");
+		std::fs::write(category_dir.join(file_name), "").expect("");
+	};
+
+	write_artifact(FindingCategory::CompilerFailure, "aaa_orig.cpp");
+	write_artifact(FindingCategory::CompilerFailure, "aaa_min.cpp");
+	// Never minimized, so it only has the original
+	write_artifact(FindingCategory::RuntimeDiff, "bbb_orig.cpp");
+	write_artifact(FindingCategory::RuntimeDiff, "bbb_input.input");
+	// No code at all, so there's nothing to run
+	write_artifact(FindingCategory::ObjectLoadFailure, "ccc_orig.o");
+	write_artifact(FindingCategory::GeneratorUB, "ddd_min.cpp");
+
+	let issues_dir_str = issues_dir.path.to_string_lossy().to_string();
+	let expected_filenames : Vec<String> = vec![
+		issues_dir.path.join("compiler_fails/aaa_min.cpp"),
+		issues_dir.path.join("runtime_diffs/bbb_orig.cpp")
+	].iter().map(|path| path.to_string_lossy().to_string()).collect();
+	assert_eq!(list_saved_findings(&issues_dir_str), expected_filenames);
+}
@@ -0,0 +1,462 @@
+// The other way of running compiled code: instead of us loading and relocating an object, the compile gets linked into a shared library,
+// and a worker process (this same exe, run as 'so-worker') dlopens it and calls do_stuff for us. It's a good bit slower than the
+// in-process loader, but the real dynamic linker does all the work, so the code can call into libm, use TLS, etc.
+// Each fuzzer thread gets its own worker, started the first time it needs one
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::aligned_slice::AlignedSlice;
+use crate::exec_mem::{ExternalCall, ExecCrash};
+use crate::parse_exe::LoadError;
+
+// What the worker gets run as, see main()
+pub const SHARED_LIB_WORKER_METHOD : &str = "so-worker";
+
+// What the arguments get copied into on the worker side, enough for anything the fuzzers load with SIMD instructions
+const WORKER_ARG_ALIGNMENT : usize = 64;
+
+// | msg length, incl. type (4 bytes, be) | type (1 byte) | payload |
+// Every message gets a reply, | msg length, incl. status (4 bytes, be) | status (1 byte) | payload |, where the payload is the error on failure
+const MSG_TYPE_LOAD : u8 = 0x01; // | lib id (4 bytes, be) | path to the shared lib (utf-8) |
+const MSG_TYPE_CALL : u8 = 0x02; // | lib id (4 bytes, be) | call kind (1 byte) | output len (4 bytes, be) | num args (4 bytes, be) | for each arg, len (4 bytes, be) + bytes |
+const MSG_TYPE_UNLOAD : u8 = 0x03; // | lib id (4 bytes, be) |
+
+const REPLY_STATUS_OK : u8 = 0x00;
+const REPLY_STATUS_ERROR : u8 = 0x01;
+
+// How the call kinds go over the pipe
+impl ExternalCall {
+	fn to_byte(&self) -> u8 {
+		match self {
+			ExternalCall::U32Io => 0,
+			ExternalCall::U64Io => 1,
+			ExternalCall::Args128i => 2,
+			ExternalCall::Args256i => 3
+		}
+	}
+
+	fn from_byte(byte : u8) -> Option<ExternalCall> {
+		match byte {
+			0 => Some(ExternalCall::U32Io),
+			1 => Some(ExternalCall::U64Io),
+			2 => Some(ExternalCall::Args128i),
+			3 => Some(ExternalCall::Args256i),
+			_ => None
+		}
+	}
+}
+
+fn write_msg(writer : &mut impl Write, msg_type : u8, payload : &[u8]) -> std::io::Result<()> {
+	writer.write_all(&((payload.len() + 1) as u32).to_be_bytes())?;
+	writer.write_all(&[msg_type])?;
+	writer.write_all(payload)?;
+	writer.flush()?;
+	return Ok(());
+}
+
+// None on a clean EOF, i.e. the other side is done with us
+fn read_msg(reader : &mut impl Read) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+	let mut msg_len_bytes = [0u8; 4];
+	match reader.read_exact(&mut msg_len_bytes) {
+		Ok(()) => {}
+		Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => { return Ok(None); }
+		Err(err) => { return Err(err); }
+	}
+
+	let msg_len = u32::from_be_bytes(msg_len_bytes) as usize;
+	if msg_len == 0 {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "empty message from shared lib worker"));
+	}
+
+	let mut msg = vec![0u8; msg_len];
+	reader.read_exact(&mut msg[..])?;
+	let payload = msg.split_off(1);
+	return Ok(Some((msg[0], payload)));
+}
+
+// Pulls the big-endian u32's and length-prefixed byte arrays back out of a message
+struct MsgReader<'a> {
+	bytes : &'a [u8]
+}
+
+impl<'a> MsgReader<'a> {
+	fn read_bytes(&mut self, len : usize) -> Result<&'a [u8], String> {
+		if self.bytes.len() < len {
+			return Err(format!("message cut off, wanted {} more bytes but only {} left", len, self.bytes.len()));
+		}
+
+		let (read_bytes, rest) = self.bytes.split_at(len);
+		self.bytes = rest;
+		return Ok(read_bytes);
+	}
+
+	fn read_u8(&mut self) -> Result<u8, String> {
+		return Ok(self.read_bytes(1)?[0]);
+	}
+
+	fn read_u32(&mut self) -> Result<u32, String> {
+		let mut u32_bytes = [0u8; 4];
+		u32_bytes.copy_from_slice(self.read_bytes(4)?);
+		return Ok(u32::from_be_bytes(u32_bytes));
+	}
+}
+
+//---------------------------------------------------------------------------------------
+// The fuzzer's side
+
+struct SharedLibWorker {
+	child : Child,
+	stdin : ChildStdin,
+	stdout : ChildStdout,
+	next_lib_id : u32
+}
+
+impl SharedLibWorker {
+	fn spawn() -> std::io::Result<SharedLibWorker> {
+		let mut command = Command::new(std::env::current_exe()?);
+		command.arg(SHARED_LIB_WORKER_METHOD)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::inherit());
+
+		// Same as the compilers, a Ctrl-C should let us finish the current case instead of taking the worker out from under us
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::CommandExt;
+			command.process_group(0);
+		}
+
+		let mut child = command.spawn()?;
+		let stdin = child.stdin.take().expect("");
+		let stdout = child.stdout.take().expect("");
+		return Ok(SharedLibWorker { child: child, stdin: stdin, stdout: stdout, next_lib_id: 0 });
+	}
+
+	fn send(&mut self, msg_type : u8, payload : &[u8]) -> std::io::Result<Result<Vec<u8>, String>> {
+		write_msg(&mut self.stdin, msg_type, payload)?;
+		match read_msg(&mut self.stdout)? {
+			Some((REPLY_STATUS_OK, reply)) => Ok(Ok(reply)),
+			Some((_, reply)) => Ok(Err(String::from_utf8_lossy(&reply).to_string())),
+			None => Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "shared lib worker went away"))
+		}
+	}
+
+	// Describes how it died, if it did
+	fn get_exit_status(&mut self) -> String {
+		match self.child.try_wait() {
+			Ok(Some(status)) => format!("{}", status),
+			_ => "still running".to_string()
+		}
+	}
+}
+
+impl Drop for SharedLibWorker {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+thread_local! {
+	static SHARED_LIB_WORKER : RefCell<Option<SharedLibWorker>> = RefCell::new(None);
+}
+
+// Runs f on this thread's worker, starting one up if there isn't one. If talking to it fails it's assumed dead,
+// and the next call gets a fresh one
+fn with_worker<R>(f : impl FnOnce(&mut SharedLibWorker) -> std::io::Result<R>) -> Result<R, String> {
+	return SHARED_LIB_WORKER.with(|worker| {
+		let mut worker = worker.borrow_mut();
+		if worker.is_none() {
+			*worker = Some(SharedLibWorker::spawn().map_err(|err| format!("could not start shared lib worker: {}", err))?);
+		}
+
+		let result = f(worker.as_mut().expect(""));
+		return result.map_err(|err| {
+			let exit_status = worker.as_mut().expect("").get_exit_status();
+			*worker = None;
+			format!("lost shared lib worker ({}): {}", exit_status, err)
+		});
+	});
+}
+
+// A compiled shared library, loaded into whichever worker the thread running it has. We hang on to the bytes,
+// so if the worker crashed (or this gets run from a different thread) it can just get loaded again
+#[derive(Debug)]
+pub struct SharedLib {
+	lib_bytes : Vec<u8>,
+	// The run's tmp dir, where the lib gets written for as long as it takes the worker to load it
+	lib_dir : PathBuf,
+	// The worker's pid and what it calls this library
+	loaded_in : Cell<Option<(u32, u32)>>
+}
+
+impl SharedLib {
+	// Loads it right away, so anything wrong with it shows up as a load failure instead of when we go to run it
+	pub fn load(lib_bytes : Vec<u8>, lib_dir : &Path) -> Result<SharedLib, LoadError> {
+		let shared_lib = SharedLib { lib_bytes: lib_bytes, lib_dir: lib_dir.to_path_buf(), loaded_in: Cell::new(None) };
+		shared_lib.get_lib_id().map_err(|err| LoadError::BadObjectFile(err))?;
+		return Ok(shared_lib);
+	}
+
+	fn get_lib_id(&self) -> Result<u32, String> {
+		return with_worker(|worker| {
+			let worker_pid = worker.child.id();
+			if let Some((loaded_pid, lib_id)) = self.loaded_in.get() {
+				if loaded_pid == worker_pid {
+					return Ok(Ok(lib_id));
+				}
+			}
+
+			let lib_id = worker.next_lib_id;
+			worker.next_lib_id += 1;
+
+			// dlopen only takes a file, and the name's unique so nothing dlopen has cached by path gets handed back instead
+			let lib_path = self.lib_dir.join(format!("so_worker_{}_{}.so", worker_pid, lib_id));
+			if let Err(err) = std::fs::write(&lib_path, &self.lib_bytes) {
+				return Ok(Err(format!("could not write '{}': {}", lib_path.display(), err)));
+			}
+
+			let mut payload = Vec::<u8>::with_capacity(64);
+			payload.extend_from_slice(&lib_id.to_be_bytes());
+			payload.extend_from_slice(lib_path.to_string_lossy().as_bytes());
+
+			let reply = worker.send(MSG_TYPE_LOAD, &payload);
+			let _ = std::fs::remove_file(&lib_path);
+			let reply = reply?;
+			if reply.is_ok() {
+				self.loaded_in.set(Some((worker_pid, lib_id)));
+			}
+			return Ok(reply.map(|_| lib_id));
+		})?;
+	}
+
+	// Calls do_stuff with the args, and gives back the output_len bytes of output.
+	// If the code crashes the worker, this unwinds with an ExecCrash (resume_unwind, so it skips the panic hook).
+	// By then with_worker has already thrown the worker out, so the next call starts a new one
+	pub fn call(&self, call_kind : ExternalCall, args : &[&[u8]], output_len : usize) -> Vec<u8> {
+		let lib_id = self.get_lib_id().expect("could not load shared lib");
+
+		let mut payload = Vec::<u8>::with_capacity(64 + args.iter().map(|arg| arg.len() + 4).sum::<usize>());
+		payload.extend_from_slice(&lib_id.to_be_bytes());
+		payload.push(call_kind.to_byte());
+		payload.extend_from_slice(&(output_len as u32).to_be_bytes());
+		payload.extend_from_slice(&(args.len() as u32).to_be_bytes());
+		for arg in args.iter() {
+			payload.extend_from_slice(&(arg.len() as u32).to_be_bytes());
+			payload.extend_from_slice(arg);
+		}
+
+		match with_worker(|worker| worker.send(MSG_TYPE_CALL, &payload)) {
+			Ok(output) => {
+				return output.expect("shared lib worker could not run do_stuff");
+			}
+			Err(err) => {
+				print!("SHARED LIB CRASH: {}\n", err);
+				std::panic::resume_unwind(Box::new(ExecCrash(err)));
+			}
+		}
+	}
+}
+
+impl Drop for SharedLib {
+	fn drop(&mut self) {
+		let (loaded_pid, lib_id) = match self.loaded_in.get() {
+			Some(loaded_in) => loaded_in,
+			None => return
+		};
+
+		// Only if it's still the same worker, and try_with since this could be getting dropped while the thread's going away
+		let _ = SHARED_LIB_WORKER.try_with(|worker| {
+			if let Ok(mut worker) = worker.try_borrow_mut() {
+				if let Some(worker) = worker.as_mut().filter(|worker| worker.child.id() == loaded_pid) {
+					let _ = worker.send(MSG_TYPE_UNLOAD, &lib_id.to_be_bytes());
+				}
+			}
+		});
+	}
+}
+
+//---------------------------------------------------------------------------------------
+// The worker's side
+
+#[cfg(unix)]
+struct LoadedLib {
+	handle : *mut libc::c_void,
+	func_ptr : *const libc::c_void
+}
+
+#[cfg(unix)]
+fn get_dl_error() -> String {
+	let err = unsafe { libc::dlerror() };
+	if err.is_null() {
+		return "unknown error".to_string();
+	}
+
+	return unsafe { std::ffi::CStr::from_ptr(err) }.to_string_lossy().to_string();
+}
+
+#[cfg(unix)]
+fn load_lib(lib_path : &[u8]) -> Result<LoadedLib, String> {
+	let lib_path_cstr = std::ffi::CString::new(lib_path).map_err(|_| "lib path has a nul in it".to_string())?;
+	let handle = unsafe { libc::dlopen(lib_path_cstr.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+	if handle.is_null() {
+		return Err(format!("dlopen failed: {}", get_dl_error()));
+	}
+
+	let func_ptr = unsafe { libc::dlsym(handle, "do_stuff\0".as_ptr() as *const libc::c_char) };
+	if func_ptr.is_null() {
+		let err = format!("could not find function 'do_stuff': {}", get_dl_error());
+		unsafe { libc::dlclose(handle); }
+		return Err(err);
+	}
+
+	return Ok(LoadedLib { handle: handle, func_ptr: func_ptr });
+}
+
+#[cfg(unix)]
+fn unload_lib(loaded_lib : LoadedLib) {
+	unsafe { libc::dlclose(loaded_lib.handle); }
+}
+
+#[cfg(not(unix))]
+struct LoadedLib {
+	func_ptr : *const u8
+}
+
+#[cfg(not(unix))]
+fn load_lib(_lib_path : &[u8]) -> Result<LoadedLib, String> {
+	return Err("shared libs are only supported on unix for now".to_string());
+}
+
+#[cfg(not(unix))]
+fn unload_lib(_loaded_lib : LoadedLib) {
+}
+
+fn to_aligned_arg(arg : &[u8]) -> AlignedSlice<u8, WORKER_ARG_ALIGNMENT> {
+	let mut aligned_arg = AlignedSlice::new(arg.len().max(1), &0u8);
+	aligned_arg.as_slice_mut()[..arg.len()].copy_from_slice(arg);
+	return aligned_arg;
+}
+
+fn call_lib(loaded_lib : &LoadedLib, call_kind : ExternalCall, args : &[AlignedSlice<u8, WORKER_ARG_ALIGNMENT>], output_len : usize) -> Result<Vec<u8>, String> {
+	let func_ptr = loaded_lib.func_ptr;
+	let get_arg_ptr = |arg_index : usize| -> Result<*const u8, String> {
+		return args.get(arg_index).map(|arg| arg.as_slice().as_ptr()).ok_or_else(|| format!("{:?} needs at least {} args, only got {}", call_kind, arg_index + 1, args.len()));
+	};
+
+	match call_kind {
+		ExternalCall::U32Io | ExternalCall::U64Io => {
+			let input_ptr = get_arg_ptr(0)?;
+			let val_size = if call_kind == ExternalCall::U32Io { 4 } else { 8 };
+			let count = (args[0].as_slice().len() / val_size) as i32;
+			let mut output = to_aligned_arg(&vec![0u8; output_len]);
+			let func : unsafe extern "C" fn(*const u8, *mut u8, i32) = unsafe { std::mem::transmute(func_ptr) };
+			unsafe { func(input_ptr, output.as_slice_mut().as_mut_ptr(), count); }
+			return Ok(output.as_slice()[..output_len].to_vec());
+		}
+		#[cfg(target_arch = "x86_64")]
+		ExternalCall::Args128i | ExternalCall::Args256i => {
+			use core::arch::x86_64::{__m128i, __m256i};
+
+			let (i_vals_ptr, f_vals_ptr, d_vals_ptr) = (get_arg_ptr(0)?, get_arg_ptr(1)?, get_arg_ptr(2)?);
+			let ret_bytes = if call_kind == ExternalCall::Args128i {
+				let func : unsafe extern "C" fn(*const u8, *const u8, *const u8) -> __m128i = unsafe { std::mem::transmute(func_ptr) };
+				let ret = unsafe { func(i_vals_ptr, f_vals_ptr, d_vals_ptr) };
+				unsafe { std::mem::transmute::<__m128i, [u8; 16]>(ret) }.to_vec()
+			}
+			else {
+				let func : unsafe extern "C" fn(*const u8, *const u8, *const u8) -> __m256i = unsafe { std::mem::transmute(func_ptr) };
+				let ret = unsafe { func(i_vals_ptr, f_vals_ptr, d_vals_ptr) };
+				unsafe { std::mem::transmute::<__m256i, [u8; 32]>(ret) }.to_vec()
+			};
+
+			return Ok(ret_bytes[..output_len.min(ret_bytes.len())].to_vec());
+		}
+		#[cfg(not(target_arch = "x86_64"))]
+		ExternalCall::Args128i | ExternalCall::Args256i => {
+			return Err(format!("{:?} can only be called on x86-64", call_kind));
+		}
+	}
+}
+
+fn handle_worker_msg(loaded_libs : &mut HashMap<u32, LoadedLib>, msg_type : u8, payload : &[u8]) -> Result<Vec<u8>, String> {
+	let mut msg_reader = MsgReader { bytes: payload };
+	let lib_id = msg_reader.read_u32()?;
+
+	match msg_type {
+		MSG_TYPE_LOAD => {
+			let loaded_lib = load_lib(msg_reader.bytes)?;
+			if let Some(old_lib) = loaded_libs.insert(lib_id, loaded_lib) {
+				unload_lib(old_lib);
+			}
+			return Ok(Vec::new());
+		}
+		MSG_TYPE_CALL => {
+			let call_kind_byte = msg_reader.read_u8()?;
+			let call_kind = ExternalCall::from_byte(call_kind_byte).ok_or_else(|| format!("unknown call kind {}", call_kind_byte))?;
+			let output_len = msg_reader.read_u32()? as usize;
+			let num_args = msg_reader.read_u32()?;
+
+			let mut args = Vec::<AlignedSlice<u8, WORKER_ARG_ALIGNMENT>>::with_capacity(num_args as usize);
+			for _ in 0..num_args {
+				let arg_len = msg_reader.read_u32()? as usize;
+				args.push(to_aligned_arg(msg_reader.read_bytes(arg_len)?));
+			}
+
+			let loaded_lib = loaded_libs.get(&lib_id).ok_or_else(|| format!("lib {} isn't loaded", lib_id))?;
+			return call_lib(loaded_lib, call_kind, &args[..], output_len);
+		}
+		MSG_TYPE_UNLOAD => {
+			if let Some(loaded_lib) = loaded_libs.remove(&lib_id) {
+				unload_lib(loaded_lib);
+			}
+			return Ok(Vec::new());
+		}
+		_ => {
+			return Err(format!("unknown message type {}", msg_type));
+		}
+	}
+}
+
+// The 'so-worker' method: answers messages on stdin until the fuzzer closes it
+pub fn run_shared_lib_worker() {
+	let mut stdin = std::io::stdin().lock();
+	let mut stdout = std::io::stdout().lock();
+	let mut loaded_libs = HashMap::<u32, LoadedLib>::new();
+
+	while let Some((msg_type, payload)) = read_msg(&mut stdin).expect("shared lib worker could not read message") {
+		let reply = handle_worker_msg(&mut loaded_libs, msg_type, &payload);
+		let write_result = match reply {
+			Ok(reply) => write_msg(&mut stdout, REPLY_STATUS_OK, &reply),
+			Err(err) => write_msg(&mut stdout, REPLY_STATUS_ERROR, err.as_bytes())
+		};
+
+		if write_result.is_err() {
+			break;
+		}
+	}
+
+	for (_, loaded_lib) in loaded_libs.drain() {
+		unload_lib(loaded_lib);
+	}
+}
+
+#[test]
+fn test_msg_round_trip() {
+	let mut msg_bytes = Vec::<u8>::new();
+	write_msg(&mut msg_bytes, MSG_TYPE_UNLOAD, &7u32.to_be_bytes()).expect("");
+	write_msg(&mut msg_bytes, MSG_TYPE_CALL, &[]).expect("");
+
+	let mut msg_stream = &msg_bytes[..];
+	assert_eq!(read_msg(&mut msg_stream).expect(""), Some((MSG_TYPE_UNLOAD, vec![0, 0, 0, 7])));
+	assert_eq!(read_msg(&mut msg_stream).expect(""), Some((MSG_TYPE_CALL, vec![])));
+	assert_eq!(read_msg(&mut msg_stream).expect(""), None);
+
+	// Not enough there for the lib id
+	let mut loaded_libs = HashMap::<u32, LoadedLib>::new();
+	assert!(handle_worker_msg(&mut loaded_libs, MSG_TYPE_CALL, &[0, 1]).is_err());
+}
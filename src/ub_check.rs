@@ -0,0 +1,235 @@
+// A very small abstract interpreter for the loop/asm generators: each value is tracked as an interval of what it could be,
+// which is enough to say whether a shift amount is in range, whether signed math can overflow, and whether the
+// loop's array accesses stay in bounds. Anything it can't prove safe gets reported, and the case never gets compiled,
+// since two compilers disagreeing on UB isn't a bug in either of them
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntType {
+	I32,
+	U32,
+	U64
+}
+
+impl IntType {
+	pub fn min_val(&self) -> i128 {
+		match self {
+			IntType::I32 => i32::MIN as i128,
+			IntType::U32 | IntType::U64 => 0
+		}
+	}
+
+	pub fn max_val(&self) -> i128 {
+		match self {
+			IntType::I32 => i32::MAX as i128,
+			IntType::U32 => u32::MAX as i128,
+			IntType::U64 => u64::MAX as i128
+		}
+	}
+
+	pub fn num_bits(&self) -> u32 {
+		match self {
+			IntType::I32 | IntType::U32 => 32,
+			IntType::U64 => 64
+		}
+	}
+
+	pub fn is_signed(&self) -> bool {
+		return matches!(self, IntType::I32);
+	}
+}
+
+// Inclusive on both ends. i128 so adding/subtracting two 64-bit values still comes out exact (mul and shl saturate instead),
+// and then apply_type decides what the type does with it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+	pub lo : i128,
+	pub hi : i128
+}
+
+// The smallest all-ones mask that covers val, i.e. the biggest thing or/xor can give back
+fn all_ones_covering(val : i128) -> i128 {
+	let mut mask = 0;
+	while mask < val {
+		mask = (mask << 1) | 1;
+	}
+
+	return mask;
+}
+
+impl Interval {
+	pub fn new(lo : i128, hi : i128) -> Interval {
+		assert!(lo <= hi);
+		return Interval { lo: lo, hi: hi };
+	}
+
+	pub fn exact(val : i128) -> Interval {
+		return Interval::new(val, val);
+	}
+
+	pub fn full(int_type : IntType) -> Interval {
+		return Interval::new(int_type.min_val(), int_type.max_val());
+	}
+
+	pub fn is_within(&self, int_type : IntType) -> bool {
+		return self.lo >= int_type.min_val() && self.hi <= int_type.max_val();
+	}
+
+	// For conditional assignments, where it could be either
+	pub fn join(&self, other : Interval) -> Interval {
+		return Interval::new(std::cmp::min(self.lo, other.lo), std::cmp::max(self.hi, other.hi));
+	}
+
+	pub fn add(&self, other : Interval) -> Interval {
+		return Interval::new(self.lo + other.lo, self.hi + other.hi);
+	}
+
+	pub fn sub(&self, other : Interval) -> Interval {
+		return Interval::new(self.lo - other.hi, self.hi - other.lo);
+	}
+
+	pub fn mul(&self, other : Interval) -> Interval {
+		// Two full u64s can multiply past i128, but saturating still gives the right side of the type's range
+		let corners = [self.lo.saturating_mul(other.lo), self.lo.saturating_mul(other.hi), self.hi.saturating_mul(other.lo), self.hi.saturating_mul(other.hi)];
+		return Interval::new(*corners.iter().min().expect(""), *corners.iter().max().expect(""));
+	}
+
+	// The bitwise ones are only used on unsigned values, so they just need to be non-negative
+	pub fn bit_and(&self, other : Interval) -> Interval {
+		assert!(self.lo >= 0 && other.lo >= 0);
+		return Interval::new(0, std::cmp::min(self.hi, other.hi));
+	}
+
+	pub fn bit_or(&self, other : Interval) -> Interval {
+		assert!(self.lo >= 0 && other.lo >= 0);
+		return Interval::new(std::cmp::max(self.lo, other.lo), all_ones_covering(std::cmp::max(self.hi, other.hi)));
+	}
+
+	pub fn bit_xor(&self, other : Interval) -> Interval {
+		assert!(self.lo >= 0 && other.lo >= 0);
+		return Interval::new(0, all_ones_covering(std::cmp::max(self.hi, other.hi)));
+	}
+
+	// amount has to already be checked with check_shift_amount
+	pub fn shl(&self, amount : Interval) -> Interval {
+		assert!(self.lo >= 0);
+		let saturating_shl = |val : i128, shift : i128| if shift >= val.leading_zeros() as i128 - 1 && val != 0 { i128::MAX } else { val << shift };
+		return Interval::new(saturating_shl(self.lo, amount.lo), saturating_shl(self.hi, amount.hi));
+	}
+
+	pub fn shr(&self, amount : Interval) -> Interval {
+		assert!(self.lo >= 0);
+		return Interval::new(self.lo >> amount.hi, self.hi >> amount.lo);
+	}
+}
+
+// Keeps a list of everything that might be UB, with a description of where it is
+#[derive(Default, Debug)]
+pub struct UBChecker {
+	pub problems : Vec<String>
+}
+
+impl UBChecker {
+	// What C++ does with the exact result of an op in int_type: unsigned wraps (so it could be anything), signed overflow is UB
+	pub fn apply_type(&mut self, result : Interval, int_type : IntType, what : &str) -> Interval {
+		if result.is_within(int_type) {
+			return result;
+		}
+
+		if int_type.is_signed() {
+			self.problems.push(format!("{} can overflow ({:?} in [{}, {}])", what, int_type, result.lo, result.hi));
+		}
+
+		return Interval::full(int_type);
+	}
+
+	// Shifting by the type's width or more (or a negative amount) is UB. Gives back whether it's fine
+	pub fn check_shift_amount(&mut self, amount : Interval, int_type : IntType, what : &str) -> bool {
+		if amount.lo < 0 || amount.hi >= int_type.num_bits() as i128 {
+			self.problems.push(format!("{} shifts a {:?} by [{}, {}]", what, int_type, amount.lo, amount.hi));
+			return false;
+		}
+
+		return true;
+	}
+
+	// Both generators wrap everything in the same loop:
+	//   for (int i = 0; i < count - (stride - 1); i += stride) { ... array[i + offset] ... }
+	// where count is both how many inputs there are and how big outputs is. This checks the loop itself
+	pub fn check_strided_loop_header(&mut self, stride : u32, count : Interval) {
+		assert!(stride > 0);
+		let stride = stride as i128;
+
+		let bound = self.apply_type(count.sub(Interval::exact(stride - 1)), IntType::I32, "loop bound 'count - (stride - 1)'");
+
+		// Inside the loop i < bound, so i <= count - stride, and it's never negative since it starts at 0 and only goes up
+		if bound.hi < 1 {
+			return;
+		}
+		let i = Interval::new(0, bound.hi - 1);
+		self.apply_type(i.add(Interval::exact(stride)), IntType::I32, "loop increment 'i += stride'");
+	}
+
+	// ...and this checks an access at array[i + offset] in that loop. Intervals alone would lose that i and count are tied together,
+	// so this leans on i <= count - stride directly: the last index is count - stride + offset, which is in bounds as long as offset < stride
+	pub fn check_strided_loop_access(&mut self, stride : u32, count : Interval, array_name : &str, offset : u32) {
+		let index_past_count = offset as i128 - stride as i128;
+		if index_past_count >= 0 {
+			self.problems.push(format!("'{}[i + {}]' can go past the end (stride is {})", array_name, offset, stride));
+		}
+
+		let i = Interval::new(0, std::cmp::max(count.hi - stride as i128, 0));
+		self.apply_type(i.add(Interval::exact(offset as i128)), IntType::I32, &format!("index of '{}[i + {}]'", array_name, offset));
+	}
+
+	pub fn is_clean(&self) -> bool {
+		return self.problems.len() == 0;
+	}
+}
+
+// count is an int holding how many values got passed in, so that's all it can be
+pub fn loop_count_interval() -> Interval {
+	return Interval::new(0, i32::MAX as i128);
+}
+
+#[test]
+fn test_ub_checker() {
+	let mut checker = UBChecker::default();
+
+	// Unsigned just wraps, signed doesn't get to
+	let wrapped = checker.apply_type(Interval::exact(u32::MAX as i128).add(Interval::exact(1)), IntType::U32, "u32 add");
+	assert_eq!(wrapped, Interval::full(IntType::U32));
+	assert!(checker.is_clean());
+	checker.apply_type(Interval::exact(i32::MAX as i128).add(Interval::exact(1)), IntType::I32, "i32 add");
+	assert_eq!(checker.problems.len(), 1);
+
+	let mut checker = UBChecker::default();
+	let masked = Interval::full(IntType::U32).bit_and(Interval::exact(0x0f));
+	assert!(checker.check_shift_amount(masked, IntType::U32, "masked shift"));
+	assert!(checker.check_shift_amount(Interval::exact(31), IntType::U64, "constant shift"));
+	assert!(!checker.check_shift_amount(Interval::full(IntType::U32), IntType::U32, "unmasked shift"));
+	assert_eq!(checker.problems.len(), 1);
+
+	let mut checker = UBChecker::default();
+	checker.check_strided_loop_header(50, loop_count_interval());
+	checker.check_strided_loop_access(50, loop_count_interval(), "inputs", 49);
+	assert!(checker.is_clean());
+	checker.check_strided_loop_access(50, loop_count_interval(), "outputs", 50);
+	assert_eq!(checker.problems.len(), 1);
+
+	// A negative count would make the bound itself overflow
+	let mut checker = UBChecker::default();
+	checker.check_strided_loop_header(50, Interval::full(IntType::I32));
+	assert!(!checker.is_clean());
+}
+
+#[test]
+fn test_generated_cases_have_no_ub() {
+	use crate::loop_codegen_fuzzing::LoopCodegenCtx;
+	use crate::inline_asm_codegen_fuzzing::AsmCodegenCtx;
+
+	// Both generators are supposed to be UB-free by construction, so nothing should ever get rejected
+	for seed in 0..200 {
+		assert_eq!(LoopCodegenCtx::new(seed).find_potential_ub(), Vec::<String>::new());
+		assert_eq!(AsmCodegenCtx::new(seed).find_potential_ub(), Vec::<String>::new());
+	}
+}
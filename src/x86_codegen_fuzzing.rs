@@ -4,7 +4,7 @@ use std::convert::TryInto;
 
 use std::fmt::Write;
 
-use crate::codegen_fuzzing::CodegenFuzzer;
+use crate::codegen_fuzzing::{CodegenFuzzer, read_serialized_values};
 use crate::rand::Rand;
 
 use crate::aligned_slice::AlignedSlice;
@@ -32,7 +32,7 @@ pub struct X86CodegenFuzzerThreadInput {
 
 pub struct X86CodegenFuzzer {
 	type_to_intrinsics_map : HashMap<X86SIMDType, Vec<X86SIMDIntrinsic>>,
-	outer_rng : Rand,
+	thread_seed : u64,
 }
 
 const X86_SIMD_ALIGNMENT : usize = 32;
@@ -57,18 +57,37 @@ impl X86CodeFuzzerInputValues {
 			write!(out_str, "{} ", i_val).expect("");
 		}
 		
-		write!(out_str, "{}\n", f_vals.len()).expect("");
+		write!(out_str, "\n{}\n", f_vals.len()).expect("");
 		for f_val in f_vals.iter() {
 			write!(out_str, "{} ", f_val).expect("");
 		}
 		
-		write!(out_str, "{}\n", d_vals.len()).expect("");
+		write!(out_str, "\n{}\n", d_vals.len()).expect("");
 		for d_val in d_vals.iter() {
 			write!(out_str, "{} ", d_val).expect("");
 		}
 		
 		return out_str;
 	}
+
+	pub fn read_from_str(serial : &str) -> Self {
+		let mut lines = serial.lines();
+		let i_vals = read_serialized_values::<i32>(&mut lines);
+		let f_vals = read_serialized_values::<f32>(&mut lines);
+		let d_vals = read_serialized_values::<f64>(&mut lines);
+
+		let mut input = X86CodeFuzzerInputValues {
+			i_vals: AlignedSlice::new(i_vals.len(), &0i32),
+			f_vals: AlignedSlice::new(f_vals.len(), &0f32),
+			d_vals: AlignedSlice::new(d_vals.len(), &0f64)
+		};
+
+		input.i_vals.as_slice_mut().copy_from_slice(&i_vals[..]);
+		input.f_vals.as_slice_mut().copy_from_slice(&f_vals[..]);
+		input.d_vals.as_slice_mut().copy_from_slice(&d_vals[..]);
+
+		return input;
+	}
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -77,8 +96,8 @@ pub enum X86SIMDOutputValues {
 	SIMD256Bit(std::simd::u8x32)
 }
 
-fn generate_random_input_for_program(num_i_vals : usize, num_f_vals : usize, num_d_vals : usize) -> X86CodeFuzzerInputValues {
-	let mut rng = Rand::default();
+fn generate_random_input_for_program(num_i_vals : usize, num_f_vals : usize, num_d_vals : usize, input_seed : u64) -> X86CodeFuzzerInputValues {
+	let mut rng = Rand::new(input_seed);
 
 	let init_i_val = 0i32;
 	let mut i_vals = AlignedSlice::new(num_i_vals, &init_i_val);
@@ -108,6 +127,23 @@ fn generate_random_input_for_program(num_i_vals : usize, num_f_vals : usize, num
 	return X86CodeFuzzerInputValues { i_vals: i_vals, f_vals: f_vals, d_vals: d_vals };
 }
 
+// We only ever execute functions returning integer vectors, so that's all the meta needs to round-trip
+fn encode_return_type(return_type : X86SIMDType) -> &'static str {
+	match return_type {
+		X86SIMDType::M128i(_) => "m128i",
+		X86SIMDType::M256i(_) => "m256i",
+		_ => panic!("bad return type {:?}", return_type)
+	}
+}
+
+fn decode_return_type(return_type : &str) -> X86SIMDType {
+	match return_type {
+		"m128i" => X86SIMDType::M128i(X86SIMDEType::UInt8),
+		"m256i" => X86SIMDType::M256i(X86SIMDEType::UInt8),
+		_ => panic!("bad return type '{}'", return_type)
+	}
+}
+
 fn minimize_gen_x86_code<F: Fn(&X86CodegenFuzzer, &X86SIMDCodegenCtx) -> bool>(fuzzer: &X86CodegenFuzzer, codegen_ctx : &X86SIMDCodegenCtx, minim_check: F) -> X86SIMDCodegenCtx {
 	let mut best_ctx = codegen_ctx.clone();
 
@@ -187,13 +223,17 @@ impl CodegenFuzzer<X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuz
 	fn new_fuzzer_state(input_data : Self::ThreadInput) -> X86CodegenFuzzer {
 		X86CodegenFuzzer {
 			type_to_intrinsics_map: input_data.type_to_intrinsics_map,
-			outer_rng: Rand::new(input_data.thread_seed)
+			thread_seed: input_data.thread_seed
 		}
 	}
 
+	fn get_thread_seed(&self) -> u64 {
+		return self.thread_seed;
+	}
+
 	// This generates some context struct that's basically analagous to the AST
-	fn generate_ctx(&mut self) -> Self::CodegenCtx {
-		let mut codegen_ctx = Self::CodegenCtx::new(self.outer_rng.rand_u64());
+	fn generate_ctx(&mut self, ctx_seed : u64) -> Self::CodegenCtx {
+		let mut codegen_ctx = Self::CodegenCtx::new(ctx_seed);
 		generate_x86_codegen_ctx(&mut codegen_ctx, &self.type_to_intrinsics_map);
 		return codegen_ctx;
 	}
@@ -210,8 +250,8 @@ impl CodegenFuzzer<X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuz
 		return (cpp_code, meta_data);
 	}
 
-	fn generate_random_input(&self, code_meta : &Self::CodeMeta) -> Self::FuzzerInput {
-		return generate_random_input_for_program(code_meta.num_i_vals, code_meta.num_f_vals, code_meta.num_d_vals);
+	fn generate_random_input(&self, code_meta : &Self::CodeMeta, input_seed : u64) -> Self::FuzzerInput {
+		return generate_random_input_for_program(code_meta.num_i_vals, code_meta.num_f_vals, code_meta.num_d_vals, input_seed);
 	}
 
 	// uhh.....idk
@@ -261,17 +301,28 @@ impl CodegenFuzzer<X86CodegenFuzzerThreadInput, X86SIMDCodegenCtx, X86CodegenFuz
 		input.write_to_str()
 	}
 
-	fn read_input_from_string(&self, _serial : &str) -> Self::FuzzerInput {
-		todo!()
+	fn read_input_from_string(&self, serial : &str) -> Self::FuzzerInput {
+		X86CodeFuzzerInputValues::read_from_str(serial)
 	}
 
 	fn save_meta_to_string(&self, meta: &Self::CodeMeta) -> String {
-		// TODO: More elegant solution, but for now just make sure there's something
-		format!("{:?}", meta)
+		format!("{} {} {} {}", meta.num_i_vals, meta.num_f_vals, meta.num_d_vals, encode_return_type(meta.return_type))
 	}
 
-	fn read_meta_from_string(&self, _serial: &str) -> Self::CodeMeta {
-		todo!()
+	fn read_meta_from_string(&self, serial: &str) -> Self::CodeMeta {
+		let mut parts = serial.trim().split(' ');
+
+		let num_i_vals = parts.next().unwrap();
+		let num_f_vals = parts.next().unwrap();
+		let num_d_vals = parts.next().unwrap();
+		let return_type = parts.next().unwrap();
+
+		return X86CodegenFuzzerCodeMetadata {
+			num_i_vals : num_i_vals.parse::<usize>().unwrap(),
+			num_f_vals : num_f_vals.parse::<usize>().unwrap(),
+			num_d_vals : num_d_vals.parse::<usize>().unwrap(),
+			return_type : decode_return_type(return_type)
+		};
 	}
 
 	fn num_inputs_per_codegen(&self) -> u32 {